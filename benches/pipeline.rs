@@ -0,0 +1,218 @@
+// Copyright (c) 2024-2026 Fred Clausen
+//
+// Licensed under the MIT license: https://opensource.org/licenses/MIT
+// Permission is granted to use, copy, modify, and redistribute the work.
+// Full license information available in the project LICENSE file.
+
+//! Throughput of the bridge, and heap allocations per message, for one
+//! input feeding two outputs.
+//!
+//! Run with `cargo bench --bench pipeline`. An optional argument sets the
+//! number of messages per run.
+
+use acars_bridge::{Bridge, Message, memory};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+
+/// The system allocator, counting every allocation and reallocation.
+struct Counting;
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+// SAFETY: every call is passed straight on to `System`.
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+const DEFAULT_COUNT: u32 = 200_000;
+
+/// Messages sent before measuring, so connections and buffers are set up.
+const WARMUP: u32 = 1_000;
+
+/// An `acarsdec` message of typical size, numbered so no two are alike.
+fn message(seq: u32) -> String {
+    format!(
+        r#"{{"timestamp":1718000000.{seq:06},"station_id":"BENCH","channel":2,"freq":131.550,"level":-21.4,"error":0,"mode":"2","label":"H1","block_id":"5","ack":false,"tail":".N123AB","flight":"AB1234","msgno":"M{seq:05}","text":"- #M1BPOSN47123W122456,KSEA,123456,350,KPDX,123789,-45,280123,ABCDEF0123456789 REPORT {seq} CONTINUES WITH FREE TEXT FOR A FEW HUNDRED BYTES MORE OF PAYLOAD AS SEEN ON A BUSY FREQUENCY","end":true,"app":{{"name":"acarsdec","ver":"3.7"}}}}"#
+    )
+}
+
+struct Run {
+    messages: u32,
+    elapsed: Duration,
+    allocations: u64,
+}
+
+impl Run {
+    fn report(&self, name: &str) {
+        let rate = f64::from(self.messages) / self.elapsed.as_secs_f64();
+        // In hundredths, to print with two decimal places.
+        let per_message = self.allocations * 100 / u64::from(self.messages);
+        println!(
+            "{name:<24} {:>8} messages  {rate:>10.0} msg/s  {:>3}.{:02} allocations/message",
+            self.messages,
+            per_message / 100,
+            per_message % 100
+        );
+    }
+}
+
+/// Time `count` messages through, counting allocations from the first
+/// `send` until both outputs have seen the last message.
+async fn measure(
+    count: u32,
+    send: impl Future<Output = ()>,
+    delivered: impl Future<Output = ()>,
+) -> Run {
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let started = Instant::now();
+    send.await;
+    delivered.await;
+    Run {
+        messages: count,
+        elapsed: started.elapsed(),
+        allocations: ALLOCATIONS.load(Ordering::Relaxed) - allocations,
+    }
+}
+
+/// Memory input to two memory outputs: the pipeline alone, no sockets.
+async fn memory_to_memory(count: u32) -> Run {
+    let input = memory::create("bench-in", 1024);
+    let outputs = [
+        memory::create("bench-out-1", 1024),
+        memory::create("bench-out-2", 1024),
+    ];
+    let bridge = Bridge::new()
+        .input("memory://bench-in")
+        .output("memory://bench-out-1")
+        .output("memory://bench-out-2")
+        .start()
+        .expect("starting the bridge");
+
+    let total = WARMUP + count;
+    // Made up front, so making them isn't measured.
+    let mut messages = (0..total)
+        .map(|seq| Message::from(message(seq)))
+        .collect::<Vec<_>>();
+    let readers = outputs.map(|output| {
+        tokio::spawn(async move {
+            for _ in 0..total {
+                drop(output.recv().await);
+            }
+        })
+    });
+    for message in messages.drain(..WARMUP as usize) {
+        input.send(message).await;
+    }
+
+    let [first, second] = readers;
+    let run = measure(
+        count,
+        async {
+            for message in messages {
+                input.send(message).await;
+            }
+        },
+        async {
+            first.await.expect("reader");
+            second.await.expect("reader");
+        },
+    )
+    .await;
+    bridge.shutdown().await.expect("shutting down");
+    run
+}
+
+/// Read lines from the bridge's connection until `total` have arrived.
+fn read_lines(listener: TcpListener, total: u32) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.expect("accepting the bridge");
+        let mut buf = vec![0; 64 * 1024];
+        let mut lines = 0;
+        while lines < total as usize {
+            let read = stream.read(&mut buf).await.expect("reading");
+            assert!(read > 0, "the bridge closed the connection");
+            lines += buf[..read].split(|b| *b == b'\n').skip(1).count();
+        }
+    })
+}
+
+/// TCP input to two TCP outputs, through loopback sockets.
+async fn tcp_to_tcp(count: u32) -> Run {
+    let total = WARMUP + count;
+    let feed = TcpListener::bind("127.0.0.1:0").await.expect("binding");
+    let sinks = [
+        TcpListener::bind("127.0.0.1:0").await.expect("binding"),
+        TcpListener::bind("127.0.0.1:0").await.expect("binding"),
+    ];
+    let url =
+        |listener: &TcpListener| format!("tcp://{}", listener.local_addr().expect("local address"));
+    let bridge = Bridge::new()
+        .input(url(&feed))
+        .output(url(&sinks[0]))
+        .output(url(&sinks[1]))
+        .start()
+        .expect("starting the bridge");
+
+    let mut lines = Vec::new();
+    for seq in 0..total {
+        lines.extend_from_slice(message(seq).as_bytes());
+        lines.push(b'\n');
+    }
+    let split = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, b)| **b == b'\n')
+        .nth(WARMUP as usize - 1)
+        .map_or(0, |(at, _)| at + 1);
+    let [first, second] = sinks.map(|sink| read_lines(sink, total));
+    let (mut stream, _) = feed.accept().await.expect("accepting the bridge");
+    stream
+        .write_all(&lines[..split])
+        .await
+        .expect("writing the warmup");
+    // Let the warmup through before measuring.
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let run = measure(
+        count,
+        async {
+            stream.write_all(&lines[split..]).await.expect("writing");
+        },
+        async {
+            first.await.expect("reader");
+            second.await.expect("reader");
+        },
+    )
+    .await;
+    bridge.shutdown().await.expect("shutting down");
+    run
+}
+
+#[tokio::main]
+async fn main() {
+    let count = std::env::args()
+        .skip(1)
+        .find_map(|arg| arg.parse().ok())
+        .unwrap_or(DEFAULT_COUNT);
+    memory_to_memory(count).await.report("memory -> 2 x memory");
+    tcp_to_tcp(count).await.report("tcp -> 2 x tcp");
+}
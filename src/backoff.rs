@@ -0,0 +1,280 @@
+// Copyright (c) 2024-2026 Fred Clausen
+//
+// Licensed under the MIT license: https://opensource.org/licenses/MIT
+// Permission is granted to use, copy, modify, and redistribute the work.
+// Full license information available in the project LICENSE file.
+
+//! How long to wait between attempts to start a server or reconnect one.
+//!
+//! Each side of the bridge has one policy, used both by its supervisor
+//! between restarts and by the `tcp` protocol between reconnects.
+
+use anyhow::{Error, Result};
+use std::time::Duration;
+
+/// The longest wait a policy can ask for. Longer `initial` and `max`
+/// settings are cut down to it.
+pub const LONGEST_WAIT: Duration = Duration::from_hours(24);
+
+/// A retry schedule: `initial`, then each wait `multiplier` times the last,
+/// up to `max`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BackoffPolicy {
+    pub initial: Duration,
+    pub max: Duration,
+    pub multiplier: f64,
+    /// Each wait is made up to this fraction, from 0 to 1, longer or shorter
+    /// at random, so peers restarted together don't retry in step.
+    pub jitter: f64,
+    /// A supervisor whose server ran at least this long before exiting
+    /// starts over at `initial` with all its attempts.
+    pub reset_after: Duration,
+    /// Attempts in a row before giving up. `None` retries forever.
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for BackoffPolicy {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_secs(1),
+            max: Duration::from_mins(1),
+            multiplier: 2.0,
+            jitter: 0.0,
+            reset_after: Duration::from_mins(1),
+            max_attempts: None,
+        }
+    }
+}
+
+impl BackoffPolicy {
+    /// Parse a policy from comma-separated `key=value` settings, e.g.
+    /// `initial=0.5,max=30,jitter=0.2`. Times are in seconds; settings left
+    /// out keep their defaults, and `initial` and `max` are capped at
+    /// `LONGEST_WAIT`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for an unknown key, a value that doesn't parse, or a
+    /// policy that makes no sense, such as `max` below `initial`.
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut policy = Self::default();
+        for setting in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let (key, value) = setting
+                .split_once('=')
+                .ok_or_else(|| Error::msg(format!("Expected key=value, got {setting}")))?;
+            let (key, value) = (key.trim(), value.trim());
+            let number = || {
+                value
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|number| number.is_finite() && *number >= 0.0)
+                    .ok_or_else(|| Error::msg(format!("Invalid {key}: {value}")))
+            };
+            let seconds = || {
+                Duration::try_from_secs_f64(number()?)
+                    .map_err(|_| Error::msg(format!("Invalid {key}: {value}")))
+            };
+            match key {
+                "initial" => policy.initial = seconds()?,
+                "max" => policy.max = seconds()?,
+                "multiplier" => policy.multiplier = number()?,
+                "jitter" => policy.jitter = number()?,
+                "reset-after" => policy.reset_after = seconds()?,
+                "max-attempts" => {
+                    let attempts = value
+                        .parse::<u32>()
+                        .map_err(|_| Error::msg(format!("Invalid {key}: {value}")))?;
+                    policy.max_attempts = (attempts > 0).then_some(attempts);
+                }
+                _ => {
+                    return Err(Error::msg(format!(
+                        "Unknown backoff setting {key}; expected initial, max, multiplier, jitter, reset-after or max-attempts"
+                    )));
+                }
+            }
+        }
+        policy.initial = policy.initial.min(LONGEST_WAIT);
+        policy.max = policy.max.min(LONGEST_WAIT);
+        if policy.initial.is_zero() {
+            return Err(Error::msg("Backoff initial must be more than 0"));
+        }
+        if policy.max < policy.initial {
+            return Err(Error::msg("Backoff max can't be less than initial"));
+        }
+        if policy.multiplier < 1.0 {
+            return Err(Error::msg("Backoff multiplier can't be less than 1"));
+        }
+        if policy.jitter > 1.0 {
+            return Err(Error::msg("Backoff jitter must be between 0 and 1"));
+        }
+        Ok(policy)
+    }
+
+    /// The waits before each attempt in turn, ending after `max_attempts`.
+    #[must_use]
+    pub fn delays(&self) -> Backoff {
+        Backoff::new(self.clone())
+    }
+}
+
+/// Where a supervisor or connection is in its policy's schedule.
+pub struct Backoff {
+    policy: BackoffPolicy,
+    next: Duration,
+    attempts: u32,
+    /// `SplitMix64` state for the jitter.
+    state: u64,
+}
+
+impl Backoff {
+    #[must_use]
+    pub fn new(policy: BackoffPolicy) -> Self {
+        Self {
+            next: policy.initial,
+            attempts: 0,
+            state: crate::generate::random_seed(),
+            policy,
+        }
+    }
+
+    /// The wait before restarting a server that ran for `ran_for`, or `None`
+    /// once the attempts are used up. A long enough run starts the schedule
+    /// over.
+    pub fn after(&mut self, ran_for: Duration) -> Option<Duration> {
+        if ran_for >= self.policy.reset_after {
+            self.next = self.policy.initial;
+            self.attempts = 0;
+        }
+        self.next()
+    }
+
+    /// A value in `0.0..1.0`.
+    fn unit(&mut self) -> f64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        f64::from(u32::try_from(z >> 32).unwrap_or_default()) / (f64::from(u32::MAX) + 1.0)
+    }
+}
+
+impl Iterator for Backoff {
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Duration> {
+        if self
+            .policy
+            .max_attempts
+            .is_some_and(|max| self.attempts >= max)
+        {
+            return None;
+        }
+        self.attempts += 1;
+        let delay = self.next;
+        self.next = Duration::try_from_secs_f64(delay.as_secs_f64() * self.policy.multiplier)
+            .map_or(self.policy.max, |next| next.min(self.policy.max));
+        let jitter = self.policy.jitter * 2.0f64.mul_add(self.unit(), -1.0);
+        Some(
+            Duration::try_from_secs_f64(delay.as_secs_f64() * (1.0 + jitter))
+                .unwrap_or(self.policy.max),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_keeps_defaults_for_what_is_left_out() {
+        let policy = BackoffPolicy::parse(" initial=0.5 , max-attempts=3,").expect("valid");
+        assert_eq!(policy.initial, Duration::from_millis(500));
+        assert_eq!(policy.max_attempts, Some(3));
+        assert_eq!(policy.max, BackoffPolicy::default().max);
+        assert_eq!(
+            BackoffPolicy::parse("").expect("valid"),
+            BackoffPolicy::default()
+        );
+        assert_eq!(
+            BackoffPolicy::parse("max-attempts=0")
+                .expect("valid")
+                .max_attempts,
+            None
+        );
+    }
+
+    #[test]
+    fn parse_rejects_bad_settings() {
+        for spec in [
+            "initial",
+            "initial=",
+            "initial=soon",
+            "initial=-1",
+            "initial=NaN",
+            "initial=inf",
+            "initial=0",
+            "initial=10,max=5",
+            "multiplier=0.5",
+            "jitter=1.5",
+            "max-attempts=-1",
+            "max-attempts=1.5",
+            "delay=5",
+        ] {
+            assert!(BackoffPolicy::parse(spec).is_err(), "{spec} was accepted");
+        }
+    }
+
+    #[test]
+    fn parse_caps_long_waits() {
+        let policy = BackoffPolicy::parse("initial=1e18,max=1e19").expect("valid");
+        assert_eq!(policy.initial, LONGEST_WAIT);
+        assert_eq!(policy.max, LONGEST_WAIT);
+    }
+
+    #[test]
+    fn delays_grow_to_max_and_stop_after_max_attempts() {
+        let policy =
+            BackoffPolicy::parse("initial=1,max=5,multiplier=2,max-attempts=5").expect("valid");
+        let delays = policy
+            .delays()
+            .map(|delay| delay.as_secs())
+            .collect::<Vec<_>>();
+        assert_eq!(delays, [1, 2, 4, 5, 5]);
+    }
+
+    #[test]
+    fn jitter_stays_in_bounds_at_the_longest_wait() {
+        let policy = BackoffPolicy {
+            initial: LONGEST_WAIT,
+            max: LONGEST_WAIT,
+            jitter: 1.0,
+            ..BackoffPolicy::default()
+        };
+        for delay in policy.delays().take(100) {
+            assert!(delay <= LONGEST_WAIT * 2, "{delay:?}");
+        }
+        let policy = BackoffPolicy {
+            initial: Duration::MAX,
+            max: Duration::MAX,
+            jitter: 1.0,
+            ..BackoffPolicy::default()
+        };
+        // Jitter on a wait this long used to overflow and panic.
+        assert_eq!(policy.delays().take(100).count(), 100);
+    }
+
+    #[test]
+    fn a_long_run_starts_the_schedule_over() {
+        let policy =
+            BackoffPolicy::parse("initial=1,max=60,reset-after=10,max-attempts=2").expect("valid");
+        let mut backoff = policy.delays();
+        assert_eq!(backoff.after(Duration::ZERO), Some(Duration::from_secs(1)));
+        assert_eq!(backoff.after(Duration::ZERO), Some(Duration::from_secs(2)));
+        assert_eq!(backoff.after(Duration::ZERO), None);
+        assert_eq!(
+            backoff.after(Duration::from_secs(10)),
+            Some(Duration::from_secs(1))
+        );
+    }
+}
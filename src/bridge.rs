@@ -0,0 +1,565 @@
+// Copyright (c) 2024-2026 Fred Clausen
+//
+// Licensed under the MIT license: https://opensource.org/licenses/MIT
+// Permission is granted to use, copy, modify, and redistribute the work.
+// Full license information available in the project LICENSE file.
+
+//! Running the bridge from another program.
+//!
+//! `Bridge` puts together any number of inputs and outputs, each under its
+//! own supervisor, the way the `acars-bridge` binary does for one of each.
+//! Every input feeds one bridge channel; a fan-out task applies the filters
+//! and copies each message that passes to every output. A full output holds
+//! the fan-out back only for the stall timeout, after which it misses
+//! messages until it catches up rather than holding the others back too.
+//! Shutdown drains in
+//! the binary's order: the inputs stop, the outputs send what is queued,
+//! then the stats stop.
+//!
+//! ```no_run
+//! use acars_bridge::{Bridge, MessageFilter};
+//!
+//! # async fn example() -> anyhow::Result<()> {
+//! let bridge = Bridge::new()
+//!     .input("udp://0.0.0.0:5550")
+//!     .output("zmq+bind://0.0.0.0:45555")
+//!     .output("file:///srv/acars")
+//!     .filter(MessageFilter::parse(&["type=hfdl"])?)
+//!     .stats_sink(|event| println!("{event:?}"))
+//!     .start()?;
+//! // ... later, e.g. when the service shuts down:
+//! bridge.shutdown().await
+//! # }
+//! ```
+
+use anyhow::{Error, Result};
+use futures::future::join_all;
+use std::time::Duration;
+use tokio::sync::mpsc::error::{SendTimeoutError, TrySendError};
+use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use crate::backoff::BackoffPolicy;
+use crate::endpoint::Endpoint;
+use crate::filter::MessageFilter;
+use crate::message::Message;
+use crate::pipeline::{InputSinks, Supervised};
+use crate::serverconfig::{InputServer, OutputServer, TransportOptions};
+use crate::stats::{Stats, StatsCounters, StatsEvent, StatsSink};
+use crate::status::{StatusReporter, SupervisorStatus};
+use crate::supervisor::Supervisor;
+use crate::transport::{InputServerTransport, OutputServerTransport, Transport};
+
+/// A test every message must pass to reach the outputs.
+type Filter = Box<dyn Fn(&str) -> bool + Send + Sync>;
+
+/// One input or output, as it was added.
+enum Side {
+    /// An endpoint URL, parsed at `start` with the bridge's options.
+    Url(String),
+    Transport(&'static dyn Transport, String, u16),
+}
+
+/// Builds a bridge. See the module docs.
+pub struct Bridge {
+    inputs: Vec<Side>,
+    outputs: Vec<Side>,
+    filters: Vec<Filter>,
+    sinks: Vec<StatsSink>,
+    options: TransportOptions,
+    channel_capacity: usize,
+    stat_interval: Option<u64>,
+    drain_timeout: Option<Duration>,
+    stall_timeout: Option<Duration>,
+}
+
+impl Default for Bridge {
+    fn default() -> Self {
+        Self {
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            filters: Vec::new(),
+            sinks: Vec::new(),
+            options: TransportOptions::default(),
+            channel_capacity: 1024,
+            stat_interval: None,
+            drain_timeout: Some(Duration::from_secs(30)),
+            stall_timeout: Some(Duration::from_secs(5)),
+        }
+    }
+}
+
+impl Bridge {
+    /// A bridge with nothing added, and the binary's defaults for
+    /// everything else.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Receive from the endpoint `url` gives, e.g. `udp://0.0.0.0:5550`, in
+    /// the syntax of `--source`.
+    #[must_use]
+    pub fn input(mut self, url: impl Into<String>) -> Self {
+        self.inputs.push(Side::Url(url.into()));
+        self
+    }
+
+    /// Send to the endpoint `url` gives, e.g. `tcp://router:5550`.
+    #[must_use]
+    pub fn output(mut self, url: impl Into<String>) -> Self {
+        self.outputs.push(Side::Url(url.into()));
+        self
+    }
+
+    /// Receive with `transport`, which need not be in the registry.
+    #[must_use]
+    pub fn input_transport(
+        mut self,
+        transport: &'static dyn Transport,
+        host: impl Into<String>,
+        port: u16,
+    ) -> Self {
+        self.inputs
+            .push(Side::Transport(transport, host.into(), port));
+        self
+    }
+
+    /// Send with `transport`, which need not be in the registry.
+    #[must_use]
+    pub fn output_transport(
+        mut self,
+        transport: &'static dyn Transport,
+        host: impl Into<String>,
+        port: u16,
+    ) -> Self {
+        self.outputs
+            .push(Side::Transport(transport, host.into(), port));
+        self
+    }
+
+    /// Receive with an `InputServer` of the caller's own, built with `host`
+    /// and `port`.
+    #[must_use]
+    pub fn input_server<S: InputServer + Send + 'static>(
+        self,
+        host: impl Into<String>,
+        port: u16,
+    ) -> Self {
+        // The transport has no fields, so leaking it allocates nothing.
+        let transport = Box::leak(Box::new(InputServerTransport::<S>::default()));
+        self.input_transport(transport, host, port)
+    }
+
+    /// Send with an `OutputServer` of the caller's own, built with `host`
+    /// and `port`.
+    #[must_use]
+    pub fn output_server<S: OutputServer + Send + 'static>(
+        self,
+        host: impl Into<String>,
+        port: u16,
+    ) -> Self {
+        let transport = Box::leak(Box::new(OutputServerTransport::<S>::default()));
+        self.output_transport(transport, host, port)
+    }
+
+    /// Forward only messages that match `filter`. With several filters a
+    /// message must match them all.
+    #[must_use]
+    pub fn filter(self, filter: MessageFilter) -> Self {
+        self.filter_with(move |message| filter.matches(message))
+    }
+
+    /// Forward only messages for which `filter` returns true.
+    #[must_use]
+    pub fn filter_with(mut self, filter: impl Fn(&str) -> bool + Send + Sync + 'static) -> Self {
+        self.filters.push(Box::new(filter));
+        self
+    }
+
+    /// Hand every stats event to `sink`. It runs on the stats task, so it
+    /// must not block.
+    #[must_use]
+    pub fn stats_sink(mut self, sink: impl FnMut(StatsEvent) + Send + 'static) -> Self {
+        self.sinks.push(Box::new(sink));
+        self
+    }
+
+    /// Log the counts every `minutes`, as the binary does. Off by default.
+    #[must_use]
+    pub const fn log_stats(mut self, minutes: u64) -> Self {
+        self.stat_interval = Some(minutes);
+        self
+    }
+
+    /// The settings every server runs with, which those in an endpoint URL
+    /// add to.
+    #[must_use]
+    pub fn options(mut self, options: TransportOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// How many messages the bridge channel, and each output's, holds.
+    #[must_use]
+    pub const fn channel_capacity(mut self, capacity: usize) -> Self {
+        self.channel_capacity = capacity;
+        self
+    }
+
+    /// How long the outputs get to send what is queued at shutdown, or
+    /// `None` to wait for them however long it takes. What they don't send
+    /// in time is dropped and counted in the log.
+    #[must_use]
+    pub const fn drain_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.drain_timeout = timeout;
+        self
+    }
+
+    /// How long the fan-out waits for an output whose channel is full before
+    /// it drops the message for that output, so that one slow output doesn't
+    /// hold the rest back. Until the output takes a message again, messages
+    /// for it are dropped without waiting, and how many is logged once it
+    /// does. `None` waits however long it takes, so a stalled output holds
+    /// back every output and, in turn, the inputs.
+    #[must_use]
+    pub const fn stall_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.stall_timeout = timeout;
+        self
+    }
+
+    /// Start every input and output. Must be called within a Tokio runtime.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is no input, the channel capacity is 0, or
+    /// an endpoint doesn't parse or can't be used on its side.
+    pub fn start(self) -> Result<BridgeHandle> {
+        if self.inputs.is_empty() {
+            return Err(Error::msg("A bridge needs at least one input"));
+        }
+        if self.channel_capacity == 0 {
+            return Err(Error::msg("The channel capacity must be at least 1"));
+        }
+        let inputs = self
+            .inputs
+            .into_iter()
+            .map(|side| endpoint(side, true, &self.options))
+            .collect::<Result<Vec<_>>>()?;
+        let outputs = self
+            .outputs
+            .into_iter()
+            .map(|side| endpoint(side, false, &self.options))
+            .collect::<Result<Vec<_>>>()?;
+
+        let (bridge_sender, bridge_receiver) = mpsc::channel(self.channel_capacity);
+        let (stats_sender, stats_receiver) = mpsc::channel(self.channel_capacity);
+        let mut stats = Stats::new(stats_receiver);
+        for sink in self.sinks {
+            stats.sink(sink);
+        }
+        let counters = stats.counters();
+
+        let cancel = CancellationToken::new();
+        let gave_up = CancellationToken::new();
+        let supervisor =
+            |label: String, policy: &Option<BackoffPolicy>, status: &StatusReporter| Supervisor {
+                label,
+                policy: policy.clone().unwrap_or_default(),
+                status: status.clone(),
+                stop: cancel.child_token(),
+                shutdown: cancel.clone(),
+                gave_up: gave_up.clone(),
+            };
+
+        let sinks = InputSinks {
+            output: Some(bridge_sender),
+            stats: stats_sender,
+            recorder: None,
+        };
+        let mut input_statuses = Vec::new();
+        let mut running_inputs = Vec::new();
+        for input in inputs {
+            let reporter = StatusReporter::default();
+            let label = input.label();
+            info!("Creating input server: {label}");
+            reporter.endpoint(label.clone());
+            let policy = &input.options.source_backoff;
+            let supervisor = supervisor(format!("input/{label}"), policy, &reporter);
+            running_inputs.push(crate::pipeline::supervise_input(
+                input,
+                sinks.clone(),
+                supervisor,
+            ));
+            input_statuses.push(reporter.subscribe());
+        }
+
+        let mut output_statuses = Vec::new();
+        let mut running_outputs = Vec::new();
+        let mut fan_outputs = Vec::new();
+        for output in outputs {
+            let reporter = StatusReporter::default();
+            let label = output.label();
+            info!("Creating output server: {label}");
+            reporter.endpoint(label.clone());
+            let policy = &output.options.destination_backoff;
+            let mut supervisor = supervisor(format!("output/{label}"), policy, &reporter);
+            // Only the drain timeout stops an output mid-run; the shutdown
+            // lets it send what is queued first.
+            supervisor.stop = CancellationToken::new();
+            let (sender, receiver) = mpsc::channel(self.channel_capacity);
+            fan_outputs.push(FanOutput::new(label, sender));
+            running_outputs.push(crate::pipeline::supervise_output(
+                output, receiver, supervisor,
+            ));
+            output_statuses.push(reporter.subscribe());
+        }
+        if running_outputs.is_empty() {
+            info!("No output added; messages will only be counted");
+        }
+        let fan_out = tokio::spawn(fan_out(
+            bridge_receiver,
+            fan_outputs,
+            self.filters,
+            self.stall_timeout,
+        ));
+        stats.run(self.stat_interval);
+
+        let running = Running {
+            sinks,
+            inputs: running_inputs,
+            outputs: running_outputs,
+            fan_out,
+            cancel: cancel.clone(),
+            gave_up,
+            drain_timeout: self.drain_timeout,
+        };
+        Ok(BridgeHandle {
+            cancel,
+            task: tokio::spawn(running.run()),
+            counters,
+            inputs: input_statuses,
+            outputs: output_statuses,
+        })
+    }
+}
+
+fn endpoint(side: Side, input: bool, options: &TransportOptions) -> Result<Endpoint> {
+    match side {
+        Side::Url(url) => Endpoint::parse(&url, input, options),
+        Side::Transport(transport, host, port) => {
+            if (input && !transport.supports_input()) || (!input && !transport.supports_output()) {
+                return Err(Error::msg(format!(
+                    "{} cannot be used as an {}",
+                    transport.name(),
+                    if input { "input" } else { "output" }
+                )));
+            }
+            Ok(Endpoint {
+                transport,
+                scheme: transport.name().to_string(),
+                host,
+                port,
+                options: options.clone(),
+            })
+        }
+    }
+}
+
+/// Copy each message that passes `filters` from the bridge channel to every
+/// output at once, until the channel closes at shutdown. Dropping the output
+/// Senders then lets each output finish.
+async fn fan_out(
+    mut receiver: Receiver<Message>,
+    mut outputs: Vec<FanOutput>,
+    filters: Vec<Filter>,
+    stall_timeout: Option<Duration>,
+) {
+    while let Some(message) = receiver.recv().await {
+        if !filters.iter().all(|filter| filter(&message)) {
+            continue;
+        }
+        join_all(
+            outputs
+                .iter_mut()
+                .map(|output| output.send(message.clone(), stall_timeout)),
+        )
+        .await;
+    }
+}
+
+/// One output, as the fan-out sends to it.
+struct FanOutput {
+    label: String,
+    sender: Sender<Message>,
+    /// Messages dropped for it since it last took one.
+    dropped: u64,
+}
+
+impl FanOutput {
+    const fn new(label: String, sender: Sender<Message>) -> Self {
+        Self {
+            label,
+            sender,
+            dropped: 0,
+        }
+    }
+
+    /// Hand `message` to the output, waiting up to `stall_timeout` for room
+    /// unless it is already missing messages.
+    async fn send(&mut self, message: Message, stall_timeout: Option<Duration>) {
+        // An output only lets go of its receiver once it has stopped for
+        // good, and then there's nobody left to send to.
+        let full = match (self.dropped, stall_timeout) {
+            (0, None) => {
+                let _ = self.sender.send(message).await;
+                false
+            }
+            (0, Some(timeout)) => matches!(
+                self.sender.send_timeout(message, timeout).await,
+                Err(SendTimeoutError::Timeout(_))
+            ),
+            _ => matches!(self.sender.try_send(message), Err(TrySendError::Full(_))),
+        };
+        if full {
+            if self.dropped == 0 {
+                warn!(
+                    "Output {} is stalled; dropping messages for it until it catches up",
+                    self.label
+                );
+            }
+            self.dropped += 1;
+        } else if self.dropped > 0 {
+            warn!(
+                "Output {} caught up after {} message(s) were dropped for it",
+                self.label, self.dropped
+            );
+            self.dropped = 0;
+        }
+    }
+}
+
+/// Everything a started bridge owns until it has shut down.
+struct Running {
+    /// The master sinks, which keep the channels open while inputs restart.
+    sinks: InputSinks,
+    inputs: Vec<Supervised<()>>,
+    outputs: Vec<Supervised<Receiver<Message>>>,
+    fan_out: JoinHandle<()>,
+    cancel: CancellationToken,
+    gave_up: CancellationToken,
+    drain_timeout: Option<Duration>,
+}
+
+impl Running {
+    /// Run until shut down, a supervisor gives up, or every input ends at
+    /// EOF, then drain.
+    async fn run(self) -> Result<()> {
+        let Self {
+            sinks,
+            inputs,
+            outputs,
+            fan_out,
+            cancel,
+            gave_up,
+            drain_timeout,
+        } = self;
+        let inputs = join_all(inputs.into_iter().map(|input| input.handle));
+        tokio::pin!(inputs);
+        let finished = tokio::select! {
+            () = cancel.cancelled() => None,
+            () = gave_up.cancelled() => None,
+            joined = &mut inputs => Some(joined),
+        };
+        info!("[SHUTDOWN] Stopping the input supervisors");
+        cancel.cancel();
+        let joined = match finished {
+            Some(joined) => joined,
+            None => inputs.await,
+        };
+        for result in joined {
+            if let Err(e) = result {
+                error!("[SHUTDOWN] Input supervisor join error: {e}");
+            }
+        }
+
+        // Only the fan-out holds the bridge channel now, so it finishes once
+        // it has passed on what is queued, while the outputs drain.
+        let InputSinks { output, stats, .. } = sinks;
+        drop(output);
+        let drains = join_all(
+            outputs
+                .into_iter()
+                .map(|output| output.drain(drain_timeout, None)),
+        );
+        let (fanned, _) = tokio::join!(fan_out, drains);
+        if let Err(e) = fanned {
+            error!("[SHUTDOWN] Fan-out join error: {e}");
+        }
+        drop(stats);
+
+        if gave_up.is_cancelled() {
+            return Err(Error::msg(
+                "Stopped after a supervisor ran out of restart attempts",
+            ));
+        }
+        info!("[SHUTDOWN] Clean exit");
+        Ok(())
+    }
+}
+
+/// A running bridge.
+pub struct BridgeHandle {
+    cancel: CancellationToken,
+    task: JoinHandle<Result<()>>,
+    counters: StatsCounters,
+    inputs: Vec<watch::Receiver<SupervisorStatus>>,
+    outputs: Vec<watch::Receiver<SupervisorStatus>>,
+}
+
+impl BridgeHandle {
+    /// Start a graceful shutdown, without waiting for it.
+    pub fn stop(&self) {
+        self.cancel.cancel();
+    }
+
+    /// Wait for the bridge to stop, which it does after `stop`, once every
+    /// input has ended at EOF, or when a supervisor gives up.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a supervisor gave up or the bridge panicked.
+    pub async fn wait(self) -> Result<()> {
+        self.task.await?
+    }
+
+    /// Shut the bridge down gracefully and wait for it to finish.
+    ///
+    /// # Errors
+    ///
+    /// As for `wait`.
+    pub async fn shutdown(self) -> Result<()> {
+        self.stop();
+        self.wait().await
+    }
+
+    /// The running totals.
+    #[must_use]
+    pub fn counters(&self) -> StatsCounters {
+        self.counters.clone()
+    }
+
+    /// The state of each input's supervisor, in the order they were added.
+    #[must_use]
+    pub fn input_status(&self) -> &[watch::Receiver<SupervisorStatus>] {
+        &self.inputs
+    }
+
+    /// The state of each output's supervisor, in the order they were added.
+    #[must_use]
+    pub fn output_status(&self) -> &[watch::Receiver<SupervisorStatus>] {
+        &self.outputs
+    }
+}
@@ -0,0 +1,387 @@
+// Copyright (c) 2024-2026 Fred Clausen
+//
+// Licensed under the MIT license: https://opensource.org/licenses/MIT
+// Permission is granted to use, copy, modify, and redistribute the work.
+// Full license information available in the project LICENSE file.
+
+//! Capture files: the raw input stream with receive timestamps and sources.
+//!
+//! A capture is a header followed by records, all integers little-endian:
+//!
+//! ```text
+//! header:  magic "ABCAP" (5 bytes), version u8 (= 1), reserved u16 (= 0)
+//! record:  timestamp_ns u64   receive time, nanoseconds since the Unix epoch
+//!          source_len   u16
+//!          source       source_len bytes of UTF-8, e.g. "udp://192.0.2.7:41234"
+//!          frame_count  u16   1 except for multipart ZMQ messages
+//!          frame_count times:
+//!            frame_len  u32
+//!            frame      frame_len bytes, exactly as received
+//! ```
+//!
+//! Records are appended in the order they were received. A capture cut off
+//! mid-record (the bridge was killed) is valid up to the last whole record,
+//! and recording to it again cuts the partial record off first.
+
+use anyhow::{Context, Error, Result};
+use std::io::{ErrorKind, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tokio::fs::{File, OpenOptions};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncSeekExt, AsyncWriteExt, BufReader, BufWriter};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+pub const MAGIC: &[u8; 5] = b"ABCAP";
+pub const VERSION: u8 = 1;
+pub const HEADER_LEN: usize = 8;
+/// Frames longer than this are taken as a sign of a corrupt file rather
+/// than allocated.
+const MAX_FRAME_LEN: u32 = 64 * 1024 * 1024;
+
+/// One message as it came off the wire.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CaptureRecord {
+    /// Receive time, nanoseconds since the Unix epoch.
+    pub timestamp_ns: u64,
+    /// Where the message came from, as `<protocol>://<host>:<port>`.
+    pub source: String,
+    pub frames: Vec<Vec<u8>>,
+}
+
+impl CaptureRecord {
+    /// Append the encoded record to `buf`. Fields too long for their length
+    /// prefix are truncated; no decoder produces anything close.
+    pub fn encode(&self, buf: &mut Vec<u8>) {
+        let source = &self.source.as_bytes()[..self.source.len().min(u16::MAX as usize)];
+        let frames = &self.frames[..self.frames.len().min(u16::MAX as usize)];
+
+        buf.extend_from_slice(&self.timestamp_ns.to_le_bytes());
+        buf.extend_from_slice(
+            &u16::try_from(source.len())
+                .unwrap_or(u16::MAX)
+                .to_le_bytes(),
+        );
+        buf.extend_from_slice(source);
+        buf.extend_from_slice(
+            &u16::try_from(frames.len())
+                .unwrap_or(u16::MAX)
+                .to_le_bytes(),
+        );
+        for frame in frames {
+            let frame = &frame[..frame.len().min(u32::MAX as usize)];
+            buf.extend_from_slice(&u32::try_from(frame.len()).unwrap_or(u32::MAX).to_le_bytes());
+            buf.extend_from_slice(frame);
+        }
+    }
+
+    /// How many bytes `encode` writes, for a record within the format's
+    /// limits, as every record read from a file is.
+    #[must_use]
+    pub fn encoded_len(&self) -> usize {
+        8 + 2
+            + self.source.len()
+            + 2
+            + self
+                .frames
+                .iter()
+                .map(|frame| 4 + frame.len())
+                .sum::<usize>()
+    }
+}
+
+/// The file header for the current format version.
+#[must_use]
+pub fn header() -> [u8; HEADER_LEN] {
+    let mut header = [0; HEADER_LEN];
+    header[..MAGIC.len()].copy_from_slice(MAGIC);
+    header[MAGIC.len()] = VERSION;
+    header
+}
+
+/// Check a file header.
+///
+/// # Errors
+///
+/// Returns an error if `header` is not a capture header or is from a format
+/// version this build does not understand.
+pub fn check_header(header: &[u8; HEADER_LEN]) -> Result<()> {
+    if &header[..MAGIC.len()] != MAGIC {
+        return Err(Error::msg("not an acars-bridge capture file"));
+    }
+    match header[MAGIC.len()] {
+        VERSION => Ok(()),
+        version => Err(Error::msg(format!(
+            "unsupported capture format version {version}"
+        ))),
+    }
+}
+
+/// Reads the records of a capture file in order.
+pub struct CaptureReader {
+    path: PathBuf,
+    reader: BufReader<File>,
+}
+
+impl CaptureReader {
+    /// Open a capture and check its header.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be read or isn't a capture.
+    pub async fn open(path: &Path) -> Result<Self> {
+        let mut reader = BufReader::new(File::open(path).await?);
+        let mut header = [0; HEADER_LEN];
+        reader
+            .read_exact(&mut header)
+            .await
+            .context("file is too short to be a capture")?;
+        check_header(&header)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            reader,
+        })
+    }
+
+    /// Go back to the first record.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be seeked.
+    pub async fn rewind(&mut self) -> Result<()> {
+        self.reader.seek(SeekFrom::Start(HEADER_LEN as u64)).await?;
+        Ok(())
+    }
+
+    /// The next record, or `None` at the end of the capture. A record cut
+    /// off by the end of the file counts as the end, with a warning.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be read or a record is corrupt.
+    pub async fn next_record(&mut self) -> Result<Option<CaptureRecord>> {
+        if self.reader.fill_buf().await?.is_empty() {
+            return Ok(None);
+        }
+        match self.read_record().await {
+            Ok(record) => Ok(Some(record)),
+            Err(e)
+                if e.downcast_ref::<std::io::Error>()
+                    .is_some_and(|e| e.kind() == ErrorKind::UnexpectedEof) =>
+            {
+                warn!(
+                    "[CAPTURE] {} ends part-way through a record; ignoring it",
+                    self.path.display()
+                );
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn read_record(&mut self) -> Result<CaptureRecord> {
+        let timestamp_ns = self.reader.read_u64_le().await?;
+        let mut source = vec![0; usize::from(self.reader.read_u16_le().await?)];
+        self.reader.read_exact(&mut source).await?;
+        let source = String::from_utf8(source).context("capture source is not UTF-8")?;
+
+        let frame_count = self.reader.read_u16_le().await?;
+        let mut frames = Vec::with_capacity(usize::from(frame_count));
+        for _ in 0..frame_count {
+            let frame_len = self.reader.read_u32_le().await?;
+            if frame_len > MAX_FRAME_LEN {
+                return Err(Error::msg(format!(
+                    "capture frame of {frame_len} bytes; the file is probably corrupt"
+                )));
+            }
+            let mut frame = vec![0; frame_len as usize];
+            self.reader.read_exact(&mut frame).await?;
+            frames.push(frame);
+        }
+
+        Ok(CaptureRecord {
+            timestamp_ns,
+            source,
+            frames,
+        })
+    }
+}
+
+/// Handle the inputs use to add records to the capture. Cheap to clone.
+#[derive(Clone, Debug)]
+pub struct Recorder {
+    sender: mpsc::Sender<CaptureRecord>,
+}
+
+impl Recorder {
+    /// Record a message received now from `source`.
+    pub async fn record(&self, source: &str, frames: &[&[u8]]) {
+        let timestamp_ns = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |elapsed| {
+                u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX)
+            });
+        let record = CaptureRecord {
+            timestamp_ns,
+            source: source.to_string(),
+            frames: frames.iter().map(|frame| frame.to_vec()).collect(),
+        };
+        // The writer only goes away during shutdown, after the inputs.
+        let _ = self.sender.send(record).await;
+    }
+}
+
+/// Open `path` for recording, appending to it if it is already a capture.
+/// A partial record at its end is cut off first, so that what is appended
+/// is read as records of its own.
+async fn open(path: &Path) -> Result<File> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .read(true)
+        .append(true)
+        .open(path)
+        .await?;
+    let len = file.metadata().await?.len();
+    if len == 0 {
+        file.write_all(&header()).await?;
+        return Ok(file);
+    }
+    let whole = whole_records_len(path)
+        .await
+        .context("not appending to it")?;
+    if whole < len {
+        warn!(
+            "[RECORDER] Cutting the partial record ({} bytes) off the end of {} before appending",
+            len - whole,
+            path.display()
+        );
+        file.set_len(whole).await?;
+    }
+    Ok(file)
+}
+
+/// The length of the capture at `path` up to the end of its last whole
+/// record.
+async fn whole_records_len(path: &Path) -> Result<u64> {
+    let mut reader = CaptureReader::open(path).await?;
+    let mut len = HEADER_LEN as u64;
+    while let Some(record) = reader.next_record().await? {
+        len += record.encoded_len() as u64;
+    }
+    Ok(len)
+}
+
+/// Open the capture and start the task that writes it.
+///
+/// The task ends once every `Recorder` clone has been dropped, after writing
+/// out everything recorded. A write error stops recording but not the
+/// bridge; later records are discarded.
+///
+/// # Errors
+///
+/// Returns an error if the file can't be opened or exists and isn't a
+/// capture.
+pub async fn start(path: PathBuf, capacity: usize) -> Result<(Recorder, JoinHandle<()>)> {
+    let file = open(&path)
+        .await
+        .with_context(|| format!("[RECORDER] Error opening {}", path.display()))?;
+    let (sender, mut receiver) = mpsc::channel::<CaptureRecord>(capacity);
+    info!("[RECORDER] Recording input to {}", path.display());
+
+    let handle = tokio::spawn(async move {
+        let mut writer = Some(BufWriter::new(file));
+        let mut buf = Vec::new();
+        let mut recorded: u64 = 0;
+        while let Some(record) = receiver.recv().await {
+            let Some(active) = writer.as_mut() else {
+                continue;
+            };
+
+            // Encode whatever else is already queued too, then write and
+            // flush once.
+            buf.clear();
+            record.encode(&mut buf);
+            let mut batch = 1;
+            while let Ok(record) = receiver.try_recv() {
+                record.encode(&mut buf);
+                batch += 1;
+            }
+            let written = async {
+                active.write_all(&buf).await?;
+                active.flush().await
+            };
+            match written.await {
+                Ok(()) => recorded += batch,
+                Err(e) => {
+                    error!(
+                        "[RECORDER] Error writing {}: {e}; recording stopped",
+                        path.display()
+                    );
+                    writer = None;
+                }
+            }
+        }
+        info!("[RECORDER] Capture closed after {recorded} message(s)");
+    });
+
+    Ok((Recorder { sender }, handle))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn record(path: &Path, messages: &[&str]) {
+        let (recorder, handle) = start(path.to_path_buf(), 16).await.expect("recording");
+        for message in messages {
+            recorder
+                .record("udp://192.0.2.7:41234", &[message.as_bytes()])
+                .await;
+        }
+        drop(recorder);
+        handle.await.expect("writer task");
+    }
+
+    async fn read_all(path: &Path) -> Vec<String> {
+        let mut reader = CaptureReader::open(path).await.expect("opening");
+        let mut messages = Vec::new();
+        while let Some(record) = reader.next_record().await.expect("reading") {
+            messages.push(String::from_utf8(record.frames.concat()).expect("UTF-8"));
+        }
+        messages
+    }
+
+    #[tokio::test]
+    async fn appending_after_a_cut_off_record_starts_a_whole_one() {
+        let path =
+            std::env::temp_dir().join(format!("acars-bridge-capture-{}.abcap", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        record(&path, &["one", "two"]).await;
+        let len = std::fs::metadata(&path).expect("capture").len();
+        OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .await
+            .expect("opening")
+            .set_len(len - 2)
+            .await
+            .expect("cutting off");
+        assert_eq!(read_all(&path).await, ["one"]);
+
+        record(&path, &["three"]).await;
+        assert_eq!(read_all(&path).await, ["one", "three"]);
+        std::fs::remove_file(&path).expect("removing the capture");
+    }
+
+    #[tokio::test]
+    async fn a_file_that_is_not_a_capture_is_not_appended_to() {
+        let path = std::env::temp_dir().join(format!(
+            "acars-bridge-not-a-capture-{}.txt",
+            std::process::id()
+        ));
+        std::fs::write(&path, "plain text\n").expect("writing");
+        assert!(start(path.clone(), 16).await.is_err());
+        assert_eq!(std::fs::read(&path).expect("reading"), b"plain text\n");
+        std::fs::remove_file(&path).expect("removing the file");
+    }
+}
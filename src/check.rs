@@ -0,0 +1,217 @@
+// Copyright (c) 2024-2026 Fred Clausen
+//
+// Licensed under the MIT license: https://opensource.org/licenses/MIT
+// Permission is granted to use, copy, modify, and redistribute the work.
+// Full license information available in the project LICENSE file.
+
+//! The `check` subcommand: find every configuration problem without
+//! starting the bridge.
+//!
+//! It goes through everything a start would need, from parsing the flags to
+//! resolving hosts, binding ports and opening files, and reports each
+//! problem instead of stopping at the first.
+//!
+//! Nothing connects to a peer; a host that resolves may still refuse the
+//! connection once the bridge runs.
+
+use anyhow::{Context, Error, Result};
+use std::io::ErrorKind;
+use std::net::SocketAddr;
+use std::path::Path;
+use tokio::net::{TcpListener, lookup_host};
+
+use crate::capture::CaptureReader;
+use crate::config::Config;
+use crate::endpoint::Endpoint;
+use crate::transport::Checks;
+
+/// Prints one line per check and counts the failures.
+#[derive(Default)]
+struct Report {
+    problems: usize,
+}
+
+impl Report {
+    fn record<T>(&mut self, what: &str, result: Result<T>) -> Option<T> {
+        match result {
+            Ok(value) => {
+                println!("ok    {what}");
+                Some(value)
+            }
+            Err(e) => {
+                println!("FAIL  {what}: {e:#}");
+                self.problems += 1;
+                None
+            }
+        }
+    }
+}
+
+/// Run every check and print the results.
+///
+/// # Errors
+///
+/// Returns an error giving the number of problems if any check failed.
+pub async fn run(config: &Config) -> Result<()> {
+    let mut report = Report::default();
+
+    report.record("--zmq-frame-mode", crate::zmq::zmq_frame_mode(config));
+    report.record("--http-header", crate::http::http_headers(config));
+    report.record("--mqtt-qos", crate::mqtt::parse_qos(config.get_mqtt_qos()));
+    report.record("--file-compression", crate::file::file_compression(config));
+    report.record("--replay-speed", crate::replay::replay_speed(config));
+    report.record("--tail-start", crate::tail::tail_from_beginning(config));
+    report.record("--ui", crate::cli::dashboard_requested(config));
+    report.record(
+        "--source-backoff",
+        crate::cli::backoff_policy("source-backoff", config.get_source_backoff().as_deref()),
+    );
+    report.record(
+        "--destination-backoff",
+        crate::cli::backoff_policy(
+            "destination-backoff",
+            config.get_destination_backoff().as_deref(),
+        ),
+    );
+
+    // Endpoint settings are checked on top of the flags' settings, or the
+    // defaults if those are bad.
+    let options = report
+        .record("settings", crate::cli::build_transport_options(config))
+        .unwrap_or_default();
+    if let Some(endpoint) = report.record("source", crate::cli::parse_source(config, &options)) {
+        probe(
+            &mut report,
+            &format!("source {}", endpoint.label()),
+            &endpoint,
+            true,
+        )
+        .await;
+    }
+
+    match crate::cli::parse_destination(config, &options) {
+        Ok(Some(endpoint)) => {
+            report.record("destination", Ok(()));
+            let label = format!("destination {}", endpoint.label());
+            probe(&mut report, &label, &endpoint, false).await;
+        }
+        Ok(None)
+            if config.get_destination_host().is_some()
+                || config.get_destination_port().is_some() =>
+        {
+            report.record::<()>(
+                "destination",
+                Err(Error::msg(
+                    "--destination-protocol is required with --destination-host or --destination-port",
+                )),
+            );
+        }
+        Ok(None) => println!("-     destination: none set; messages will only be counted"),
+        Err(e) => {
+            report.record::<()>("destination", Err(e));
+        }
+    }
+
+    if let Some(path) = config.get_record() {
+        report.record("--record", recording_file(Path::new(path)).await);
+    }
+    if let Some(path) = config.get_spill_file() {
+        report.record("--spill-file", writable_file(Path::new(path)));
+    }
+
+    if report.problems == 0 {
+        println!("No problems found");
+        Ok(())
+    } else {
+        Err(Error::msg(format!("{} problem(s) found", report.problems)))
+    }
+}
+
+/// Check what the server for one side needs at startup: a usable port, and
+/// whatever its transport checks, like a host that resolves, a port free to
+/// bind, a file to read.
+async fn probe(report: &mut Report, label: &str, endpoint: &Endpoint, input: bool) {
+    if endpoint.transport.needs_port() && endpoint.port == 0 {
+        report.record::<()>(
+            &format!("{label}: port"),
+            Err(Error::msg("port 0 can't be used")),
+        );
+        return;
+    }
+    for (what, result) in endpoint.transport.check(endpoint, input).await {
+        report.record(&format!("{label}: {what}"), result);
+    }
+}
+
+/// That `host` resolves, for a transport that connects to it.
+pub async fn resolves(host: &str, port: u16) -> Checks {
+    vec![("resolve", resolve(host, port).await.map(drop))]
+}
+
+/// That `host:port` is free to listen on, for a transport that accepts
+/// connections.
+pub async fn binds(host: &str, port: u16) -> Checks {
+    let bound = TcpListener::bind((host, port))
+        .await
+        .map(drop)
+        .context("Error binding");
+    vec![("bind", bound)]
+}
+
+async fn resolve(host: &str, port: u16) -> Result<SocketAddr> {
+    lookup_host((host, port))
+        .await
+        .with_context(|| format!("DNS lookup failed for {host}:{port}"))?
+        .next()
+        .ok_or_else(|| Error::msg(format!("No addresses resolved for {host}:{port}")))
+}
+
+/// The file output creates its directory if it doesn't exist yet, but can't
+/// write into something that isn't one.
+///
+/// # Errors
+///
+/// Returns an error if `path` can't be used.
+pub fn directory(path: &Path) -> Result<()> {
+    match std::fs::metadata(path) {
+        Ok(metadata) if metadata.is_dir() => Ok(()),
+        Ok(_) => Err(Error::msg(format!("{} is not a directory", path.display()))),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(Error::msg(format!("Error reading {}: {e}", path.display()))),
+    }
+}
+
+/// A file the bridge creates or rewrites: it must not be a directory, and
+/// the directory it goes in must exist.
+///
+/// # Errors
+///
+/// Returns an error if `path` can't be used.
+pub fn writable_file(path: &Path) -> Result<()> {
+    if path.is_dir() {
+        return Err(Error::msg(format!("{} is a directory", path.display())));
+    }
+    let parent = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    if !parent.is_dir() {
+        return Err(Error::msg(format!(
+            "Directory {} does not exist",
+            parent.display()
+        )));
+    }
+    Ok(())
+}
+
+/// `--record` appends to an existing capture, so a non-empty file there must
+/// already be one.
+async fn recording_file(path: &Path) -> Result<()> {
+    writable_file(path)?;
+    if std::fs::metadata(path).is_ok_and(|metadata| metadata.len() > 0) {
+        CaptureReader::open(path)
+            .await
+            .with_context(|| format!("Error opening {}", path.display()))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,188 @@
+// Copyright (c) 2024-2026 Fred Clausen
+//
+// Licensed under the MIT license: https://opensource.org/licenses/MIT
+// Permission is granted to use, copy, modify, and redistribute the work.
+// Full license information available in the project LICENSE file.
+
+//! Turning the command line into what the `acars-bridge` binary runs.
+//!
+//! The transport options, the source and destination, and how it logs all
+//! come from here. The pipeline and `check` use these too, so a reload and
+//! a check read the configuration the same way a start does.
+
+use anyhow::{Context as _, Result};
+use sdre_rust_logging::SetupLogging;
+
+use crate::backoff::BackoffPolicy;
+use crate::config::{Command, Config};
+use crate::dashboard::LogBuffer;
+use crate::endpoint::Endpoint;
+use crate::generate::GenerateTransport;
+use crate::serverconfig::TransportOptions;
+use crate::tap::TapTransport;
+use crate::transport::Transport;
+
+pub(crate) fn backoff_policy(flag: &str, spec: Option<&str>) -> Result<Option<BackoffPolicy>> {
+    spec.map(|spec| BackoffPolicy::parse(spec).with_context(|| format!("Invalid --{flag}")))
+        .transpose()
+}
+
+/// Parse the protocol-specific flags into the `TransportOptions` shared by
+/// every input and output server. Each registered transport reads its own
+/// flags, as do `generate` and `tap` for their subcommands.
+///
+/// # Errors
+///
+/// Returns an error naming the first flag that doesn't parse.
+pub fn build_transport_options(config: &Config) -> Result<TransportOptions> {
+    let mut options = TransportOptions::default();
+    options.source_backoff =
+        backoff_policy("source-backoff", config.get_source_backoff().as_deref())?;
+    options.destination_backoff = backoff_policy(
+        "destination-backoff",
+        config.get_destination_backoff().as_deref(),
+    )?;
+    let subcommands: [&dyn Transport; 2] = [&GenerateTransport, &TapTransport];
+    for transport in crate::transport::registry().transports().chain(subcommands) {
+        transport.options_from_config(config, &mut options)?;
+    }
+    Ok(options)
+}
+
+/// The input to run: the source given by `--source` or the `--source-*`
+/// flags, or the generator for the `generate` subcommand. Its server runs
+/// with `options`, and any settings from its URL.
+///
+/// # Errors
+///
+/// Returns an error if no source is given or it doesn't parse.
+pub fn parse_source(config: &Config, options: &TransportOptions) -> Result<Endpoint> {
+    match config.get_command() {
+        Some(Command::Generate(_)) if !config.is_destination_set() => {
+            Err(anyhow::anyhow!("generate needs a destination to send to"))
+        }
+        Some(Command::Generate(_)) => Ok(Endpoint::bare(&GenerateTransport, options.clone())),
+        Some(Command::Tap(_) | Command::Check) | None => match config.get_source() {
+            Some(url) => Endpoint::parse(url, true, options),
+            None => Endpoint::from_flags(
+                config
+                    .get_source_protocol()
+                    .ok_or_else(|| anyhow::anyhow!("--source or --source-protocol is required"))?,
+                config.get_source_host(),
+                config.get_source_port(),
+                true,
+                options,
+            ),
+        },
+    }
+}
+
+/// The output to run, if any: the destination given by `--destination` or
+/// the `--destination-*` flags, or the printer for the `tap` subcommand.
+///
+/// # Errors
+///
+/// Returns an error if the destination doesn't parse.
+pub fn parse_destination(config: &Config, options: &TransportOptions) -> Result<Option<Endpoint>> {
+    if matches!(config.get_command(), Some(Command::Tap(_))) {
+        return Ok(Some(Endpoint::bare(&TapTransport, options.clone())));
+    }
+    if !config.is_destination_set() {
+        return Ok(None);
+    }
+    let destination = config.get_destination().map_or_else(
+        || {
+            Endpoint::from_flags(
+                config
+                    .get_destination_protocol()
+                    .as_deref()
+                    .unwrap_or_default(),
+                config.get_destination_host().as_deref(),
+                config.get_destination_port(),
+                false,
+                options,
+            )
+        },
+        |url| Endpoint::parse(url, false, options),
+    )?;
+    Ok(Some(destination))
+}
+
+/// Wait for either SIGINT (Ctrl-C) or, on Unix, SIGTERM. Container
+/// orchestrators (docker stop, systemd) typically send SIGTERM, which the
+/// default `tokio::signal::ctrl_c()` alone does not catch.
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            error!("Failed to install Ctrl-C handler: {e}");
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut sig) => {
+                sig.recv().await;
+            }
+            Err(e) => {
+                error!("Failed to install SIGTERM handler: {e}");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        () = ctrl_c => info!("[SHUTDOWN] Received SIGINT"),
+        () = terminate => info!("[SHUTDOWN] Received SIGTERM"),
+    }
+}
+
+/// Start logging the way `--ui` asks. With the dashboard, records are held
+/// for its log panel in the returned buffer. `check` always logs as usual,
+/// and reports a bad `--ui` along with everything else.
+///
+/// # Errors
+///
+/// Returns an error if `--ui` is invalid or the dashboard can't start.
+pub fn start_logging(config: &Config) -> Result<Option<LogBuffer>> {
+    if matches!(config.get_command(), Some(Command::Check)) || !dashboard_requested(config)? {
+        config.get_log_level().enable_logging();
+        return Ok(None);
+    }
+    if !crate::dashboard::terminal_available() {
+        return Err(anyhow::anyhow!(
+            "--ui dashboard needs standard output to be a terminal"
+        ));
+    }
+    crate::dashboard::install_logger(config.get_log_level().set_logging_level()).map(Some)
+}
+
+/// Whether `--ui` asks for the dashboard. The dashboard draws on standard
+/// output, so it can't share the terminal with `stdin`, `stdout` or `tap`.
+///
+/// # Errors
+///
+/// Returns an error for an unknown `--ui`, or the dashboard alongside a
+/// transport that uses the terminal.
+pub fn dashboard_requested(config: &Config) -> Result<bool> {
+    match config.get_ui() {
+        "log" => Ok(false),
+        "dashboard" => {
+            let options = TransportOptions::default();
+            let source = parse_source(config, &options)?;
+            let destination = parse_destination(config, &options)?;
+            if source.transport.uses_terminal()
+                || destination.is_some_and(|destination| destination.transport.uses_terminal())
+            {
+                return Err(anyhow::anyhow!(
+                    "--ui dashboard can't be used with stdin, stdout or tap"
+                ));
+            }
+            Ok(true)
+        }
+        other => Err(anyhow::anyhow!("Unknown UI: {other}")),
+    }
+}
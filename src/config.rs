@@ -3,32 +3,99 @@
 // Licensed under the MIT license: https://opensource.org/licenses/MIT
 // Permission is granted to use, copy, modify, and redistribute the work.
 // Full license information available in the project LICENSE file.
-use clap::Parser;
+use anyhow::{Context, Error, Result};
+use clap::parser::ValueSource;
+use clap::{ArgMatches, Args, CommandFactory, FromArgMatches, Parser, Subcommand};
+use std::ffi::{OsStr, OsString};
+use std::num::NonZeroUsize;
+use std::path::Path;
 
 #[derive(Parser, Debug, Clone, Default)]
 #[command(name = "ACARS Bridge", author, version, about, long_about = None)]
 pub struct Config {
-    #[clap(long, env = "AB_LOG_LEVEL", default_value = "info")]
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[clap(long, env = "AB_LOG_LEVEL", default_value = "info", global = true)]
     pub log_level: String,
 
-    #[clap(long, env = "AB_SOURCE", required = true, requires_all = &["source_port", "source_protocol"])]
-    pub source_host: String,
+    /// How the bridge reports what it is doing: `log` (log lines on
+    /// standard error) or `dashboard` (a full-screen terminal dashboard).
+    #[clap(long, env = "AB_UI", default_value = "log", global = true)]
+    pub ui: String,
+
+    /// File of `AB_*=value` lines, one per flag, read at startup and again
+    /// on `SIGHUP`. Its settings override the environment, and flags on the
+    /// command line override both.
+    #[clap(long, env = "AB_CONFIG_FILE", global = true)]
+    pub config_file: Option<String>,
+
+    /// The source as a URL, e.g. `udp://0.0.0.0:5550` or
+    /// `zmq://acarsdec:45555?hwm=1000`, in place of `--source-protocol`,
+    /// `--source-host` and `--source-port`.
+    #[clap(
+        long,
+        env = "AB_SOURCE_URL",
+        conflicts_with_all = ["source_host", "source_port", "source_protocol"],
+        global = true
+    )]
+    pub source: Option<String>,
+
+    /// For the `replay` and `tail` protocols, the file to read. Required for
+    /// every protocol except `stdin`; main checks this once the protocol is
+    /// known.
+    #[clap(long, env = "AB_SOURCE", requires = "source_protocol", global = true)]
+    pub source_host: Option<String>,
 
-    #[clap(long, env = "AB_SOURCE_PORT")]
-    pub source_port: u16,
+    /// Required for every protocol except `replay` and `tail`; main checks
+    /// this once the protocol is known.
+    #[clap(long, env = "AB_SOURCE_PORT", global = true)]
+    pub source_port: Option<u16>,
 
-    #[clap(long, env = "AB_SOURCE_PROTOCOL")]
-    pub source_protocol: String,
+    /// Required unless a subcommand takes the place of the source; main
+    /// checks this once the subcommand is known.
+    #[clap(long, env = "AB_SOURCE_PROTOCOL", global = true)]
+    pub source_protocol: Option<String>,
 
-    #[clap(long, env = "AB_DESTINATION", requires_all = &["destination_port", "destination_protocol"])]
+    /// The destination as a URL, e.g. `tcp://aggregator:5550`, in place of
+    /// `--destination-protocol`, `--destination-host` and
+    /// `--destination-port`.
+    #[clap(
+        long,
+        env = "AB_DESTINATION_URL",
+        conflicts_with_all = ["destination_host", "destination_port", "destination_protocol"],
+        global = true
+    )]
+    pub destination: Option<String>,
+
+    /// For the `file` protocol, the directory to write to. Not used by
+    /// `stdout`, which needs only `--destination-protocol`.
+    #[clap(
+        long,
+        env = "AB_DESTINATION",
+        requires = "destination_protocol",
+        global = true
+    )]
     pub destination_host: Option<String>,
 
-    #[clap(long, env = "AB_DESTINATION_PORT")]
+    /// Required with `--destination-host` for every protocol except `file`;
+    /// main checks this once the protocol is known.
+    #[clap(long, env = "AB_DESTINATION_PORT", global = true)]
     pub destination_port: Option<u16>,
 
-    #[clap(long, env = "AB_DESTINATION_PROTOCOL")]
+    #[clap(long, env = "AB_DESTINATION_PROTOCOL", global = true)]
     pub destination_protocol: Option<String>,
 
+    /// How the input is retried, as comma-separated `key=value` settings:
+    /// `initial`, `max` and `reset-after` in seconds, `multiplier`, `jitter`
+    /// (0 to 1) and `max-attempts` (0 for no limit).
+    #[clap(long, env = "AB_SOURCE_BACKOFF", global = true)]
+    pub source_backoff: Option<String>,
+
+    /// How the output is retried, in the same form as `--source-backoff`.
+    #[clap(long, env = "AB_DESTINATION_BACKOFF", global = true)]
+    pub destination_backoff: Option<String>,
+
     #[clap(long, env = "AB_STAT_INTERVAL", default_value = "5", value_parser = clap::value_parser!(u64).range(1..))]
     pub stat_interval: u64,
 
@@ -39,19 +106,338 @@ pub struct Config {
     /// backpressuring upstream during output stalls.
     #[clap(long, env = "AB_CHANNEL_CAPACITY", default_value = "1024", value_parser = clap::value_parser!(u64).range(1..))]
     pub channel_capacity: u64,
+
+    /// Seconds to wait at shutdown for the output to deliver what is still
+    /// queued before stopping it. `0` waits as long as it takes.
+    #[clap(long, env = "AB_DRAIN_TIMEOUT", default_value = "30")]
+    pub drain_timeout: u64,
+
+    /// At shutdown, append the messages the output didn't deliver to this
+    /// file, one per line, instead of dropping them.
+    #[clap(long, env = "AB_SPILL_FILE")]
+    pub spill_file: Option<String>,
+
+    /// Most messages the TCP output writes before flushing them to the
+    /// socket. `1` flushes every message on its own.
+    #[clap(long, env = "AB_TCP_BATCH_SIZE", default_value = "64", value_parser = clap::value_parser!(u64).range(1..))]
+    pub tcp_batch_size: u64,
+
+    /// Milliseconds a partial TCP batch waits for more messages once the
+    /// queue is empty before it is flushed. `0` flushes as soon as nothing
+    /// more is queued.
+    #[clap(long, env = "AB_TCP_BATCH_DELAY_MS", default_value = "0")]
+    pub tcp_batch_delay_ms: u64,
+
+    /// How multipart ZMQ input messages are collapsed into one bridged
+    /// message: `join` (all frames joined with a space), `last` (last frame
+    /// only), `drop-first` (drop the topic frame) or `metadata` (last frame
+    /// as the body, leading frames added to it as a `zmq_frames` array).
+    #[clap(long, env = "AB_ZMQ_FRAME_MODE", default_value = "join")]
+    pub zmq_frame_mode: String,
+
+    /// ZMQ high-water mark, in messages, for every ZMQ socket. Applied as the
+    /// receive HWM on inputs and the send HWM on outputs. `0` is unlimited.
+    #[clap(long, env = "AB_ZMQ_HWM", value_parser = clap::value_parser!(i32).range(0..))]
+    pub zmq_hwm: Option<i32>,
+
+    /// How long a closing ZMQ socket keeps trying to deliver unsent
+    /// messages, in milliseconds. `-1` waits forever.
+    #[clap(long, env = "AB_ZMQ_LINGER_MS", value_parser = clap::value_parser!(i32).range(-1..))]
+    pub zmq_linger_ms: Option<i32>,
+
+    /// Initial ZMQ reconnect interval, in milliseconds.
+    #[clap(long, env = "AB_ZMQ_RECONNECT_IVL_MS", value_parser = clap::value_parser!(i32).range(0..))]
+    pub zmq_reconnect_ivl_ms: Option<i32>,
+
+    /// Maximum ZMQ reconnect interval, in milliseconds. `0` keeps retrying at
+    /// the initial interval.
+    #[clap(long, env = "AB_ZMQ_RECONNECT_IVL_MAX_MS", value_parser = clap::value_parser!(i32).range(0..))]
+    pub zmq_reconnect_ivl_max_ms: Option<i32>,
+
+    /// Enable TCP keepalive on ZMQ connections, probing after this many
+    /// seconds of idle time.
+    #[clap(long, env = "AB_ZMQ_TCP_KEEPALIVE_IDLE", value_parser = clap::value_parser!(i32).range(1..))]
+    pub zmq_tcp_keepalive_idle: Option<i32>,
+
+    /// Seconds between ZMQ TCP keepalive probes.
+    #[clap(long, env = "AB_ZMQ_TCP_KEEPALIVE_INTVL", value_parser = clap::value_parser!(i32).range(1..))]
+    pub zmq_tcp_keepalive_intvl: Option<i32>,
+
+    /// Unanswered ZMQ TCP keepalive probes before the connection is dropped.
+    #[clap(long, env = "AB_ZMQ_TCP_KEEPALIVE_CNT", value_parser = clap::value_parser!(i32).range(1..))]
+    pub zmq_tcp_keepalive_cnt: Option<i32>,
+
+    /// Path (and optional query string) the HTTP output posts to.
+    #[clap(long, env = "AB_HTTP_PATH", default_value = "/")]
+    pub http_path: String,
+
+    /// Use HTTPS for the HTTP output.
+    #[clap(long, env = "AB_HTTP_TLS")]
+    pub http_tls: bool,
+
+    /// Extra header for the HTTP output, as `Name: value`. May be repeated;
+    /// separate multiple headers in the environment variable with `;`.
+    #[clap(long = "http-header", env = "AB_HTTP_HEADERS", value_delimiter = ';')]
+    pub http_headers: Vec<String>,
+
+    /// Bearer token sent in the `Authorization` header by the HTTP output.
+    #[clap(long, env = "AB_HTTP_BEARER_TOKEN", conflicts_with = "http_basic_auth")]
+    pub http_bearer_token: Option<String>,
+
+    /// Basic auth credentials for the HTTP output, as `user:password`.
+    #[clap(long, env = "AB_HTTP_BASIC_AUTH")]
+    pub http_basic_auth: Option<String>,
+
+    /// Messages per HTTP POST. `1` posts each message on its own; larger
+    /// values post a JSON array of up to this many messages.
+    #[clap(long, env = "AB_HTTP_BATCH_SIZE", default_value = "1", value_parser = clap::value_parser!(u64).range(1..))]
+    pub http_batch_size: u64,
+
+    /// Milliseconds a partial HTTP batch waits for more messages before it
+    /// is posted anyway.
+    #[clap(long, env = "AB_HTTP_BATCH_TIMEOUT_MS", default_value = "1000")]
+    pub http_batch_timeout_ms: u64,
+
+    /// Retries for an HTTP POST that failed with a 5xx, a 429 or a
+    /// connection error before the message is dropped.
+    #[clap(long, env = "AB_HTTP_MAX_RETRIES", default_value = "5")]
+    pub http_max_retries: u32,
+
+    /// Timeout for a single HTTP request, in seconds.
+    #[clap(long, env = "AB_HTTP_TIMEOUT", default_value = "10", value_parser = clap::value_parser!(u64).range(1..))]
+    pub http_timeout: u64,
+
+    /// Topic template for the MQTT output. `{type}` (acars, vdl2 or hfdl),
+    /// `{tail}` and dotted JSON paths such as `{station_id}` or
+    /// `{vdl2.freq}` are replaced with values from each message.
+    #[clap(long, env = "AB_MQTT_TOPIC", default_value = "acars/{type}")]
+    pub mqtt_topic: String,
+
+    /// MQTT `QoS` level for published messages: 0, 1 or 2.
+    #[clap(long, env = "AB_MQTT_QOS", default_value = "0", value_parser = clap::value_parser!(u8).range(0..=2))]
+    pub mqtt_qos: u8,
+
+    /// Publish MQTT messages with the retained flag set.
+    #[clap(long, env = "AB_MQTT_RETAIN")]
+    pub mqtt_retain: bool,
+
+    #[clap(long, env = "AB_MQTT_USERNAME")]
+    pub mqtt_username: Option<String>,
+
+    #[clap(long, env = "AB_MQTT_PASSWORD", requires = "mqtt_username")]
+    pub mqtt_password: Option<String>,
+
+    /// MQTT client id. Defaults to `acars-bridge-<pid>`.
+    #[clap(long, env = "AB_MQTT_CLIENT_ID")]
+    pub mqtt_client_id: Option<String>,
+
+    /// MQTT keep-alive interval, in seconds.
+    #[clap(long, env = "AB_MQTT_KEEP_ALIVE", default_value = "30", value_parser = clap::value_parser!(u64).range(5..))]
+    pub mqtt_keep_alive: u64,
+
+    /// Messages buffered per WebSocket client. A client that falls further
+    /// behind skips the oldest messages rather than slowing the others.
+    #[clap(long, env = "AB_WS_CLIENT_BUFFER", default_value = "256", value_parser = clap::value_parser!(u64).range(1..))]
+    pub ws_client_buffer: u64,
+
+    /// Recent messages the SSE output keeps so clients reconnecting with
+    /// `Last-Event-ID` can catch up. `0` disables resume.
+    #[clap(long, env = "AB_SSE_HISTORY", default_value = "100")]
+    pub sse_history: u64,
+
+    /// Messages buffered per SSE client. A client that falls further behind
+    /// skips the oldest messages rather than slowing the others.
+    #[clap(long, env = "AB_SSE_CLIENT_BUFFER", default_value = "256", value_parser = clap::value_parser!(u64).range(1..))]
+    pub sse_client_buffer: u64,
+
+    /// Record the raw input, with receive timestamps and sources, to this
+    /// capture file. Appends if the file is already a capture.
+    #[clap(long, env = "AB_RECORD")]
+    pub record: Option<String>,
+
+    /// Replay speed relative to the recorded timing, e.g. `10` for ten times
+    /// as fast. `0` replays as fast as possible.
+    #[clap(long, env = "AB_REPLAY_SPEED", default_value = "1")]
+    pub replay_speed: f64,
+
+    /// Start the replay again from the beginning when it reaches the end.
+    #[clap(long, env = "AB_REPLAY_LOOP")]
+    pub replay_loop: bool,
+
+    /// Where the tail input keeps its read offset between runs. Defaults to
+    /// the followed file's path with `.offset` appended.
+    #[clap(long, env = "AB_TAIL_STATE_FILE")]
+    pub tail_state_file: Option<String>,
+
+    /// Where the tail input starts when there is no saved offset: `end` or
+    /// `beginning`.
+    #[clap(long, env = "AB_TAIL_START", default_value = "end")]
+    pub tail_start: String,
+
+    /// How often the tail input checks the file, in milliseconds.
+    #[clap(long, env = "AB_TAIL_POLL_MS", default_value = "250", value_parser = clap::value_parser!(u64).range(10..))]
+    pub tail_poll_ms: u64,
+
+    /// Name prefix for the file output's active file and rotated segments.
+    #[clap(long, env = "AB_FILE_PREFIX", default_value = "acars")]
+    pub file_prefix: String,
+
+    /// Rotate the output file when the clock crosses a multiple of this many
+    /// seconds (UTC), e.g. 3600 for hourly. `0` disables time rotation.
+    #[clap(long, env = "AB_FILE_ROTATE_INTERVAL", default_value = "86400")]
+    pub file_rotate_interval: u64,
+
+    /// Rotate the output file before it grows past this many MiB. `0`
+    /// disables size rotation.
+    #[clap(long, env = "AB_FILE_MAX_SIZE_MB", default_value = "0")]
+    pub file_max_size_mb: u64,
+
+    /// Compression for rotated segments: `none`, `gzip` or `zstd`.
+    #[clap(long, env = "AB_FILE_COMPRESSION", default_value = "none")]
+    pub file_compression: String,
+
+    /// Rotated segments to keep. `0` keeps all of them.
+    #[clap(long, env = "AB_FILE_RETAIN_COUNT", default_value = "0")]
+    pub file_retain_count: u64,
+
+    /// Delete rotated segments older than this many days. `0` keeps them
+    /// regardless of age.
+    #[clap(long, env = "AB_FILE_RETAIN_DAYS", default_value = "0")]
+    pub file_retain_days: u64,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Send synthetic decoder messages to the destination instead of
+    /// bridging a source, for load testing.
+    Generate(GenerateArgs),
+    /// Print messages from the source to the terminal, decoded and colored
+    /// by type, instead of forwarding them.
+    Tap(TapArgs),
+    /// Check the flags, resolve the hosts and try the binds and files the
+    /// bridge would use, report every problem found, and exit without
+    /// starting it.
+    Check,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct GenerateArgs {
+    /// Messages per second. `0` sends as fast as the destination accepts
+    /// them.
+    #[clap(long, env = "AB_GENERATE_RATE", default_value = "10")]
+    pub rate: f64,
+
+    /// Stop after this many messages. `0` runs until stopped.
+    #[clap(long, env = "AB_GENERATE_COUNT", default_value = "0")]
+    pub count: u64,
+
+    /// Decoder formats to mix: `acarsdec`, `dumpvdl2` and/or `dumphfdl`,
+    /// comma-separated.
+    #[clap(
+        long,
+        env = "AB_GENERATE_FORMATS",
+        value_delimiter = ',',
+        default_value = "acarsdec,dumpvdl2,dumphfdl"
+    )]
+    pub formats: Vec<String>,
+
+    /// Seed for the message content, so a run can be repeated. Different
+    /// every run by default.
+    #[clap(long, env = "AB_GENERATE_SEED")]
+    pub seed: Option<u64>,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct TapArgs {
+    /// Only print messages matching `field=value`, using the field names of
+    /// the MQTT topic template. May be repeated; separate multiple filters
+    /// in the environment variable with `;`.
+    #[clap(long = "filter", env = "AB_TAP_FILTER", value_delimiter = ';')]
+    pub filters: Vec<String>,
+
+    /// When to color the output: `auto` (when printing to a terminal and
+    /// `NO_COLOR` is unset), `always` or `never`.
+    #[clap(long, env = "AB_TAP_COLOR", default_value = "auto")]
+    pub color: String,
+
+    /// Print each message's full JSON, indented, under its summary line.
+    #[clap(long, env = "AB_TAP_JSON")]
+    pub json: bool,
 }
 
 impl Config {
     pub fn show_config(&self) {
         debug!("Log Level: {}", self.log_level);
-        debug!("Source Host: {}", self.source_host);
-        debug!("Source Port: {}", self.source_port);
-        debug!("Source Protocol: {}", self.source_protocol);
+        debug!("Command: {:?}", self.command);
+        debug!("UI: {}", self.ui);
+        debug!("Config File: {:?}", self.config_file);
+        debug!("Source: {:?}", self.source);
+        debug!("Source Host: {:?}", self.source_host);
+        debug!("Source Port: {:?}", self.source_port);
+        debug!("Source Protocol: {:?}", self.source_protocol);
+        debug!("Destination: {:?}", self.destination);
         debug!("Destination Host: {:?}", self.destination_host);
         debug!("Destination Port: {:?}", self.destination_port);
         debug!("Destination Protocol: {:?}", self.destination_protocol);
+        debug!("Source Backoff: {:?}", self.source_backoff);
+        debug!("Destination Backoff: {:?}", self.destination_backoff);
         debug!("Stat Interval: {}", self.stat_interval);
         debug!("Channel Capacity: {}", self.channel_capacity);
+        debug!("Drain Timeout: {}", self.drain_timeout);
+        debug!("Spill File: {:?}", self.spill_file);
+        debug!("TCP Batch Size: {}", self.tcp_batch_size);
+        debug!("TCP Batch Delay (ms): {}", self.tcp_batch_delay_ms);
+        debug!("ZMQ Frame Mode: {}", self.zmq_frame_mode);
+        debug!("ZMQ HWM: {:?}", self.zmq_hwm);
+        debug!("ZMQ Linger (ms): {:?}", self.zmq_linger_ms);
+        debug!(
+            "ZMQ Reconnect Interval (ms): {:?}",
+            self.zmq_reconnect_ivl_ms
+        );
+        debug!(
+            "ZMQ Reconnect Interval Max (ms): {:?}",
+            self.zmq_reconnect_ivl_max_ms
+        );
+        debug!("ZMQ TCP Keepalive Idle: {:?}", self.zmq_tcp_keepalive_idle);
+        debug!(
+            "ZMQ TCP Keepalive Interval: {:?}",
+            self.zmq_tcp_keepalive_intvl
+        );
+        debug!("ZMQ TCP Keepalive Count: {:?}", self.zmq_tcp_keepalive_cnt);
+        debug!("HTTP Path: {}", self.http_path);
+        debug!("HTTP TLS: {}", self.http_tls);
+        debug!("HTTP Headers: {}", self.http_headers.len());
+        debug!(
+            "HTTP Bearer Token Set: {}",
+            self.http_bearer_token.is_some()
+        );
+        debug!("HTTP Basic Auth Set: {}", self.http_basic_auth.is_some());
+        debug!("HTTP Batch Size: {}", self.http_batch_size);
+        debug!("HTTP Batch Timeout (ms): {}", self.http_batch_timeout_ms);
+        debug!("HTTP Max Retries: {}", self.http_max_retries);
+        debug!("HTTP Timeout: {}", self.http_timeout);
+        debug!("MQTT Topic: {}", self.mqtt_topic);
+        debug!("MQTT QoS: {}", self.mqtt_qos);
+        debug!("MQTT Retain: {}", self.mqtt_retain);
+        debug!("MQTT Username: {:?}", self.mqtt_username);
+        debug!("MQTT Password Set: {}", self.mqtt_password.is_some());
+        debug!("MQTT Client ID: {:?}", self.mqtt_client_id);
+        debug!("MQTT Keep Alive: {}", self.mqtt_keep_alive);
+        debug!("WebSocket Client Buffer: {}", self.ws_client_buffer);
+        debug!("SSE History: {}", self.sse_history);
+        debug!("SSE Client Buffer: {}", self.sse_client_buffer);
+        debug!("Record: {:?}", self.record);
+        debug!("Replay Speed: {}", self.replay_speed);
+        debug!("Replay Loop: {}", self.replay_loop);
+        debug!("Tail State File: {:?}", self.tail_state_file);
+        debug!("Tail Start: {}", self.tail_start);
+        debug!("Tail Poll MS: {}", self.tail_poll_ms);
+        debug!("File Prefix: {}", self.file_prefix);
+        debug!("File Rotate Interval: {}", self.file_rotate_interval);
+        debug!("File Max Size MB: {}", self.file_max_size_mb);
+        debug!("File Compression: {}", self.file_compression);
+        debug!("File Retain Count: {}", self.file_retain_count);
+        debug!("File Retain Days: {}", self.file_retain_days);
         debug!("Would start output server: {}", self.is_destination_set());
     }
 
@@ -61,18 +447,43 @@ impl Config {
     }
 
     #[must_use]
-    pub fn get_source_host(&self) -> &str {
-        &self.source_host
+    pub fn get_ui(&self) -> &str {
+        &self.ui
+    }
+
+    #[must_use]
+    pub const fn get_config_file(&self) -> &Option<String> {
+        &self.config_file
+    }
+
+    #[must_use]
+    pub const fn get_command(&self) -> Option<&Command> {
+        self.command.as_ref()
+    }
+
+    #[must_use]
+    pub fn get_source(&self) -> Option<&str> {
+        self.source.as_deref()
+    }
+
+    #[must_use]
+    pub fn get_source_host(&self) -> Option<&str> {
+        self.source_host.as_deref()
     }
 
     #[must_use]
-    pub const fn get_source_port(&self) -> u16 {
+    pub const fn get_source_port(&self) -> Option<u16> {
         self.source_port
     }
 
     #[must_use]
-    pub fn get_source_protocol(&self) -> &str {
-        &self.source_protocol
+    pub fn get_source_protocol(&self) -> Option<&str> {
+        self.source_protocol.as_deref()
+    }
+
+    #[must_use]
+    pub fn get_destination(&self) -> Option<&str> {
+        self.destination.as_deref()
     }
 
     #[must_use]
@@ -90,6 +501,16 @@ impl Config {
         &self.destination_protocol
     }
 
+    #[must_use]
+    pub const fn get_source_backoff(&self) -> &Option<String> {
+        &self.source_backoff
+    }
+
+    #[must_use]
+    pub const fn get_destination_backoff(&self) -> &Option<String> {
+        &self.destination_backoff
+    }
+
     #[must_use]
     pub const fn get_stat_interval(&self) -> u64 {
         self.stat_interval
@@ -104,9 +525,421 @@ impl Config {
     }
 
     #[must_use]
-    pub const fn is_destination_set(&self) -> bool {
-        self.destination_host.is_some()
-            && self.destination_port.is_some()
-            && self.destination_protocol.is_some()
+    pub const fn get_drain_timeout(&self) -> u64 {
+        self.drain_timeout
+    }
+
+    #[must_use]
+    pub const fn get_spill_file(&self) -> &Option<String> {
+        &self.spill_file
+    }
+
+    #[must_use]
+    pub fn get_tcp_batch_size(&self) -> NonZeroUsize {
+        usize::try_from(self.tcp_batch_size)
+            .ok()
+            .and_then(NonZeroUsize::new)
+            .unwrap_or(NonZeroUsize::MAX)
+    }
+
+    #[must_use]
+    pub const fn get_tcp_batch_delay_ms(&self) -> u64 {
+        self.tcp_batch_delay_ms
+    }
+
+    #[must_use]
+    pub fn get_zmq_frame_mode(&self) -> &str {
+        &self.zmq_frame_mode
+    }
+
+    #[must_use]
+    pub const fn get_zmq_hwm(&self) -> Option<i32> {
+        self.zmq_hwm
+    }
+
+    #[must_use]
+    pub const fn get_zmq_linger_ms(&self) -> Option<i32> {
+        self.zmq_linger_ms
+    }
+
+    #[must_use]
+    pub const fn get_zmq_reconnect_ivl_ms(&self) -> Option<i32> {
+        self.zmq_reconnect_ivl_ms
+    }
+
+    #[must_use]
+    pub const fn get_zmq_reconnect_ivl_max_ms(&self) -> Option<i32> {
+        self.zmq_reconnect_ivl_max_ms
+    }
+
+    #[must_use]
+    pub const fn get_zmq_tcp_keepalive_idle(&self) -> Option<i32> {
+        self.zmq_tcp_keepalive_idle
+    }
+
+    #[must_use]
+    pub const fn get_zmq_tcp_keepalive_intvl(&self) -> Option<i32> {
+        self.zmq_tcp_keepalive_intvl
+    }
+
+    #[must_use]
+    pub const fn get_zmq_tcp_keepalive_cnt(&self) -> Option<i32> {
+        self.zmq_tcp_keepalive_cnt
     }
+
+    #[must_use]
+    pub fn get_http_path(&self) -> &str {
+        &self.http_path
+    }
+
+    #[must_use]
+    pub const fn get_http_tls(&self) -> bool {
+        self.http_tls
+    }
+
+    #[must_use]
+    pub fn get_http_headers(&self) -> &[String] {
+        &self.http_headers
+    }
+
+    #[must_use]
+    pub const fn get_http_bearer_token(&self) -> &Option<String> {
+        &self.http_bearer_token
+    }
+
+    #[must_use]
+    pub const fn get_http_basic_auth(&self) -> &Option<String> {
+        &self.http_basic_auth
+    }
+
+    #[must_use]
+    pub fn get_http_batch_size(&self) -> usize {
+        usize::try_from(self.http_batch_size).unwrap_or(usize::MAX)
+    }
+
+    #[must_use]
+    pub const fn get_http_batch_timeout_ms(&self) -> u64 {
+        self.http_batch_timeout_ms
+    }
+
+    #[must_use]
+    pub const fn get_http_max_retries(&self) -> u32 {
+        self.http_max_retries
+    }
+
+    #[must_use]
+    pub const fn get_http_timeout(&self) -> u64 {
+        self.http_timeout
+    }
+
+    #[must_use]
+    pub fn get_mqtt_topic(&self) -> &str {
+        &self.mqtt_topic
+    }
+
+    #[must_use]
+    pub const fn get_mqtt_qos(&self) -> u8 {
+        self.mqtt_qos
+    }
+
+    #[must_use]
+    pub const fn get_mqtt_retain(&self) -> bool {
+        self.mqtt_retain
+    }
+
+    #[must_use]
+    pub const fn get_mqtt_username(&self) -> &Option<String> {
+        &self.mqtt_username
+    }
+
+    #[must_use]
+    pub const fn get_mqtt_password(&self) -> &Option<String> {
+        &self.mqtt_password
+    }
+
+    #[must_use]
+    pub const fn get_mqtt_client_id(&self) -> &Option<String> {
+        &self.mqtt_client_id
+    }
+
+    #[must_use]
+    pub const fn get_mqtt_keep_alive(&self) -> u64 {
+        self.mqtt_keep_alive
+    }
+
+    #[must_use]
+    pub fn get_ws_client_buffer(&self) -> usize {
+        usize::try_from(self.ws_client_buffer).unwrap_or(usize::MAX)
+    }
+
+    #[must_use]
+    pub fn get_sse_history(&self) -> usize {
+        usize::try_from(self.sse_history).unwrap_or(usize::MAX)
+    }
+
+    #[must_use]
+    pub fn get_sse_client_buffer(&self) -> usize {
+        usize::try_from(self.sse_client_buffer).unwrap_or(usize::MAX)
+    }
+
+    #[must_use]
+    pub const fn get_record(&self) -> &Option<String> {
+        &self.record
+    }
+
+    #[must_use]
+    pub const fn get_replay_speed(&self) -> f64 {
+        self.replay_speed
+    }
+
+    #[must_use]
+    pub const fn get_replay_loop(&self) -> bool {
+        self.replay_loop
+    }
+
+    #[must_use]
+    pub const fn get_tail_state_file(&self) -> &Option<String> {
+        &self.tail_state_file
+    }
+
+    #[must_use]
+    pub fn get_tail_start(&self) -> &str {
+        &self.tail_start
+    }
+
+    #[must_use]
+    pub const fn get_tail_poll_ms(&self) -> u64 {
+        self.tail_poll_ms
+    }
+
+    #[must_use]
+    pub fn get_file_prefix(&self) -> &str {
+        &self.file_prefix
+    }
+
+    #[must_use]
+    pub const fn get_file_rotate_interval(&self) -> Option<std::time::Duration> {
+        if self.file_rotate_interval == 0 {
+            None
+        } else {
+            Some(std::time::Duration::from_secs(self.file_rotate_interval))
+        }
+    }
+
+    #[must_use]
+    pub const fn get_file_max_size(&self) -> Option<u64> {
+        if self.file_max_size_mb == 0 {
+            None
+        } else {
+            Some(self.file_max_size_mb.saturating_mul(1024 * 1024))
+        }
+    }
+
+    #[must_use]
+    pub fn get_file_compression(&self) -> &str {
+        &self.file_compression
+    }
+
+    #[must_use]
+    pub fn get_file_retain_count(&self) -> Option<usize> {
+        (self.file_retain_count != 0)
+            .then(|| usize::try_from(self.file_retain_count).unwrap_or(usize::MAX))
+    }
+
+    #[must_use]
+    pub const fn get_file_retain_age(&self) -> Option<std::time::Duration> {
+        if self.file_retain_days == 0 {
+            None
+        } else {
+            Some(std::time::Duration::from_hours(
+                self.file_retain_days.saturating_mul(24),
+            ))
+        }
+    }
+
+    /// A destination needs a URL, or a protocol and a host unless the
+    /// protocol has no use for one (`stdout`).
+    #[must_use]
+    pub fn is_destination_set(&self) -> bool {
+        if self.destination.is_some() {
+            return true;
+        }
+        match (&self.destination_host, &self.destination_protocol) {
+            (Some(_), Some(_)) => true,
+            (None, Some(protocol)) => crate::transport::registry()
+                .find(protocol)
+                .is_ok_and(|transport| !transport.needs_host()),
+            _ => false,
+        }
+    }
+}
+
+/// Values the environment and clap accept for a flag that is on.
+pub const TRUE_VALUES: [&str; 4] = ["true", "yes", "on", "1"];
+/// Values the environment and clap accept for a flag that is off.
+pub const FALSE_VALUES: [&str; 4] = ["false", "no", "off", "0"];
+
+impl Config {
+    /// Parse the command line and environment, then the `--config-file` if
+    /// one is given. Exits with clap's usage message on a bad flag, like
+    /// `Config::parse`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the config file can't be read or its settings
+    /// don't parse.
+    pub fn load() -> Result<Self> {
+        let args: Vec<OsString> = std::env::args_os().collect();
+        let matches = Self::command().get_matches_from(&args);
+        Self::with_config_file(&args, &matches)
+    }
+
+    /// Read the configuration again for a reload. Only the config file can
+    /// have changed; the command line and environment are fixed for the life
+    /// of the process.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the configuration no longer parses.
+    pub fn reload() -> Result<Self> {
+        let args: Vec<OsString> = std::env::args_os().collect();
+        let matches = Self::command().try_get_matches_from(&args)?;
+        Self::with_config_file(&args, &matches)
+    }
+
+    /// Settings that are only read at startup and differ in `other`.
+    #[must_use]
+    pub fn restart_only_changes(&self, other: &Self) -> Vec<&'static str> {
+        [
+            ("--log-level", self.log_level != other.log_level),
+            ("--ui", self.ui != other.ui),
+            ("--config-file", self.config_file != other.config_file),
+            ("--stat-interval", self.stat_interval != other.stat_interval),
+            (
+                "--channel-capacity",
+                self.channel_capacity != other.channel_capacity,
+            ),
+            ("--record", self.record != other.record),
+        ]
+        .into_iter()
+        .filter_map(|(flag, changed)| changed.then_some(flag))
+        .collect()
+    }
+
+    fn with_config_file(args: &[OsString], matches: &ArgMatches) -> Result<Self> {
+        let config = Self::from_arg_matches(matches)?;
+        let Some(path) = &config.config_file else {
+            return Ok(config);
+        };
+        // The file's settings become flags around the real ones, so clap
+        // applies them exactly as if they had been typed: the bridge's ahead
+        // of everything, the subcommand's after its name.
+        let file = file_arguments(Path::new(path), matches)?;
+        let mut combined = args.first().cloned().into_iter().collect::<Vec<_>>();
+        combined.extend(file.bridge);
+        combined.extend(args.iter().skip(1).cloned());
+        combined.extend(file.subcommand);
+        // A flag that is off has no argument to give, so it is turned off
+        // by no longer reading it from the environment.
+        let mut command = Self::command();
+        for (subcommand, id) in file.off {
+            command = match subcommand {
+                Some(name) => {
+                    command.mut_subcommand(name, |sub| sub.mut_arg(id, |arg| arg.env(None)))
+                }
+                None => command.mut_arg(id, |arg| arg.env(None)),
+            };
+        }
+        command
+            .try_get_matches_from(combined)
+            .map_err(Error::from)
+            .and_then(|matches| Ok(Self::from_arg_matches(&matches)?))
+            .with_context(|| format!("Error in config file {path}"))
+    }
+}
+
+/// The settings in a config file, as flags, skipping any that were given on
+/// the command line.
+#[derive(Default)]
+struct FileArguments {
+    /// Flags for the bridge.
+    bridge: Vec<OsString>,
+    /// Flags for the subcommand being run. Settings for another subcommand
+    /// are ignored, as they would be in the environment.
+    subcommand: Vec<OsString>,
+    /// The flags the file turns off, by subcommand (`None` for the bridge)
+    /// and id, so that the environment can't turn them on.
+    off: Vec<(Option<String>, String)>,
+}
+
+fn file_arguments(path: &Path, matches: &ArgMatches) -> Result<FileArguments> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Error reading config file {}", path.display()))?;
+    let command = Config::command();
+    let mut arguments = FileArguments::default();
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let at = format!("{}:{}", path.display(), number + 1);
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| Error::msg(format!("{at}: expected KEY=value")))?;
+        let (key, value) = (key.trim(), unquote(value.trim()));
+        let with_key = |arg: &&clap::Arg| arg.get_env() == Some(OsStr::new(key));
+        let (arg, matches, into, subcommand) =
+            if let Some(arg) = command.get_arguments().find(with_key) {
+                (arg, matches, &mut arguments.bridge, None)
+            } else {
+                let (name, arg) = command
+                    .get_subcommands()
+                    .find_map(|sub| Some((sub.get_name(), sub.get_arguments().find(with_key)?)))
+                    .ok_or_else(|| Error::msg(format!("{at}: unknown setting {key}")))?;
+                match matches.subcommand() {
+                    Some((running, sub_matches)) if running == name => (
+                        arg,
+                        sub_matches,
+                        &mut arguments.subcommand,
+                        Some(name.to_string()),
+                    ),
+                    _ => continue,
+                }
+            };
+        if arg.get_id() == "config_file" {
+            return Err(Error::msg(format!(
+                "{at}: {key} can't be set in a config file"
+            )));
+        }
+        if matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine) {
+            continue;
+        }
+        let long = arg.get_long().unwrap_or_default();
+        if arg.get_action().takes_values() {
+            into.push(format!("--{long}={value}").into());
+        } else if TRUE_VALUES.contains(&value.to_lowercase().as_str()) {
+            into.push(format!("--{long}").into());
+        } else if FALSE_VALUES.contains(&value.to_lowercase().as_str()) {
+            arguments
+                .off
+                .push((subcommand, arg.get_id().as_str().to_string()));
+        } else {
+            return Err(Error::msg(format!(
+                "{at}: {key} must be true or false, not {value}"
+            )));
+        }
+    }
+    Ok(arguments)
+}
+
+/// Strip one pair of matching quotes, as a shell or Docker env file would.
+fn unquote(value: &str) -> &str {
+    ['"', '\'']
+        .iter()
+        .find_map(|quote| {
+            value
+                .strip_prefix(*quote)
+                .and_then(|rest| rest.strip_suffix(*quote))
+        })
+        .unwrap_or(value)
 }
@@ -0,0 +1,483 @@
+// Copyright (c) 2024-2026 Fred Clausen
+//
+// Licensed under the MIT license: https://opensource.org/licenses/MIT
+// Permission is granted to use, copy, modify, and redistribute the work.
+// Full license information available in the project LICENSE file.
+
+//! The full-screen terminal dashboard shown with `--ui dashboard`.
+//!
+//! The dashboard only reads: supervisor states from their
+//! `StatusReporter`s, totals from `Stats`, and the bridge channel through
+//! weak senders, so it never keeps the pipeline alive during shutdown. Recent
+//! messages are copied by a monitor task main puts between the inputs and
+//! the output. While the dashboard is up, log records go to a panel on it
+//! instead of standard error.
+
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use log::{LevelFilter, Log, Metadata, Record};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Cell, Gauge, Paragraph, Row, Sparkline, Table};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use tokio::sync::mpsc::{Receiver, Sender, WeakSender};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+
+use crate::message::Message;
+use crate::stats::StatsCounters;
+use crate::status::{SupervisorState, SupervisorStatus, format_elapsed};
+use crate::tap::TapOptions;
+
+/// Log lines kept for the log panel.
+const LOG_LINES: usize = 200;
+/// Messages kept for the recent messages panel.
+const RECENT_MESSAGES: usize = 200;
+/// Seconds of message rates kept for the sparklines.
+const RATE_SAMPLES: usize = 300;
+const REDRAW_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Log records captured while the dashboard owns the terminal.
+#[derive(Clone, Default)]
+pub struct LogBuffer {
+    lines: Arc<Mutex<VecDeque<String>>>,
+    /// Set once the dashboard has closed; records are then written to
+    /// standard error as usual.
+    passthrough: Arc<AtomicBool>,
+}
+
+struct BufferLogger {
+    level: LevelFilter,
+    buffer: LogBuffer,
+}
+
+impl Log for BufferLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let line = format!(
+            "[{:<5}][{}]{}",
+            record.level(),
+            Local::now().format("%Y-%m-%dT%H:%M:%S"),
+            record.args()
+        );
+        if self.buffer.passthrough.load(Ordering::Relaxed) {
+            eprintln!("{line}");
+            return;
+        }
+        let mut lines = self
+            .buffer
+            .lines
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if lines.len() == LOG_LINES {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+
+    fn flush(&self) {}
+}
+
+/// Route log records at `level` and above into a buffer for the log panel,
+/// in place of the usual standard error logger.
+///
+/// # Errors
+///
+/// Returns an error if a logger has already been installed.
+pub fn install_logger(level: LevelFilter) -> Result<LogBuffer> {
+    let buffer = LogBuffer::default();
+    log::set_boxed_logger(Box::new(BufferLogger {
+        level,
+        buffer: buffer.clone(),
+    }))
+    .map_err(|e| anyhow::anyhow!("Error installing dashboard logger: {e}"))?;
+    log::set_max_level(level);
+    Ok(buffer)
+}
+
+/// Whether the dashboard can take over this terminal.
+#[must_use]
+pub fn terminal_available() -> bool {
+    std::io::stdout().is_terminal()
+}
+
+/// A message and when the monitor saw it.
+type RecentMessage = (DateTime<Local>, Message);
+
+/// Messages recently passed to the output, newest last.
+#[derive(Clone, Default)]
+pub struct RecentMessages {
+    messages: Arc<Mutex<VecDeque<RecentMessage>>>,
+}
+
+impl RecentMessages {
+    fn push(&self, message: &Message) {
+        let mut messages = self.messages.lock().unwrap_or_else(PoisonError::into_inner);
+        if messages.len() == RECENT_MESSAGES {
+            messages.pop_front();
+        }
+        messages.push_back((Local::now(), message.clone()));
+    }
+
+    /// The newest `count` messages, oldest first.
+    fn newest(&self, count: usize) -> Vec<RecentMessage> {
+        let messages = self.messages.lock().unwrap_or_else(PoisonError::into_inner);
+        messages
+            .iter()
+            .skip(messages.len().saturating_sub(count))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Pass messages from the bridge channel on to `output`, copying each into
+/// `recent` and counting it in `forwarded`.
+///
+/// The task ends once every Sender for `receiver` is gone, dropping
+/// `output` in turn, so the output drains at shutdown just as it would
+/// without the monitor in between.
+fn spawn_monitor(
+    mut receiver: Receiver<Message>,
+    output: Sender<Message>,
+    recent: RecentMessages,
+    forwarded: Arc<AtomicU64>,
+) {
+    tokio::spawn(async move {
+        while let Some(message) = receiver.recv().await {
+            recent.push(&message);
+            if output.send(message).await.is_err() {
+                break;
+            }
+            forwarded.fetch_add(1, Ordering::Relaxed);
+        }
+        debug!("[DASHBOARD] Bridge channel closed; monitor exiting");
+    });
+}
+
+/// Put the monitor on the bridge channel. It passes messages on through a
+/// new channel of `capacity`, whose receiver is returned for the output to
+/// read.
+pub fn monitor(
+    receiver: Receiver<Message>,
+    output: &mut Side,
+    capacity: usize,
+    recent: RecentMessages,
+    forwarded: Arc<AtomicU64>,
+) -> Receiver<Message> {
+    let (sender, receiver_for_output) = tokio::sync::mpsc::channel(capacity);
+    output.queue = Some(sender.downgrade());
+    spawn_monitor(receiver, sender, recent, forwarded);
+    receiver_for_output
+}
+
+/// One side of the bridge as the dashboard shows it.
+pub struct Side {
+    pub status: watch::Receiver<SupervisorStatus>,
+    /// The channel feeding this side, for its queue depth.
+    pub queue: Option<WeakSender<Message>>,
+}
+
+/// Everything the dashboard reads.
+pub struct Dashboard {
+    pub input: Side,
+    pub output: Side,
+    pub counters: StatsCounters,
+    /// Messages handed to the output channel by the monitor.
+    pub forwarded: Arc<AtomicU64>,
+    pub recent: RecentMessages,
+    pub logs: LogBuffer,
+}
+
+/// Messages-per-second history for one sparkline.
+#[derive(Default)]
+struct Rate {
+    samples: VecDeque<u64>,
+    last_total: u64,
+}
+
+impl Rate {
+    fn sample(&mut self, total: u64) {
+        if self.samples.len() == RATE_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples
+            .push_back(total.saturating_sub(self.last_total));
+        self.last_total = total;
+    }
+
+    fn current(&self) -> u64 {
+        self.samples.back().copied().unwrap_or_default()
+    }
+
+    /// The newest samples that fit in `width` columns, oldest first.
+    fn newest(&self, width: u16) -> Vec<u64> {
+        self.samples
+            .iter()
+            .skip(self.samples.len().saturating_sub(usize::from(width)))
+            .copied()
+            .collect()
+    }
+}
+
+/// Messages queued in a channel and its capacity.
+fn queue_depth(queue: Option<&WeakSender<Message>>) -> (usize, usize) {
+    queue
+        .and_then(WeakSender::upgrade)
+        .map_or((0, 0), |sender| {
+            (
+                sender.max_capacity() - sender.capacity(),
+                sender.max_capacity(),
+            )
+        })
+}
+
+const fn state_color(state: SupervisorState) -> Color {
+    match state {
+        SupervisorState::Starting => Color::Yellow,
+        SupervisorState::Running => Color::Green,
+        SupervisorState::Backoff { .. } => Color::Red,
+        SupervisorState::Stopped => Color::DarkGray,
+    }
+}
+
+impl Dashboard {
+    /// Take over the terminal and draw until `done` is cancelled, by main at
+    /// shutdown or from here when the user presses `q` or Ctrl-C. Raw mode
+    /// swallows Ctrl-C, so it is handled as a key rather than a signal.
+    #[must_use]
+    pub fn spawn(self, done: CancellationToken) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut terminal = match ratatui::try_init() {
+                Ok(terminal) => terminal,
+                Err(e) => {
+                    self.logs.passthrough.store(true, Ordering::Relaxed);
+                    error!("[DASHBOARD] Error opening the terminal: {e}; stopping");
+                    done.cancel();
+                    return;
+                }
+            };
+            self.run(&mut terminal, &done).await;
+            if let Err(e) = ratatui::try_restore() {
+                eprintln!("Error restoring the terminal: {e}");
+            }
+            self.logs.passthrough.store(true, Ordering::Relaxed);
+        })
+    }
+
+    async fn run(&self, terminal: &mut DefaultTerminal, done: &CancellationToken) {
+        let started = Instant::now();
+        let mut input_rate = Rate {
+            last_total: self.counters.total(),
+            ..Rate::default()
+        };
+        let mut output_rate = Rate::default();
+        let mut next_sample = started + Duration::from_secs(1);
+        let mut ticker = tokio::time::interval(REDRAW_INTERVAL);
+
+        loop {
+            tokio::select! {
+                () = done.cancelled() => return,
+                _ = ticker.tick() => {}
+            }
+
+            if Instant::now() >= next_sample {
+                next_sample += Duration::from_secs(1);
+                input_rate.sample(self.counters.total());
+                // The output has taken whatever the monitor handed over and
+                // is no longer queued.
+                let (queued, _) = queue_depth(self.output.queue.as_ref());
+                let delivered = self
+                    .forwarded
+                    .load(Ordering::Relaxed)
+                    .saturating_sub(queued as u64);
+                output_rate.sample(delivered);
+            }
+
+            if let Err(e) = terminal.draw(|frame| {
+                self.draw(frame, started.elapsed(), &input_rate, &output_rate);
+            }) {
+                self.logs.passthrough.store(true, Ordering::Relaxed);
+                error!("[DASHBOARD] Error drawing: {e}; stopping");
+                done.cancel();
+                return;
+            }
+
+            while event::poll(Duration::ZERO).unwrap_or(false) {
+                let Ok(Event::Key(key)) = event::read() else {
+                    continue;
+                };
+                let ctrl_c =
+                    key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c');
+                if key.kind == KeyEventKind::Press
+                    && (ctrl_c || matches!(key.code, KeyCode::Char('q') | KeyCode::Esc))
+                {
+                    info!("[DASHBOARD] Quit requested");
+                    done.cancel();
+                    return;
+                }
+            }
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame, uptime: Duration, input_rate: &Rate, output_rate: &Rate) {
+        let [supervisors, rates, queue, recent, logs] = Layout::vertical([
+            Constraint::Length(5),
+            Constraint::Length(7),
+            Constraint::Length(3),
+            Constraint::Fill(3),
+            Constraint::Fill(2),
+        ])
+        .areas(frame.area());
+
+        self.draw_supervisors(frame, supervisors, uptime);
+        self.draw_rates(frame, rates, input_rate, output_rate);
+        self.draw_queue(frame, queue);
+        self.draw_recent(frame, recent);
+        self.draw_logs(frame, logs);
+    }
+
+    fn draw_supervisors(&self, frame: &mut Frame, area: Rect, uptime: Duration) {
+        let endpoints = [("Input", &self.input), ("Output", &self.output)];
+        let rows = endpoints.into_iter().map(|(side, endpoint)| {
+            let status = endpoint.status.borrow().clone();
+            if status.endpoint.is_empty() {
+                return Row::new([side, "none"]);
+            }
+            Row::new([
+                Cell::from(side),
+                Cell::from(status.endpoint),
+                Cell::from(status.state.to_string())
+                    .style(Style::default().fg(state_color(status.state))),
+                Cell::from(format_elapsed(status.since.elapsed())),
+                Cell::from(status.restarts.to_string()),
+                Cell::from(status.last_error.unwrap_or_default()),
+            ])
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(6),
+                Constraint::Length(32),
+                Constraint::Length(12),
+                Constraint::Length(9),
+                Constraint::Length(8),
+                Constraint::Fill(1),
+            ],
+        )
+        .header(
+            Row::new(["", "Endpoint", "State", "For", "Restarts", "Last error"])
+                .style(Style::default().add_modifier(Modifier::BOLD)),
+        )
+        .block(Block::bordered().title(format!(
+            " acars-bridge {} | up {} | q to quit ",
+            env!("CARGO_PKG_VERSION"),
+            format_elapsed(uptime)
+        )));
+        frame.render_widget(table, area);
+    }
+
+    fn draw_rates(&self, frame: &mut Frame, area: Rect, input_rate: &Rate, output_rate: &Rate) {
+        let [input_area, output_area] =
+            Layout::horizontal([Constraint::Fill(1), Constraint::Fill(1)]).areas(area);
+
+        let mut title = format!(
+            " Input {}/s, {} total ",
+            input_rate.current(),
+            self.counters.total()
+        );
+        let zmq_frames = self.counters.zmq_frames();
+        if zmq_frames.iter().any(|&count| count > 0) {
+            let _ = write!(
+                title,
+                "| ZMQ frames 1: {} 2: {} 3: {} 4+: {} ",
+                zmq_frames[0], zmq_frames[1], zmq_frames[2], zmq_frames[3]
+            );
+        }
+        let input = Sparkline::default()
+            .block(Block::bordered().title(title))
+            .data(input_rate.newest(input_area.width.saturating_sub(2)))
+            .style(Style::default().fg(Color::Cyan));
+        frame.render_widget(input, input_area);
+
+        let output_title = if self.output.status.borrow().endpoint.is_empty() {
+            " No output ".to_string()
+        } else {
+            format!(" Output {}/s ", output_rate.current())
+        };
+        let output = Sparkline::default()
+            .block(Block::bordered().title(output_title))
+            .data(output_rate.newest(output_area.width.saturating_sub(2)))
+            .style(Style::default().fg(Color::Magenta));
+        frame.render_widget(output, output_area);
+    }
+
+    fn draw_queue(&self, frame: &mut Frame, area: Rect) {
+        let (bridge, bridge_capacity) = queue_depth(self.input.queue.as_ref());
+        let (output, output_capacity) = queue_depth(self.output.queue.as_ref());
+        let queued = bridge + output;
+        let capacity = (bridge_capacity + output_capacity).max(1);
+        let ratio = (f64::from(u32::try_from(queued).unwrap_or(u32::MAX))
+            / f64::from(u32::try_from(capacity).unwrap_or(u32::MAX)))
+        .clamp(0.0, 1.0);
+        let gauge = Gauge::default()
+            .block(Block::bordered().title(" Queued for output "))
+            .gauge_style(Style::default().fg(if ratio > 0.8 {
+                Color::Red
+            } else {
+                Color::Green
+            }))
+            .ratio(ratio)
+            .label(format!("{queued} / {capacity}"));
+        frame.render_widget(gauge, area);
+    }
+
+    fn draw_recent(&self, frame: &mut Frame, area: Rect) {
+        let tap = TapOptions::default();
+        let lines = self
+            .recent
+            .newest(usize::from(area.height.saturating_sub(2)))
+            .into_iter()
+            .map(|(time, message)| Line::from(tap.format_at(&message, time).trim_end().to_string()))
+            .collect::<Vec<_>>();
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title(" Recent messages ")),
+            area,
+        );
+    }
+
+    fn draw_logs(&self, frame: &mut Frame, area: Rect) {
+        let count = usize::from(area.height.saturating_sub(2));
+        let lines = {
+            let lines = self
+                .logs
+                .lines
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            lines
+                .iter()
+                .skip(lines.len().saturating_sub(count))
+                .map(|line| Line::from(line.clone()))
+                .collect::<Vec<_>>()
+        };
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title(" Log ")),
+            area,
+        );
+    }
+}
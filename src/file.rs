@@ -0,0 +1,653 @@
+// Copyright (c) 2024-2026 Fred Clausen
+//
+// Licensed under the MIT license: https://opensource.org/licenses/MIT
+// Permission is granted to use, copy, modify, and redistribute the work.
+// Full license information available in the project LICENSE file.
+
+use anyhow::{Context, Error, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, TimeZone, Utc};
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tokio::fs::{File, OpenOptions};
+use tokio::io::{AsyncWriteExt, BufWriter};
+use tokio::sync::mpsc::Receiver;
+
+use crate::config::Config;
+use crate::endpoint::{Endpoint, parse_setting, unknown_setting};
+use crate::message::Message;
+use crate::serverconfig::OutputServer;
+use crate::serverconfig::OutputServerOptions;
+use crate::serverconfig::TransportOptions;
+use crate::status::StatusReporter;
+use crate::transport::{Checks, Transport};
+
+/// Extension of the active file and of rotated segments before compression.
+const SEGMENT_EXTENSION: &str = "jsonl";
+/// Suffix of a compressed segment that is still being written.
+const PARTIAL_SUFFIX: &str = ".partial";
+
+/// How rotated segments are compressed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FileCompression {
+    #[default]
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl TryFrom<&str> for FileCompression {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "none" => Ok(Self::None),
+            "gzip" | "gz" => Ok(Self::Gzip),
+            "zstd" | "zst" => Ok(Self::Zstd),
+            _ => Err(Error::msg(format!("Unknown file compression: {s}"))),
+        }
+    }
+}
+
+impl FileCompression {
+    /// Extension appended to a segment's name once it is compressed.
+    const fn extension(self) -> Option<&'static str> {
+        match self {
+            Self::None => None,
+            Self::Gzip => Some("gz"),
+            Self::Zstd => Some("zst"),
+        }
+    }
+}
+
+/// Settings for the file output.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileOptions {
+    /// File names start with this: `<prefix>.jsonl` for the active file and
+    /// `<prefix>-<start>.jsonl[.gz|.zst]` for rotated segments.
+    pub prefix: String,
+    /// Rotate when the clock crosses a multiple of this interval (UTC).
+    pub rotate_interval: Option<Duration>,
+    /// Rotate before the active file would grow past this many bytes.
+    pub max_size: Option<u64>,
+    pub compression: FileCompression,
+    /// Rotated segments to keep; the oldest are deleted first.
+    pub retain_count: Option<usize>,
+    /// Rotated segments older than this are deleted.
+    pub retain_age: Option<Duration>,
+}
+
+impl Default for FileOptions {
+    fn default() -> Self {
+        Self {
+            prefix: "acars".to_string(),
+            rotate_interval: Some(Duration::from_hours(24)),
+            max_size: None,
+            compression: FileCompression::None,
+            retain_count: None,
+            retain_age: None,
+        }
+    }
+}
+
+/// The active segment of the file output.
+pub struct ArchiveFile {
+    dir: PathBuf,
+    writer: BufWriter<File>,
+    /// Bytes in the active file, including what is still buffered.
+    size: u64,
+    /// When the active segment was started; rotated segments are named
+    /// after it.
+    started: DateTime<Utc>,
+}
+
+/// Start of the rotation period containing `time`.
+fn period_start(time: DateTime<Utc>, interval: Duration) -> DateTime<Utc> {
+    let secs = i64::try_from(interval.as_secs()).unwrap_or(i64::MAX).max(1);
+    Utc.timestamp_opt(time.timestamp().div_euclid(secs) * secs, 0)
+        .single()
+        .unwrap_or(time)
+}
+
+/// Turn a message into exactly one line of JSON. Messages are normally
+/// single-line JSON already and are written untouched; JSON spread over
+/// several lines is compacted, and anything that fails to parse is written
+/// as a JSON string rather than dropped.
+fn json_line<'a>(name: &str, message: &'a str) -> Cow<'a, str> {
+    let message = message.trim_end();
+    match serde_json::from_str::<serde_json::Value>(message) {
+        Ok(_) if !message.contains('\n') => Cow::Borrowed(message),
+        Ok(value) => Cow::Owned(value.to_string()),
+        Err(e) => {
+            warn!("{name}Message is not valid JSON ({e}); writing it as a string");
+            Cow::Owned(serde_json::Value::String(message.to_string()).to_string())
+        }
+    }
+}
+
+fn active_path(dir: &Path, prefix: &str) -> PathBuf {
+    dir.join(format!("{prefix}.{SEGMENT_EXTENSION}"))
+}
+
+/// What follows `.jsonl` in the name of a rotated segment of the output
+/// writing `prefix`, e.g. `""`, `".gz"` or `".zst.partial"`, or `None` if
+/// `file_name` isn't one. Only the names `segment_path` gives match, so
+/// outputs with prefixes like `acars` and `acars-vdl` can share a
+/// directory without claiming each other's segments.
+fn segment_suffix<'a>(file_name: &'a str, prefix: &str) -> Option<&'a str> {
+    let rest = file_name.strip_prefix(prefix)?.strip_prefix('-')?;
+    // `%Y%m%dT%H%M%SZ`
+    let (stamp, rest) = rest.split_at_checked(16)?;
+    let stamped = stamp.bytes().enumerate().all(|(at, byte)| match at {
+        8 => byte == b'T',
+        15 => byte == b'Z',
+        _ => byte.is_ascii_digit(),
+    });
+    if !stamped {
+        return None;
+    }
+    let rest = match rest.strip_prefix('_') {
+        Some(counted) => {
+            let rest = counted.trim_start_matches(|c: char| c.is_ascii_digit());
+            (rest.len() < counted.len()).then_some(rest)?
+        }
+        None => rest,
+    };
+    let suffix = rest.strip_prefix('.')?.strip_prefix(SEGMENT_EXTENSION)?;
+    ["", ".gz", ".zst", ".gz.partial", ".zst.partial"]
+        .contains(&suffix)
+        .then_some(suffix)
+}
+
+/// Whether `file_name` is a rotated segment, compressed or not.
+fn is_segment(file_name: &str, prefix: &str) -> bool {
+    segment_suffix(file_name, prefix).is_some_and(|suffix| !suffix.ends_with(PARTIAL_SUFFIX))
+}
+
+/// Pick an unused name for a segment started at `started`. Segments from
+/// the same second (size rotation on a busy feed) get a counter after an
+/// underscore, which sorts after the `.` of the first one's extension so
+/// name order stays rotation order.
+fn segment_path(dir: &Path, prefix: &str, started: DateTime<Utc>) -> PathBuf {
+    let stamp = started.format("%Y%m%dT%H%M%SZ").to_string();
+    let mut stem = format!("{prefix}-{stamp}");
+    let mut n = 0;
+    loop {
+        let plain = dir.join(format!("{stem}.{SEGMENT_EXTENSION}"));
+        let taken = ["", ".gz", ".zst"].iter().any(|compressed| {
+            dir.join(format!("{stem}.{SEGMENT_EXTENSION}{compressed}"))
+                .exists()
+        });
+        if !taken {
+            return plain;
+        }
+        n += 1;
+        stem = format!("{prefix}-{stamp}_{n:03}");
+    }
+}
+
+/// Compress `path` next to itself and remove the original.
+fn compress(path: &Path, compression: FileCompression) -> Result<PathBuf> {
+    let Some(extension) = compression.extension() else {
+        return Ok(path.to_path_buf());
+    };
+    let mut target = path.as_os_str().to_owned();
+    target.push(format!(".{extension}"));
+    let target = PathBuf::from(target);
+    let mut partial = target.as_os_str().to_owned();
+    partial.push(PARTIAL_SUFFIX);
+    let partial = PathBuf::from(partial);
+
+    let mut source = std::fs::File::open(path)?;
+    let output = std::io::BufWriter::new(std::fs::File::create(&partial)?);
+    let output = match compression {
+        FileCompression::Gzip => {
+            let mut encoder = flate2::write::GzEncoder::new(output, flate2::Compression::default());
+            std::io::copy(&mut source, &mut encoder)?;
+            encoder.finish()?
+        }
+        FileCompression::Zstd => {
+            let mut encoder = zstd::stream::Encoder::new(output, 0)?;
+            std::io::copy(&mut source, &mut encoder)?;
+            encoder.finish()?
+        }
+        FileCompression::None => unreachable!("handled above"),
+    };
+    output.into_inner()?.sync_all()?;
+    std::fs::rename(&partial, &target)?;
+    std::fs::remove_file(path)?;
+    Ok(target)
+}
+
+/// Compress any rotated segments that aren't yet and then apply the
+/// retention limits. Works from a directory listing rather than a list of
+/// new segments, so it also picks up segments left uncompressed when the
+/// bridge was stopped mid-way. Blocking; run it off the runtime.
+fn sweep(dir: &Path, options: &FileOptions, name: &str) {
+    let list = || -> Vec<(PathBuf, String)> {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        let mut segments = entries
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                let file_name = entry.file_name().into_string().ok()?;
+                Some((entry.path(), file_name))
+            })
+            .collect::<Vec<_>>();
+        segments.sort_by(|(_, a), (_, b)| a.cmp(b));
+        segments
+    };
+
+    for (path, file_name) in list() {
+        let Some(suffix) = segment_suffix(&file_name, &options.prefix) else {
+            continue;
+        };
+        if suffix.ends_with(PARTIAL_SUFFIX) {
+            debug!("{name}Removing incomplete {}", path.display());
+            let _ = std::fs::remove_file(&path);
+        } else if options.compression != FileCompression::None && suffix.is_empty() {
+            match compress(&path, options.compression) {
+                Ok(target) => debug!("{name}Compressed {}", target.display()),
+                Err(e) => warn!("{name}Error compressing {}: {e}", path.display()),
+            }
+        }
+    }
+
+    let segments = list()
+        .into_iter()
+        .filter(|(_, file_name)| is_segment(file_name, &options.prefix))
+        .map(|(path, _)| path)
+        .collect::<Vec<_>>();
+    let excess = options
+        .retain_count
+        .map_or(0, |keep| segments.len().saturating_sub(keep));
+    let cutoff = options
+        .retain_age
+        .and_then(|age| SystemTime::now().checked_sub(age));
+    for (index, path) in segments.iter().enumerate() {
+        let expired = cutoff.is_some_and(|cutoff| {
+            std::fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .is_ok_and(|modified| modified < cutoff)
+        });
+        if index < excess || expired {
+            match std::fs::remove_file(path) {
+                Ok(()) => info!("{name}Removed old segment {}", path.display()),
+                Err(e) => warn!("{name}Error removing {}: {e}", path.display()),
+            }
+        }
+    }
+}
+
+impl OutputServerOptions<ArchiveFile> {
+    /// Close the active segment, move it aside and start a new one.
+    async fn rotate(&mut self) -> Result<()> {
+        let name = self.format_name();
+        let prefix = &self.options.get::<FileOptions>().prefix;
+        let dir = self.socket.dir.clone();
+        let active = active_path(&dir, prefix);
+
+        self.socket.writer.flush().await?;
+        self.socket.writer.get_ref().sync_all().await?;
+        let target = segment_path(&dir, prefix, self.socket.started);
+        tokio::fs::rename(&active, &target)
+            .await
+            .with_context(|| format!("{name}Error rotating to {}", target.display()))?;
+        info!("{name}Rotated to {}", target.display());
+
+        self.socket.writer = BufWriter::new(open_active(&active).await?);
+        self.socket.size = 0;
+        self.socket.started = Utc::now();
+        Ok(())
+    }
+
+    /// Write one message, rotating first if it would push the active file
+    /// past the size limit. Returns whether a rotation happened.
+    async fn write_message(&mut self, message: &str) -> Result<bool> {
+        let line = json_line(&self.format_name(), message);
+        let len = line.len() as u64 + 1;
+        let rotate = self
+            .options
+            .get::<FileOptions>()
+            .max_size
+            .is_some_and(|max| self.socket.size > 0 && self.socket.size + len > max);
+        if rotate {
+            self.rotate().await?;
+        }
+        self.append(&line).await?;
+        Ok(rotate)
+    }
+
+    async fn append(&mut self, line: &str) -> std::io::Result<()> {
+        self.socket.writer.write_all(line.as_bytes()).await?;
+        self.socket.writer.write_all(b"\n").await?;
+        self.socket.size += line.len() as u64 + 1;
+        Ok(())
+    }
+
+    /// The write loop. Returns once the bridge channel closes.
+    async fn run(
+        &mut self,
+        receiver: &mut Receiver<Message>,
+        sweeps: &std::sync::mpsc::Sender<()>,
+    ) -> Result<()> {
+        let name = self.format_name();
+        loop {
+            let next_rotation = self
+                .options
+                .get::<FileOptions>()
+                .rotate_interval
+                .map(|interval| {
+                    let next = period_start(Utc::now(), interval)
+                        + chrono::Duration::from_std(interval).unwrap_or(chrono::TimeDelta::MAX);
+                    let wait = (next - Utc::now()).to_std().unwrap_or_default();
+                    tokio::time::Instant::now() + wait
+                });
+
+            tokio::select! {
+                () = async {
+                    match next_rotation {
+                        Some(deadline) => tokio::time::sleep_until(deadline).await,
+                        None => std::future::pending().await,
+                    }
+                } => {
+                    if self.socket.size == 0 {
+                        // Nothing to set aside; the next segment simply
+                        // starts now.
+                        self.socket.started = Utc::now();
+                    } else {
+                        self.rotate().await?;
+                        let _ = sweeps.send(());
+                    }
+                }
+                message = receiver.recv() => {
+                    let Some(message) = message else {
+                        self.socket.writer.flush().await?;
+                        return Ok(());
+                    };
+                    debug!("{name}Received: {message}");
+                    if self.write_message(&message).await? {
+                        let _ = sweeps.send(());
+                    }
+
+                    // Write out whatever else is already queued, then flush,
+                    // so a busy feed isn't flushed line by line and a quiet
+                    // one is never left sitting in the buffer.
+                    while let Ok(message) = receiver.try_recv() {
+                        debug!("{name}Received: {message}");
+                        if self.write_message(&message).await? {
+                            let _ = sweeps.send(());
+                        }
+                    }
+                    self.socket.writer.flush().await?;
+                }
+            }
+        }
+    }
+}
+
+async fn open_active(path: &Path) -> Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await
+        .with_context(|| format!("Error opening {}", path.display()))
+}
+
+#[async_trait]
+impl OutputServer for OutputServerOptions<ArchiveFile> {
+    /// `host` is the directory to write to; `port` is unused.
+    async fn new(host: &str, port: u16, options: &TransportOptions) -> Result<Self, Error> {
+        let dir = PathBuf::from(host);
+        tokio::fs::create_dir_all(&dir)
+            .await
+            .with_context(|| format!("[File Output {host}] Error creating directory"))?;
+        let active = active_path(&dir, &options.get::<FileOptions>().prefix);
+        let file = open_active(&active)
+            .await
+            .with_context(|| format!("[File Output {host}] Error opening output file"))?;
+
+        // Carry on with a file left by an earlier run; it is named after
+        // when it was started, which is as close as the filesystem can say.
+        let metadata = file.metadata().await?;
+        let started = metadata
+            .created()
+            .or_else(|_| metadata.modified())
+            .map_or_else(|_| Utc::now(), DateTime::<Utc>::from);
+
+        Ok(Self {
+            host: host.to_string(),
+            port,
+            socket: ArchiveFile {
+                dir,
+                writer: BufWriter::new(file),
+                size: metadata.len(),
+                started,
+            },
+            options: options.clone(),
+        })
+    }
+
+    async fn watch_queue(mut self, receiver: &mut Receiver<Message>) -> Result<(), Error> {
+        let name = self.format_name();
+        info!(
+            "{name}Writing to {}",
+            active_path(&self.socket.dir, &self.options.get::<FileOptions>().prefix).display()
+        );
+
+        // Compression and retention run one sweep at a time on a blocking
+        // thread. Requests that arrive while a sweep is running are folded
+        // into the next one.
+        let (sweeps, requests) = std::sync::mpsc::channel::<()>();
+        let dir = self.socket.dir.clone();
+        let file_options = self.options.get::<FileOptions>().into_owned();
+        let sweep_name = name.clone();
+        let sweeper = tokio::task::spawn_blocking(move || {
+            while requests.recv().is_ok() {
+                while requests.try_recv().is_ok() {}
+                sweep(&dir, &file_options, &sweep_name);
+            }
+        });
+        let _ = sweeps.send(());
+
+        // A file left over from before a rotation boundary that passed
+        // while the bridge was down belongs to the earlier period.
+        let stale = self
+            .options
+            .get::<FileOptions>()
+            .rotate_interval
+            .is_some_and(|interval| {
+                self.socket.size > 0 && self.socket.started < period_start(Utc::now(), interval)
+            });
+        let result = if stale {
+            self.rotate().await.map(|()| {
+                let _ = sweeps.send(());
+            })
+        } else {
+            Ok(())
+        };
+        let result = match result {
+            Ok(()) => self.run(receiver, &sweeps).await,
+            Err(e) => Err(e),
+        };
+
+        // Let a running sweep finish before returning, so a restarted output
+        // never has two compressing the same segment.
+        drop(sweeps);
+        if let Err(e) = sweeper.await {
+            warn!("{name}Compression task panicked: {e}");
+        }
+        if result.is_ok() {
+            info!("{name}Input channel closed (shutdown); file flushed");
+        }
+        result
+    }
+
+    fn format_name(&self) -> String {
+        format!("[File Output {}] ", self.host)
+    }
+}
+
+/// JSON lines written to a local directory. The host is the directory and
+/// the port is unused.
+pub struct FileTransport;
+
+pub(crate) fn file_compression(config: &Config) -> Result<FileCompression> {
+    FileCompression::try_from(config.get_file_compression())
+        .map_err(|e| anyhow::anyhow!("Error parsing file compression: {e}"))
+}
+
+#[async_trait]
+impl Transport for FileTransport {
+    fn name(&self) -> &'static str {
+        "file"
+    }
+
+    fn needs_port(&self) -> bool {
+        false
+    }
+
+    fn supports_output(&self) -> bool {
+        true
+    }
+
+    fn same_options(&self, old: &TransportOptions, new: &TransportOptions) -> bool {
+        old.get::<FileOptions>() == new.get::<FileOptions>()
+    }
+
+    fn options_from_config(&self, config: &Config, options: &mut TransportOptions) -> Result<()> {
+        options.set(FileOptions {
+            prefix: config.get_file_prefix().to_string(),
+            rotate_interval: config.get_file_rotate_interval(),
+            max_size: config.get_file_max_size(),
+            compression: file_compression(config)?,
+            retain_count: config.get_file_retain_count(),
+            retain_age: config.get_file_retain_age(),
+        });
+        Ok(())
+    }
+
+    fn configure(&self, name: &str, value: &str, options: &mut TransportOptions) -> Result<()> {
+        let file = options.get_mut::<FileOptions>();
+        match name {
+            "prefix" => file.prefix = value.to_string(),
+            "compression" => file.compression = FileCompression::try_from(value)?,
+            "rotate-interval" => {
+                file.rotate_interval = Some(Duration::from_secs(parse_setting(name, value)?))
+                    .filter(|interval| !interval.is_zero());
+            }
+            "max-size-mb" => {
+                file.max_size = Some(parse_setting::<u64>(name, value)?)
+                    .filter(|mb| *mb > 0)
+                    .map(|mb| mb.saturating_mul(1024 * 1024));
+            }
+            "retain-count" => {
+                file.retain_count =
+                    Some(parse_setting::<usize>(name, value)?).filter(|count| *count > 0);
+            }
+            "retain-days" => {
+                file.retain_age = Some(parse_setting::<u64>(name, value)?)
+                    .filter(|days| *days > 0)
+                    .map(|days| Duration::from_hours(days.saturating_mul(24)));
+            }
+            _ => return Err(unknown_setting(self.name(), name)),
+        }
+        Ok(())
+    }
+
+    async fn check(&self, endpoint: &Endpoint, _input: bool) -> Checks {
+        vec![(
+            "directory",
+            crate::check::directory(Path::new(&endpoint.host)),
+        )]
+    }
+
+    async fn run_output(
+        &self,
+        host: &str,
+        port: u16,
+        receiver: &mut Receiver<Message>,
+        options: &TransportOptions,
+        status: &StatusReporter,
+    ) -> Result<()> {
+        crate::transport::serve_output::<ArchiveFile>(host, port, receiver, options, status).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn segment_names_match_only_their_own_prefix() {
+        for (file_name, suffix) in [
+            ("acars-20260101T000000Z.jsonl", Some("")),
+            ("acars-20260101T000000Z_001.jsonl.gz", Some(".gz")),
+            ("acars-20260101T000000Z_1234.jsonl.zst", Some(".zst")),
+            (
+                "acars-20260101T000000Z.jsonl.zst.partial",
+                Some(".zst.partial"),
+            ),
+            ("acars.jsonl", None),
+            ("acars-vdl-20260101T000000Z.jsonl", None),
+            ("acars-vdl-20260101T000000Z.jsonl.gz.partial", None),
+            ("acars-20260101T000000Z_.jsonl", None),
+            ("acars-20260101T000000Z.jsonl.bak", None),
+            ("acars-20260101T000000.jsonl", None),
+            ("acars-notes.jsonl", None),
+        ] {
+            assert_eq!(segment_suffix(file_name, "acars"), suffix, "{file_name}");
+        }
+        assert_eq!(
+            segment_suffix("acars-vdl-20260101T000000Z.jsonl", "acars-vdl"),
+            Some("")
+        );
+    }
+
+    #[test]
+    fn sweeping_one_prefix_leaves_another_alone() {
+        let dir = std::env::temp_dir().join(format!("acars-bridge-sweep-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("creating the directory");
+        let ours = [
+            "acars-20260101T000000Z.jsonl.gz",
+            "acars-20260102T000000Z.jsonl.gz",
+            "acars-20260103T000000Z.jsonl.gz.partial",
+        ];
+        let theirs = [
+            "acars-vdl-20260101T000000Z.jsonl",
+            "acars-vdl-20260102T000000Z.jsonl.gz",
+            "acars-vdl-20260103T000000Z.jsonl.gz.partial",
+            "acars-vdl.jsonl",
+        ];
+        for file_name in ours.iter().chain(&theirs) {
+            std::fs::write(dir.join(file_name), "{}\n").expect("writing a segment");
+        }
+
+        let options = FileOptions {
+            compression: FileCompression::Gzip,
+            retain_count: Some(1),
+            ..FileOptions::default()
+        };
+        sweep(&dir, &options, "");
+
+        let mut left = std::fs::read_dir(&dir)
+            .expect("listing the directory")
+            .map(|entry| {
+                entry
+                    .expect("entry")
+                    .file_name()
+                    .into_string()
+                    .expect("name")
+            })
+            .collect::<Vec<_>>();
+        left.sort();
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut expected = vec!["acars-20260102T000000Z.jsonl.gz"];
+        expected.extend(theirs);
+        expected.sort_unstable();
+        assert_eq!(left, expected);
+    }
+}
@@ -0,0 +1,115 @@
+// Copyright (c) 2024-2026 Fred Clausen
+//
+// Licensed under the MIT license: https://opensource.org/licenses/MIT
+// Permission is granted to use, copy, modify, and redistribute the work.
+// Full license information available in the project LICENSE file.
+
+use anyhow::{Error, Result};
+use serde_json::Value;
+
+use crate::message;
+
+/// A set of `field=value` conditions a message must satisfy.
+///
+/// Field names are the ones understood by `message::field` (`type`, `tail`
+/// or a dotted JSON path). Conditions on different fields must all match;
+/// several values for the same field match if any one does. Values compare
+/// case-insensitively. An empty filter matches everything, including
+/// messages that are not JSON.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MessageFilter {
+    rules: Vec<(String, Vec<String>)>,
+}
+
+impl MessageFilter {
+    /// Build a filter from `(field, value)` pairs, such as the query pairs
+    /// of a URL.
+    pub fn from_pairs<I, K, V>(pairs: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        let mut filter = Self::default();
+        for (field, value) in pairs {
+            filter.add(field.into(), value.into());
+        }
+        filter
+    }
+
+    /// Build a filter from `field=value` expressions.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an expression has no `=` or an empty field name.
+    pub fn parse<S: AsRef<str>>(expressions: &[S]) -> Result<Self> {
+        let mut filter = Self::default();
+        for expression in expressions {
+            let expression = expression.as_ref();
+            let (field, value) = expression.split_once('=').ok_or_else(|| {
+                Error::msg(format!(
+                    "Filter {expression:?} is not in `field=value` form"
+                ))
+            })?;
+            let field = field.trim();
+            if field.is_empty() {
+                return Err(Error::msg(format!(
+                    "Filter {expression:?} has an empty field name"
+                )));
+            }
+            filter.add(field.to_string(), value.trim().to_string());
+        }
+        Ok(filter)
+    }
+
+    fn add(&mut self, field: String, value: String) {
+        match self
+            .rules
+            .iter_mut()
+            .find(|(existing, _)| *existing == field)
+        {
+            Some((_, values)) => values.push(value),
+            None => self.rules.push((field, vec![value])),
+        }
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Check a raw message. Only parses it when there is something to check.
+    #[must_use]
+    pub fn matches(&self, raw: &str) -> bool {
+        if self.is_empty() {
+            return true;
+        }
+        serde_json::from_str::<Value>(raw).is_ok_and(|parsed| self.matches_value(&parsed))
+    }
+
+    /// Check an already-parsed message.
+    #[must_use]
+    pub fn matches_value(&self, message: &Value) -> bool {
+        self.rules.iter().all(|(field, values)| {
+            message::field(message, field).is_some_and(|actual| {
+                values
+                    .iter()
+                    .any(|wanted| wanted.eq_ignore_ascii_case(&actual))
+            })
+        })
+    }
+}
+
+impl std::fmt::Display for MessageFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return write!(f, "(none)");
+        }
+        let rules = self
+            .rules
+            .iter()
+            .map(|(field, values)| format!("{field}={}", values.join("|")))
+            .collect::<Vec<_>>();
+        write!(f, "{}", rules.join(" "))
+    }
+}
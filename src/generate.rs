@@ -0,0 +1,458 @@
+// Copyright (c) 2024-2026 Fred Clausen
+//
+// Licensed under the MIT license: https://opensource.org/licenses/MIT
+// Permission is granted to use, copy, modify, and redistribute the work.
+// Full license information available in the project LICENSE file.
+
+//! Synthetic decoder traffic for load testing.
+//!
+//! Messages follow the JSON layouts of `acarsdec`, `dumpvdl2` and
+//! `dumphfdl` closely enough for `acars_router` and the bridge's own
+//! outputs (topic templates, filters) to treat them as real traffic. The
+//! content is drawn from small tables with a seeded generator, so a run can
+//! be repeated exactly with `--seed`.
+
+use anyhow::{Error, Result};
+use async_trait::async_trait;
+use serde_json::{Value, json};
+use std::time::{Duration, SystemTime};
+use tokio::sync::mpsc::Sender;
+use tokio::time::Instant;
+
+use crate::capture::Recorder;
+use crate::config::{Command, Config, GenerateArgs};
+use crate::message::Message;
+use crate::pipeline::InputSinks;
+use crate::serverconfig::InputServer;
+use crate::serverconfig::InputServerOptions;
+use crate::serverconfig::TransportOptions;
+use crate::stats::StatsEvent;
+use crate::status::StatusReporter;
+use crate::transport::Transport;
+
+const REGISTRATIONS: &[&str] = &[
+    "N123AB", "N512UA", "N37274", "C-FTJO", "G-EUYM", "D-AIMA", "F-HPJA", "EI-DEO", "VH-OQA",
+    "JA873A", "9V-SMF", "A6-EUA", "B-18918", "HL8083", "PH-BHA",
+];
+const FLIGHTS: &[&str] = &[
+    "UA1234", "AA0100", "DL0047", "AC0871", "BA0286", "LH0400", "AF0022", "EI0105", "QF0012",
+    "NH0010", "SQ0026", "EK0203", "CI0006", "KE0081", "KL0601",
+];
+const STATIONS: &[&str] = &["CS-KABC", "CS-EGLL", "CS-YSSY"];
+/// Labels with text typical of them.
+const LABELS: &[(&str, &str)] = &[
+    ("H1", "#DFB(POS-N4012W07350,FL350,1432,M82)"),
+    ("5Z", "/R2 AT GATE B12"),
+    ("_d", ""),
+    ("SA", "0EV1432Z/"),
+    ("10", "ETA 1545 KJFK"),
+    ("B9", "/KJFK.TI2/038KJFKA9B4C"),
+    ("Q0", "LINK TEST"),
+];
+const ACARS_FREQS: &[f64] = &[129.125, 130.025, 130.450, 131.125, 131.550];
+const VDL2_FREQS: &[u64] = &[
+    136_650_000,
+    136_725_000,
+    136_775_000,
+    136_875_000,
+    136_975_000,
+];
+const HFDL_FREQS: &[u64] = &[
+    5_451_000, 6_529_000, 8_927_000, 10_081_000, 11_184_000, 13_276_000,
+];
+const HFDL_STATIONS: &[(u64, &str)] = &[
+    (1, "San Francisco, California"),
+    (2, "Molokai, Hawaii"),
+    (3, "Reykjavik, Iceland"),
+    (4, "Riverhead, New York"),
+    (7, "Shannon, Ireland"),
+];
+
+/// A decoder whose output the generator imitates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GeneratedFormat {
+    Acarsdec,
+    Dumpvdl2,
+    Dumphfdl,
+}
+
+impl TryFrom<&str> for GeneratedFormat {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "acarsdec" | "acars" => Ok(Self::Acarsdec),
+            "dumpvdl2" | "vdl2" => Ok(Self::Dumpvdl2),
+            "dumphfdl" | "hfdl" => Ok(Self::Dumphfdl),
+            _ => Err(Error::msg(format!("Unknown generator format: {s}"))),
+        }
+    }
+}
+
+/// Settings for the `generate` subcommand.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GenerateOptions {
+    /// Messages per second. `None` sends as fast as the bridge takes them.
+    pub rate: Option<f64>,
+    /// Messages to send before the input finishes. `None` runs until
+    /// shutdown.
+    pub count: Option<u64>,
+    /// Formats to pick from, evenly. Must not be empty.
+    pub formats: Vec<GeneratedFormat>,
+    pub seed: u64,
+}
+
+impl Default for GenerateOptions {
+    fn default() -> Self {
+        Self {
+            rate: Some(10.0),
+            count: None,
+            formats: vec![
+                GeneratedFormat::Acarsdec,
+                GeneratedFormat::Dumpvdl2,
+                GeneratedFormat::Dumphfdl,
+            ],
+            seed: 0,
+        }
+    }
+}
+
+/// A seed that differs from run to run.
+#[must_use]
+pub fn random_seed() -> u64 {
+    let nanos = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.subsec_nanos());
+    (u64::from(nanos) << 32) ^ u64::from(std::process::id())
+}
+
+/// The ACARS layer of a generated message.
+struct AcarsFields {
+    tail: &'static str,
+    flight: &'static str,
+    label: &'static str,
+    block_id: String,
+    msgno: String,
+    text: &'static str,
+}
+
+/// Builds messages from a `SplitMix64` sequence. Not suitable for anything
+/// but making up test traffic.
+pub struct Generator {
+    state: u64,
+    formats: Vec<GeneratedFormat>,
+    sequence: u64,
+}
+
+impl Generator {
+    #[must_use]
+    pub const fn new(seed: u64, formats: Vec<GeneratedFormat>) -> Self {
+        Self {
+            state: seed,
+            formats,
+            sequence: 0,
+        }
+    }
+
+    const fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// A value in `0..bound`.
+    fn below(&mut self, bound: usize) -> usize {
+        usize::try_from(self.next_u64() % bound as u64).unwrap_or_default()
+    }
+
+    fn pick<T: Copy>(&mut self, items: &[T]) -> T {
+        items[self.below(items.len())]
+    }
+
+    /// A signal level in dB, to one decimal place, between `low` and
+    /// `low + 20`.
+    fn level(&mut self, low: i32) -> f64 {
+        let tenths = i32::try_from(self.below(200)).unwrap_or_default();
+        f64::from(low * 10 + tenths) / 10.0
+    }
+
+    /// The next message, as one line of JSON.
+    pub fn next_message(&mut self) -> String {
+        let index = self.below(self.formats.len());
+        let format = self.formats[index];
+        self.sequence += 1;
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+        let message = match format {
+            GeneratedFormat::Acarsdec => self.acarsdec(now),
+            GeneratedFormat::Dumpvdl2 => self.dumpvdl2(now),
+            GeneratedFormat::Dumphfdl => self.dumphfdl(now),
+        };
+        message.to_string()
+    }
+
+    /// The ACARS payload shared by all three formats.
+    fn acars_fields(&mut self) -> AcarsFields {
+        let (label, text) = self.pick(LABELS);
+        let block_id = char::from(b'0' + u8::try_from(self.below(10)).unwrap_or_default());
+        let sequence = char::from(b'A' + u8::try_from(self.below(26)).unwrap_or_default());
+        AcarsFields {
+            tail: self.pick(REGISTRATIONS),
+            flight: self.pick(FLIGHTS),
+            label,
+            block_id: block_id.to_string(),
+            msgno: format!("M{:02}{sequence}", self.sequence % 100),
+            text,
+        }
+    }
+
+    fn acarsdec(&mut self, now: Duration) -> Value {
+        let fields = self.acars_fields();
+        let channel = self.below(ACARS_FREQS.len());
+        json!({
+            "timestamp": now.as_secs_f64(),
+            "station_id": self.pick(STATIONS),
+            "channel": channel,
+            "freq": ACARS_FREQS[channel],
+            "level": self.level(-35),
+            "error": 0,
+            "mode": "2",
+            "label": fields.label,
+            "block_id": fields.block_id,
+            "ack": false,
+            "tail": fields.tail,
+            "flight": fields.flight,
+            "msgno": fields.msgno,
+            "text": fields.text,
+            "end": true,
+            "app": { "name": "acarsdec", "ver": "3.7" },
+        })
+    }
+
+    /// The `acars` object `dumpvdl2` and `dumphfdl` nest inside a frame.
+    fn nested_acars(&mut self) -> Value {
+        let fields = self.acars_fields();
+        json!({
+            "err": false,
+            "crc_ok": true,
+            "more": false,
+            "reg": format!(".{}", fields.tail),
+            "mode": "2",
+            "label": fields.label,
+            "blk_id": fields.block_id,
+            "ack": "!",
+            "flight": fields.flight,
+            "msg_num": &fields.msgno[..3],
+            "msg_num_seq": &fields.msgno[3..],
+            "msg_text": fields.text,
+        })
+    }
+
+    fn dumpvdl2(&mut self, now: Duration) -> Value {
+        let aircraft = format!("{:06X}", self.next_u64() & 0xFF_FFFF);
+        json!({
+            "vdl2": {
+                "app": { "name": "dumpvdl2", "ver": "2.3.0" },
+                "station": self.pick(STATIONS),
+                "t": { "sec": now.as_secs(), "usec": now.subsec_micros() },
+                "freq": self.pick(VDL2_FREQS),
+                "burst_len_octets": 40 + self.below(200),
+                "hdr_bits_fixed": 0,
+                "octets_corrected_by_fec": 0,
+                "idx": 0,
+                "sig_level": self.level(-40),
+                "noise_level": self.level(-55),
+                "freq_skew": self.level(-10),
+                "avlc": {
+                    "src": { "addr": aircraft, "type": "Aircraft", "status": "Airborne" },
+                    "dst": { "addr": "1059F4", "type": "Ground station" },
+                    "cr": "Command",
+                    "frame_type": "I",
+                    "rseq": self.below(8),
+                    "sseq": self.below(8),
+                    "poll": false,
+                    "acars": self.nested_acars(),
+                },
+            },
+        })
+    }
+
+    fn dumphfdl(&mut self, now: Duration) -> Value {
+        let (station_id, station_name) = self.pick(HFDL_STATIONS);
+        json!({
+            "hfdl": {
+                "app": { "name": "dumphfdl", "ver": "1.6.1" },
+                "station": self.pick(STATIONS),
+                "t": { "sec": now.as_secs(), "usec": now.subsec_micros() },
+                "freq": self.pick(HFDL_FREQS),
+                "bit_rate": self.pick(&[300, 600, 1200, 1800]),
+                "sig_level": self.level(-40),
+                "noise_level": self.level(-55),
+                "freq_skew": self.level(-10),
+                "slot": "S",
+                "lpdu": {
+                    "src": { "type": "Aircraft", "id": 1 + self.below(200) },
+                    "dst": { "type": "Ground station", "id": station_id, "name": station_name },
+                    "type": { "id": 13, "name": "Long Enveloped Data" },
+                    "hfnpdu": {
+                        "type": { "id": 255, "name": "Enveloped data" },
+                        "acars": self.nested_acars(),
+                    },
+                },
+            },
+        })
+    }
+}
+
+#[async_trait]
+impl InputServer for InputServerOptions<Generator> {
+    /// `host` and `port` are unused.
+    async fn new(
+        host: &str,
+        port: u16,
+        sender: Option<Sender<Message>>,
+        stats: Sender<StatsEvent>,
+        recorder: Option<Recorder>,
+        options: &TransportOptions,
+    ) -> Result<Self, Error> {
+        let generate = &options.get::<GenerateOptions>();
+        Ok(Self {
+            host: host.to_string(),
+            port,
+            socket: Generator::new(generate.seed, generate.formats.clone()),
+            sender,
+            stats,
+            recorder,
+            options: options.clone(),
+        })
+    }
+
+    /// Send messages until the count is reached. Returning `Ok(())` then
+    /// tells the supervisor the input is finished, which starts the graceful
+    /// drain.
+    async fn receive_message(mut self) -> Result<(), Error> {
+        let name = self.format_name();
+        let GenerateOptions { rate, count, .. } =
+            self.options.get::<GenerateOptions>().into_owned();
+        match rate {
+            Some(rate) => info!("{name}Generating {rate} message(s) per second"),
+            None => info!("{name}Generating as fast as possible"),
+        }
+
+        // Each message is due one interval after the last. Falling behind
+        // sends the backlog straight away, so the average rate holds.
+        let interval = rate.map(|rate| Duration::from_secs_f64(1.0 / rate));
+        let mut due = Instant::now();
+        let mut sent: u64 = 0;
+        while count.is_none_or(|count| sent < count) {
+            if let Some(interval) = interval {
+                tokio::time::sleep_until(due).await;
+                due += interval;
+            }
+
+            let message = self.socket.next_message();
+            trace!("{name}Generated: {message}");
+
+            if let Some(recorder) = &self.recorder {
+                recorder.record("generate://", &[message.as_bytes()]).await;
+            }
+
+            if let Some(sender) = &self.sender {
+                if let Err(e) = sender.send(message.into()).await {
+                    return Err(Error::msg(format!("{name}Output channel closed: {e}")));
+                }
+                trace!("{name}Message sent to output channel");
+            }
+
+            if let Err(e) = self.stats.send(StatsEvent::Message).await {
+                return Err(Error::msg(format!("{name}Stats channel closed: {e}")));
+            }
+            sent += 1;
+        }
+
+        info!("{name}Sent {sent} message(s)");
+        Ok(())
+    }
+
+    fn format_name(&self) -> String {
+        "[Generate Input] ".to_string()
+    }
+}
+
+/// Synthetic decoder messages from the `generate` subcommand. Not in the
+/// registry, so `--source-protocol` can't select it; the input finishes once
+/// `--count` messages have been sent.
+pub struct GenerateTransport;
+
+fn generate_options(args: &GenerateArgs) -> Result<GenerateOptions> {
+    if !args.rate.is_finite() || args.rate < 0.0 {
+        return Err(anyhow::anyhow!(
+            "Generate rate must be 0 or a positive number, not {}",
+            args.rate
+        ));
+    }
+    let formats = args
+        .formats
+        .iter()
+        .map(|format| GeneratedFormat::try_from(format.as_str()))
+        .collect::<Result<Vec<_>>>()?;
+    if formats.is_empty() {
+        return Err(anyhow::anyhow!("Generate needs at least one format"));
+    }
+
+    Ok(GenerateOptions {
+        rate: (args.rate > 0.0).then_some(args.rate),
+        count: (args.count > 0).then_some(args.count),
+        formats,
+        seed: args.seed.unwrap_or_else(random_seed),
+    })
+}
+
+#[async_trait]
+impl Transport for GenerateTransport {
+    fn name(&self) -> &'static str {
+        "generate"
+    }
+
+    fn needs_host(&self) -> bool {
+        false
+    }
+
+    fn needs_port(&self) -> bool {
+        false
+    }
+
+    fn supports_input(&self) -> bool {
+        true
+    }
+
+    fn ends_at_eof(&self) -> bool {
+        true
+    }
+
+    fn same_options(&self, old: &TransportOptions, new: &TransportOptions) -> bool {
+        old.get::<GenerateOptions>() == new.get::<GenerateOptions>()
+    }
+
+    /// Only with the `generate` subcommand, which has the flags.
+    fn options_from_config(&self, config: &Config, options: &mut TransportOptions) -> Result<()> {
+        if let Some(Command::Generate(args)) = config.get_command() {
+            options.set(generate_options(args)?);
+        }
+        Ok(())
+    }
+
+    async fn run_input(
+        &self,
+        host: &str,
+        port: u16,
+        sinks: InputSinks,
+        options: &TransportOptions,
+        status: &StatusReporter,
+    ) -> Result<()> {
+        crate::transport::serve_input::<Generator>(host, port, sinks, options, status).await
+    }
+}
@@ -0,0 +1,377 @@
+// Copyright (c) 2024-2026 Fred Clausen
+//
+// Licensed under the MIT license: https://opensource.org/licenses/MIT
+// Permission is granted to use, copy, modify, and redistribute the work.
+// Full license information available in the project LICENSE file.
+
+use anyhow::{Context, Error, Result};
+use async_trait::async_trait;
+use bytes::Bytes;
+use reqwest::header::{CONTENT_TYPE, HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Client, StatusCode, Url};
+use std::time::Duration;
+use tokio::sync::mpsc::Receiver;
+use tokio::time::Instant;
+
+use crate::config::Config;
+use crate::endpoint::{Endpoint, parse_at_least, parse_flag, parse_setting, unknown_setting};
+use crate::message::Message;
+use crate::serverconfig::OutputServer;
+use crate::serverconfig::OutputServerOptions;
+use crate::serverconfig::TransportOptions;
+use crate::status::StatusReporter;
+use crate::transport::{Checks, Transport};
+
+/// First delay between retries of a failed POST. Doubles per attempt.
+const RETRY_INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+/// Upper bound for the delay between retries of a failed POST.
+const RETRY_MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Settings for the HTTP webhook output.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HttpOptions {
+    /// Path (and optional query) appended to `host:port`.
+    pub path: String,
+    /// Use `https://` instead of `http://`.
+    pub tls: bool,
+    /// Extra request headers, as `(name, value)` pairs.
+    pub headers: Vec<(String, String)>,
+    /// Sent as `Authorization: Bearer <token>`.
+    pub bearer_token: Option<String>,
+    /// Sent as HTTP basic auth. The password is optional.
+    pub basic_auth: Option<(String, Option<String>)>,
+    /// Messages per POST. `1` posts each message on its own; anything
+    /// larger posts a JSON array.
+    pub batch_size: usize,
+    /// How long a partial batch may wait for more messages before it is
+    /// posted anyway.
+    pub batch_timeout: Duration,
+    /// Retries for a POST that failed with a 5xx, a 429 or a transport
+    /// error before the message (or batch) is dropped.
+    pub max_retries: u32,
+    /// Timeout for a single request, including connecting.
+    pub request_timeout: Duration,
+}
+
+impl Default for HttpOptions {
+    fn default() -> Self {
+        Self {
+            path: "/".to_string(),
+            tls: false,
+            headers: Vec::new(),
+            bearer_token: None,
+            basic_auth: None,
+            batch_size: 1,
+            batch_timeout: Duration::from_secs(1),
+            max_retries: 5,
+            request_timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Split a `Name: value` header flag into its parts.
+///
+/// # Errors
+///
+/// Returns an error if there is no `:` or the name is empty.
+pub fn parse_header(header: &str) -> Result<(String, String)> {
+    let (name, value) = header
+        .split_once(':')
+        .ok_or_else(|| Error::msg(format!("Header {header:?} is not in `Name: value` form")))?;
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(Error::msg(format!("Header {header:?} has an empty name")));
+    }
+    Ok((name.to_string(), value.trim().to_string()))
+}
+
+/// Outcome of posting one message or batch.
+enum Delivery {
+    Delivered,
+    /// The consumer rejected the payload (4xx). Retrying would not help.
+    Rejected(StatusCode),
+    /// Retries were exhausted on 5xx/429/transport errors.
+    GaveUp(String),
+}
+
+/// Build the webhook URL. Bare IPv6 literals are bracketed so that the
+/// `host:port` form used everywhere else on the command line still works.
+///
+/// # Errors
+///
+/// Returns an error if the host, port and path don't make a valid URL.
+pub fn build_url(host: &str, port: u16, options: &HttpOptions) -> Result<Url> {
+    let scheme = if options.tls { "https" } else { "http" };
+    let host = if host.contains(':') && !host.starts_with('[') {
+        format!("[{host}]")
+    } else {
+        host.to_string()
+    };
+    let path = if options.path.starts_with('/') {
+        options.path.clone()
+    } else {
+        format!("/{}", options.path)
+    };
+    Url::parse(&format!("{scheme}://{host}:{port}{path}"))
+        .with_context(|| format!("Invalid webhook URL for {host}:{port}{path}"))
+}
+
+fn build_headers(options: &HttpOptions) -> Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    for (name, value) in &options.headers {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .with_context(|| format!("Invalid header name {name:?}"))?;
+        let value = HeaderValue::from_str(value)
+            .with_context(|| format!("Invalid value for header {name}"))?;
+        headers.insert(name, value);
+    }
+    Ok(headers)
+}
+
+/// Turn a batch of messages into a JSON array body. Messages are normally
+/// JSON already and are embedded as-is; anything that fails to parse is
+/// embedded as a JSON string rather than dropped.
+fn batch_body(name: &str, batch: &[Message]) -> String {
+    let values = batch
+        .iter()
+        .map(|message| {
+            serde_json::from_str::<serde_json::Value>(message).unwrap_or_else(|e| {
+                warn!("{name}Message is not valid JSON ({e}); sending it as a string");
+                serde_json::Value::String(message.to_string())
+            })
+        })
+        .collect();
+    serde_json::Value::Array(values).to_string()
+}
+
+impl OutputServerOptions<Client> {
+    /// Wait for the next batch. Returns `None` once the bridge channel is
+    /// closed and empty. A partial batch is returned when the channel closes
+    /// or `batch_timeout` elapses after its first message.
+    async fn next_batch(&self, receiver: &mut Receiver<Message>) -> Option<Vec<Message>> {
+        let first = receiver.recv().await?;
+        let batch_size = self.options.get::<HttpOptions>().batch_size;
+        let mut batch = Vec::with_capacity(batch_size);
+        batch.push(first);
+
+        let deadline = Instant::now() + self.options.get::<HttpOptions>().batch_timeout;
+        while batch.len() < batch_size {
+            match tokio::time::timeout_at(deadline, receiver.recv()).await {
+                Ok(Some(message)) => batch.push(message),
+                // Channel closed or deadline hit: post what we have.
+                Ok(None) | Err(_) => break,
+            }
+        }
+        Some(batch)
+    }
+
+    async fn post(&self, url: &Url, body: Bytes) -> Delivery {
+        let name = self.format_name();
+        let http = &self.options.get::<HttpOptions>();
+        let mut backoff = RETRY_INITIAL_BACKOFF;
+        let mut attempt: u32 = 0;
+
+        loop {
+            let mut request = self.socket.post(url.clone()).body(body.clone());
+            if let Some(token) = &http.bearer_token {
+                request = request.bearer_auth(token);
+            }
+            if let Some((user, password)) = &http.basic_auth {
+                request = request.basic_auth(user, password.as_ref());
+            }
+
+            let failure = match request.send().await {
+                Ok(response) => {
+                    let status = response.status();
+                    if status.is_success() {
+                        return Delivery::Delivered;
+                    }
+                    if status.is_client_error() && status != StatusCode::TOO_MANY_REQUESTS {
+                        return Delivery::Rejected(status);
+                    }
+                    format!("HTTP {status}")
+                }
+                Err(e) => e.to_string(),
+            };
+
+            if attempt >= http.max_retries {
+                return Delivery::GaveUp(failure);
+            }
+            attempt += 1;
+            warn!(
+                "{name}POST failed ({failure}); retry {attempt}/{} in {}ms",
+                http.max_retries,
+                backoff.as_millis()
+            );
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(RETRY_MAX_BACKOFF);
+        }
+    }
+}
+
+#[async_trait]
+impl OutputServer for OutputServerOptions<Client> {
+    async fn new(host: &str, port: u16, options: &TransportOptions) -> Result<Self, Error> {
+        let name = format!("[HTTP Output {host}:{port}] ");
+        // Validate the URL and headers up front so a typo fails fast rather
+        // than on the first message.
+        build_url(host, port, &options.get::<HttpOptions>()).with_context(|| name.clone())?;
+        let socket = Client::builder()
+            .default_headers(
+                build_headers(&options.get::<HttpOptions>()).with_context(|| name.clone())?,
+            )
+            .timeout(options.get::<HttpOptions>().request_timeout)
+            .build()
+            .map_err(|e| Error::msg(format!("{name}Error building HTTP client: {e}")))?;
+
+        Ok(Self {
+            host: host.to_string(),
+            port,
+            socket,
+            options: options.clone(),
+        })
+    }
+
+    async fn watch_queue(self, receiver: &mut Receiver<Message>) -> Result<(), Error> {
+        let name = self.format_name();
+        let url = build_url(&self.host, self.port, &self.options.get::<HttpOptions>())?;
+        debug!("{name}Posting to {url}");
+
+        while let Some(mut batch) = self.next_batch(receiver).await {
+            debug!("{name}Received batch of {} message(s)", batch.len());
+
+            let count = batch.len();
+            let body = if self.options.get::<HttpOptions>().batch_size > 1 {
+                Bytes::from(batch_body(&name, &batch))
+            } else {
+                batch.pop().map(Message::into_bytes).unwrap_or_default()
+            };
+
+            match self.post(&url, body).await {
+                Delivery::Delivered => trace!("{name}Posted {count} message(s) to consumer"),
+                Delivery::Rejected(status) => {
+                    warn!(
+                        "{name}Consumer rejected {count} message(s) with HTTP {status}; dropping"
+                    );
+                }
+                Delivery::GaveUp(failure) => {
+                    error!("{name}Giving up on {count} message(s) after retries: {failure}");
+                }
+            }
+        }
+
+        // All bridge Senders have been dropped; this happens only during
+        // graceful shutdown. Return Ok(()) so the output supervisor treats it
+        // as a terminal, clean exit rather than a failure to restart.
+        info!("{name}Input channel closed (shutdown); exiting");
+        Ok(())
+    }
+
+    fn format_name(&self) -> String {
+        format!("[HTTP Output {}:{}] ", self.host, self.port)
+    }
+}
+
+/// HTTP POST webhook.
+pub struct HttpTransport;
+
+pub(crate) fn http_headers(config: &Config) -> Result<Vec<(String, String)>> {
+    config
+        .get_http_headers()
+        .iter()
+        .map(|header| parse_header(header))
+        .collect::<Result<Vec<_>>>()
+        .map_err(|e| anyhow::anyhow!("Error parsing HTTP header: {e}"))
+}
+
+#[async_trait]
+impl Transport for HttpTransport {
+    fn name(&self) -> &'static str {
+        "http"
+    }
+
+    fn supports_output(&self) -> bool {
+        true
+    }
+
+    fn same_options(&self, old: &TransportOptions, new: &TransportOptions) -> bool {
+        old.get::<HttpOptions>() == new.get::<HttpOptions>()
+    }
+
+    fn options_from_config(&self, config: &Config, options: &mut TransportOptions) -> Result<()> {
+        let basic_auth =
+            config
+                .get_http_basic_auth()
+                .as_ref()
+                .map(|auth| match auth.split_once(':') {
+                    Some((user, password)) => (user.to_string(), Some(password.to_string())),
+                    None => (auth.clone(), None),
+                });
+        options.set(HttpOptions {
+            path: config.get_http_path().to_string(),
+            tls: config.get_http_tls(),
+            headers: http_headers(config)?,
+            bearer_token: config.get_http_bearer_token().clone(),
+            basic_auth,
+            batch_size: config.get_http_batch_size(),
+            batch_timeout: Duration::from_millis(config.get_http_batch_timeout_ms()),
+            max_retries: config.get_http_max_retries(),
+            request_timeout: Duration::from_secs(config.get_http_timeout()),
+        });
+        Ok(())
+    }
+
+    fn configure(&self, name: &str, value: &str, options: &mut TransportOptions) -> Result<()> {
+        let http = options.get_mut::<HttpOptions>();
+        match name {
+            "path" => http.path = value.to_string(),
+            "tls" => http.tls = parse_flag(name, value)?,
+            "header" => http.headers.push(parse_header(value)?),
+            "bearer-token" => http.bearer_token = Some(value.to_string()),
+            "username" => {
+                let password = http.basic_auth.take().and_then(|(_, password)| password);
+                http.basic_auth = Some((value.to_string(), password));
+            }
+            "password" => {
+                let user = http
+                    .basic_auth
+                    .take()
+                    .map(|(user, _)| user)
+                    .unwrap_or_default();
+                http.basic_auth = Some((user, Some(value.to_string())));
+            }
+            "batch-size" => http.batch_size = parse_at_least(name, value, 1)?,
+            "batch-timeout-ms" => {
+                http.batch_timeout = Duration::from_millis(parse_setting(name, value)?);
+            }
+            "max-retries" => http.max_retries = parse_setting(name, value)?,
+            "timeout" => {
+                http.request_timeout = Duration::from_secs(parse_at_least(name, value, 1)?);
+            }
+            _ => return Err(unknown_setting(self.name(), name)),
+        }
+        Ok(())
+    }
+
+    async fn check(&self, endpoint: &Endpoint, _input: bool) -> Checks {
+        let (host, port) = (endpoint.host.as_str(), endpoint.port);
+        if let Err(e) = build_url(host, port, &endpoint.options.get::<HttpOptions>()) {
+            return vec![("URL", Err(e))];
+        }
+        let mut checks = vec![("URL", Ok(()))];
+        checks.extend(crate::check::resolves(host, port).await);
+        checks
+    }
+
+    async fn run_output(
+        &self,
+        host: &str,
+        port: u16,
+        receiver: &mut Receiver<Message>,
+        options: &TransportOptions,
+        status: &StatusReporter,
+    ) -> Result<()> {
+        crate::transport::serve_output::<Client>(host, port, receiver, options, status).await
+    }
+}
@@ -0,0 +1,60 @@
+// Copyright (c) 2024-2026 Fred Clausen
+//
+// Licensed under the MIT license: https://opensource.org/licenses/MIT
+// Permission is granted to use, copy, modify, and redistribute the work.
+// Full license information available in the project LICENSE file.
+
+//! Bridges ACARS, VDLM2 and HFDL messages from a decoder to `acars_router`,
+//! or anywhere else, over UDP, TCP, ZMQ, HTTP, MQTT and more.
+//!
+//! The `acars-bridge` binary is built on this library. To run a bridge
+//! inside another program, start one with `Bridge`; to receive or send with
+//! a protocol of your own, implement `InputServer` or `OutputServer` and add
+//! it with `Bridge::input_server` or `Bridge::output_server`, or implement
+//! `Transport` and `transport::register` it to select it by URL.
+
+#![deny(unsafe_code)]
+
+#[macro_use]
+extern crate log;
+
+pub mod backoff;
+pub mod bridge;
+pub mod capture;
+pub mod check;
+pub mod cli;
+pub mod config;
+pub mod dashboard;
+pub mod endpoint;
+pub mod filter;
+pub mod listener;
+pub mod message;
+pub mod pipeline;
+pub mod serverconfig;
+pub mod stats;
+pub mod status;
+pub mod supervisor;
+pub mod transport;
+
+pub mod file;
+pub mod generate;
+pub mod http;
+pub mod memory;
+pub mod mqtt;
+pub mod replay;
+pub mod sse;
+pub mod stdio;
+pub mod tail;
+pub mod tap;
+pub mod tcp;
+pub mod udp;
+pub mod websocket;
+pub mod zmq;
+
+pub use bridge::{Bridge, BridgeHandle};
+pub use endpoint::Endpoint;
+pub use filter::MessageFilter;
+pub use message::Message;
+pub use serverconfig::{InputServer, OutputServer, TransportOptions};
+pub use stats::{StatsCounters, StatsEvent};
+pub use transport::Transport;
@@ -0,0 +1,61 @@
+// Copyright (c) 2024-2026 Fred Clausen
+//
+// Licensed under the MIT license: https://opensource.org/licenses/MIT
+// Permission is granted to use, copy, modify, and redistribute the work.
+// Full license information available in the project LICENSE file.
+
+use anyhow::{Error, Result};
+use std::future::Future;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinSet;
+use tokio_util::sync::CancellationToken;
+
+/// How long a graceful shutdown waits for clients to receive what is
+/// already buffered for them before their connections are dropped.
+const CLIENT_DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Accept connections for a listening output until `cancel` fires, running
+/// `serve` for each client on its own task. Once cancelled, connected
+/// clients get a moment to drain before they are dropped.
+///
+/// Shared by the outputs that browsers connect to (WebSocket, SSE).
+///
+/// # Errors
+///
+/// Returns an error if `accept` itself fails, so the output supervisor can
+/// rebind.
+pub async fn accept_clients<F, Fut>(
+    listener: TcpListener,
+    name: String,
+    cancel: CancellationToken,
+    mut serve: F,
+) -> Result<(), Error>
+where
+    F: FnMut(TcpStream, SocketAddr) -> Fut,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let mut clients = JoinSet::new();
+    loop {
+        tokio::select! {
+            () = cancel.cancelled() => break,
+            accepted = listener.accept() => {
+                let (stream, peer) =
+                    accepted.map_err(|e| Error::msg(format!("{name}Error accepting client: {e}")))?;
+                clients.spawn(serve(stream, peer));
+            }
+            // Reap finished clients so the set doesn't grow forever.
+            Some(_) = clients.join_next(), if !clients.is_empty() => {}
+        }
+    }
+
+    let drain = async { while clients.join_next().await.is_some() {} };
+    if tokio::time::timeout(CLIENT_DRAIN_TIMEOUT, drain)
+        .await
+        .is_err()
+    {
+        warn!("{name}Timed out waiting for clients to drain; disconnecting them");
+    }
+    Ok(())
+}
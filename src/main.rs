@@ -4,275 +4,157 @@
 // Permission is granted to use, copy, modify, and redistribute the work.
 // Full license information available in the project LICENSE file.
 
+#![forbid(unsafe_code)]
+
 #[macro_use]
 extern crate log;
 
-pub mod config;
-pub mod serverconfig;
-pub mod stats;
-
-pub mod tcp;
-pub mod udp;
-pub mod zmq;
-
 use anyhow::Result;
-use clap::Parser;
-use sdre_rust_logging::SetupLogging;
-use sdre_stubborn_io::tokio::StubbornIo;
-use serverconfig::InputServerOptions;
+use std::path::Path;
+use std::sync::Arc;
 use std::time::Duration;
-use tmq::publish::Publish;
-use tmq::subscribe::Subscribe;
-use tokio::net::TcpStream;
 use tokio::sync::mpsc;
 use tokio::sync::mpsc::Sender;
 use tokio::task::JoinHandle;
 use tokio_util::sync::CancellationToken;
 
-use crate::config::Config;
-use crate::serverconfig::{InputServer, OutputServer, OutputServerOptions, SocketType};
-
-/// Spawn a supervised input server. `output_sender` is the master Sender clone
-/// for the input->output bridge channel (None when no output is configured).
-/// `stats_sender` is the master Sender clone for the stats channel. Both are
-/// cloned for every (re)spawn so the master copies in main keep the channels
-/// alive even when the input task dies.
-///
-/// Backoff: 1s, 2s, 4s, 8s, 16s, 32s, then capped at 60s. Resets to 1s after
-/// the task survives for at least 60s, so a transient failure doesn't keep us
-/// in the slow-retry regime forever.
-///
-/// On `cancel`, the supervisor aborts any in-flight inner task and exits its
-/// loop. The returned `JoinHandle` resolves once the supervisor has exited.
-fn spawn_input(
-    proto: SocketType,
-    host: String,
-    port: u16,
-    output_sender: Option<Sender<String>>,
-    stats_sender: Sender<u8>,
-    cancel: CancellationToken,
-) -> JoinHandle<()> {
-    let label = format!("input/{host}:{port}");
+use acars_bridge::Message;
+use acars_bridge::capture::{self, Recorder};
+use acars_bridge::check;
+use acars_bridge::cli::{
+    build_transport_options, parse_destination, parse_source, shutdown_signal, start_logging,
+};
+use acars_bridge::config::{Command, Config};
+use acars_bridge::dashboard::{self, Dashboard, LogBuffer, RecentMessages, Side};
+use acars_bridge::pipeline::{Context, InputSinks, Pipeline, Stopped};
+use acars_bridge::stats::{self, StatsCounters, StatsEvent};
+use acars_bridge::status::StatusReporter;
+
+/// Exit at once, without draining, if another SIGINT or SIGTERM arrives
+/// while main drains the pipeline. Unless a signal started the drain, the
+/// first one during it only arms this.
+fn exit_on_second_signal(signalled: bool) {
     tokio::spawn(async move {
-        let mut backoff_secs: u64 = 1;
-        while !cancel.is_cancelled() {
-            let started = tokio::time::Instant::now();
-            let output_sender = output_sender.clone();
-            let stats_sender = stats_sender.clone();
-
-            let work = async {
-                match &proto {
-                    SocketType::Tcp => {
-                        let server = InputServerOptions::<StubbornIo<TcpStream>>::new(
-                            &host,
-                            port,
-                            output_sender,
-                            stats_sender,
-                        )
-                        .await?;
-                        server.receive_message().await
-                    }
-                    SocketType::Udp => {
-                        let server = InputServerOptions::<tokio::net::UdpSocket>::new(
-                            &host,
-                            port,
-                            output_sender,
-                            stats_sender,
-                        )
-                        .await?;
-                        server.receive_message().await
-                    }
-                    SocketType::Zmq => {
-                        let server = InputServerOptions::<Subscribe>::new(
-                            &host,
-                            port,
-                            output_sender,
-                            stats_sender,
-                        )
-                        .await?;
-                        server.receive_message().await
-                    }
-                }
-            };
-
-            let result: Option<Result<()>> = tokio::select! {
-                biased;
-                () = cancel.cancelled() => None,
-                r = work => Some(r),
-            };
-
-            match result {
-                None => {
-                    info!("[SUPERVISOR][{label}] Cancelled; exiting");
-                    break;
-                }
-                Some(Ok(())) => {
-                    info!("[SUPERVISOR][{label}] Task exited gracefully; restarting");
-                }
-                Some(Err(e)) => {
-                    error!("[SUPERVISOR][{label}] Task failed: {e}; restarting");
-                }
-            }
-
-            if started.elapsed() >= Duration::from_mins(1) {
-                backoff_secs = 1;
-            }
-
-            info!("[SUPERVISOR][{label}] Sleeping {backoff_secs}s before restart");
-            tokio::select! {
-                biased;
-                () = cancel.cancelled() => {
-                    info!("[SUPERVISOR][{label}] Cancelled during backoff; exiting");
-                    break;
-                }
-                () = tokio::time::sleep(Duration::from_secs(backoff_secs)) => {}
-            }
-            backoff_secs = (backoff_secs.saturating_mul(2)).min(60);
+        if !signalled {
+            shutdown_signal().await;
+            warn!("[SHUTDOWN] Already draining; another signal exits without draining");
         }
-    })
+        shutdown_signal().await;
+        warn!("[SHUTDOWN] Second signal received; exiting without draining");
+        std::process::exit(1);
+    });
 }
 
-/// Spawn a supervised output server. The output `Receiver<String>` is owned by
-/// the supervisor and borrowed mutably into `watch_queue` for each restart.
-/// Because the master `Sender<String>` lives in main, the receiver normally
-/// never sees a `None`. During shutdown, main drops its master Sender after
-/// the input supervisor exits, which lets the output drain naturally before
-/// receiving `None` and returning `Ok(())` from `watch_queue`.
-///
-/// Unlike `spawn_input`, the output supervisor does NOT include the cancel
-/// token in its inner `select!`. Aborting the inner task on cancel would
-/// drop any messages already buffered in the bridge channel. Instead,
-/// shutdown is driven by closing the channel: `recv()` returns `None`,
-/// `watch_queue` returns `Ok(())`, and the supervisor treats `Ok(())` as a
-/// terminal exit (no restart). The cancel token is still honored during the
-/// backoff sleep so a shutdown signal received while the output is between
-/// reconnect attempts breaks out promptly.
-///
-/// On an `Err` from `watch_queue` (real I/O failure) the supervisor restarts
-/// with exponential backoff. The returned `JoinHandle` resolves once the
-/// supervisor has exited.
-fn spawn_output(
-    proto: SocketType,
-    host: String,
-    port: u16,
-    mut receiver: mpsc::Receiver<String>,
-    cancel: CancellationToken,
-) -> JoinHandle<()> {
-    let label = format!("output/{host}:{port}");
-    tokio::spawn(async move {
-        let mut backoff_secs: u64 = 1;
-        while !cancel.is_cancelled() {
-            let started = tokio::time::Instant::now();
-
-            let result: Result<()> = match &proto {
-                SocketType::Tcp => {
-                    match OutputServerOptions::<StubbornIo<TcpStream>>::new(&host, port).await {
-                        Ok(server) => server.watch_queue(&mut receiver).await,
-                        Err(e) => Err(e),
-                    }
-                }
-                SocketType::Udp => {
-                    match OutputServerOptions::<tokio::net::UdpSocket>::new(&host, port).await {
-                        Ok(server) => server.watch_queue(&mut receiver).await,
-                        Err(e) => Err(e),
-                    }
-                }
-                SocketType::Zmq => match OutputServerOptions::<Publish>::new(&host, port).await {
-                    Ok(server) => server.watch_queue(&mut receiver).await,
-                    Err(e) => Err(e),
-                },
-            };
-
-            match result {
-                Ok(()) => {
-                    // Terminal exit: watch_queue only returns Ok(()) when the
-                    // bridge channel has been closed, which happens during
-                    // graceful shutdown. Do not restart.
-                    info!("[SUPERVISOR][{label}] Channel closed; exiting (no restart)");
-                    break;
-                }
-                Err(e) => {
-                    error!("[SUPERVISOR][{label}] Task failed: {e}; restarting");
-                }
-            }
-
-            if started.elapsed() >= Duration::from_mins(1) {
-                backoff_secs = 1;
-            }
-
-            info!("[SUPERVISOR][{label}] Sleeping {backoff_secs}s before restart");
-            tokio::select! {
-                biased;
-                () = cancel.cancelled() => {
-                    info!("[SUPERVISOR][{label}] Cancelled during backoff; exiting");
-                    break;
-                }
-                () = tokio::time::sleep(Duration::from_secs(backoff_secs)) => {}
-            }
-            backoff_secs = (backoff_secs.saturating_mul(2)).min(60);
-        }
-    })
-}
-
-/// Wait for either SIGINT (Ctrl-C) or, on Unix, SIGTERM. Container
-/// orchestrators (docker stop, systemd) typically send SIGTERM, which the
-/// default `tokio::signal::ctrl_c()` alone does not catch.
-async fn shutdown_signal() {
-    let ctrl_c = async {
-        if let Err(e) = tokio::signal::ctrl_c().await {
-            error!("Failed to install Ctrl-C handler: {e}");
-        }
+/// Start the dashboard if `logs` is set, with a monitor on the bridge
+/// channel copying messages to it. Returns the receiver the output should
+/// read from, which the monitor feeds with the dashboard, and the dashboard's
+/// handle.
+fn start_dashboard(
+    config: &Config,
+    logs: Option<LogBuffer>,
+    (bridge_sender, bridge_receiver): (&Sender<Message>, mpsc::Receiver<Message>),
+    statuses: (&StatusReporter, &StatusReporter),
+    counters: StatsCounters,
+    done: CancellationToken,
+) -> (mpsc::Receiver<Message>, Option<JoinHandle<()>>) {
+    let Some(logs) = logs else {
+        return (bridge_receiver, None);
     };
-
-    #[cfg(unix)]
-    let terminate = async {
-        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
-            Ok(mut sig) => {
-                sig.recv().await;
-            }
-            Err(e) => {
-                error!("Failed to install SIGTERM handler: {e}");
-                std::future::pending::<()>().await;
-            }
-        }
+    let (input_status, output_status) = statuses;
+    let mut dashboard = Dashboard {
+        input: Side {
+            status: input_status.subscribe(),
+            queue: Some(bridge_sender.downgrade()),
+        },
+        output: Side {
+            status: output_status.subscribe(),
+            queue: None,
+        },
+        counters,
+        forwarded: Arc::default(),
+        recent: RecentMessages::default(),
+        logs,
     };
+    let receiver = dashboard::monitor(
+        bridge_receiver,
+        &mut dashboard.output,
+        config.get_channel_capacity(),
+        dashboard.recent.clone(),
+        Arc::clone(&dashboard.forwarded),
+    );
+    (receiver, Some(dashboard.spawn(done)))
+}
 
-    #[cfg(not(unix))]
-    let terminate = std::future::pending::<()>();
-
-    tokio::select! {
-        () = ctrl_c => info!("[SHUTDOWN] Received SIGINT"),
-        () = terminate => info!("[SHUTDOWN] Received SIGTERM"),
+/// Start recording to `--record`, if set. Returns the master `Recorder` and
+/// the writer task's handle.
+async fn start_recorder(config: &Config) -> Result<(Option<Recorder>, Option<JoinHandle<()>>)> {
+    match config.get_record() {
+        Some(path) => {
+            let (recorder, handle) =
+                capture::start(path.into(), config.get_channel_capacity()).await?;
+            Ok((Some(recorder), Some(handle)))
+        }
+        None => Ok((None, None)),
     }
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    let config: Config = Config::parse();
-    config.get_log_level().enable_logging();
+fn main() -> Result<()> {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+    let result = runtime.block_on(run());
+    // A read from stdin runs on a blocking thread that can't be cancelled,
+    // so don't wait for it once the bridge has drained.
+    runtime.shutdown_background();
+    result
+}
+
+async fn run() -> Result<()> {
+    let config = Config::load()?;
+    let logs = start_logging(&config)?;
     config.show_config();
+    if matches!(config.get_command(), Some(Command::Check)) {
+        return check::run(&config).await;
+    }
 
     let channel_capacity = config.get_channel_capacity();
-
-    // Master bridge channel (input -> output). We retain the master Sender in
-    // main so that even if all input tasks die simultaneously, the output side
-    // does not see a closed channel.
-    let (bridge_sender_master, bridge_receiver) = if config.is_destination_set() {
-        info!("Destination set, creating output channel");
-        let (tx, rx) = mpsc::channel::<String>(channel_capacity);
-        (Some(tx), Some(rx))
-    } else {
-        (None, None)
-    };
-
-    // Master stats channel. Same reasoning: the master Sender stays in main so
-    // the stats receiver loop never observes a closed channel due to a dead
+    let transport_options = build_transport_options(&config)?;
+    let source = parse_source(&config, &transport_options)?;
+    let destination = parse_destination(&config, &transport_options)?;
+
+    // Master bridge channel (input -> output). The master Sender is kept in
+    // the pipeline's sinks so that even if all input tasks die
+    // simultaneously, the output side does not see a closed channel. It
+    // exists even without a destination, so a reload can add one.
+    let (bridge_sender_master, bridge_receiver) = mpsc::channel::<Message>(channel_capacity);
+
+    // Master stats channel. Same reasoning: the master Sender is kept so the
+    // stats receiver loop never observes a closed channel due to a dead
     // input task.
-    let (stats_sender_master, stats_receiver) = mpsc::channel::<u8>(channel_capacity);
+    let (stats_sender_master, stats_receiver) = mpsc::channel::<StatsEvent>(channel_capacity);
+
+    let mut stats = stats::Stats::new(stats_receiver);
+
+    // With the dashboard, a monitor sits between the bridge channel and the
+    // output, and `ui_done` is cancelled when the user quits it.
+    let input_status = StatusReporter::default();
+    let output_status = StatusReporter::default();
+    stats.watch("Input", input_status.subscribe());
+    stats.watch("Output", output_status.subscribe());
+    let ui_done = CancellationToken::new();
+    let (bridge_receiver, dashboard_handle) = start_dashboard(
+        &config,
+        logs,
+        (&bridge_sender_master, bridge_receiver),
+        (&input_status, &output_status),
+        stats.counters(),
+        ui_done.clone(),
+    );
+    stats.run(Some(config.get_stat_interval()));
 
-    let stats = stats::Stats::new(stats_receiver);
-    let print_interval = config.get_stat_interval();
-    stats.run(print_interval);
+    // The recorder's master handle is kept like the Senders above, and is
+    // dropped once the input supervisor has exited.
+    let (recorder_master, recorder_handle) = start_recorder(&config).await?;
 
     // One CancellationToken governs the whole process. Input supervisors and
     // the output supervisor each get a clone. On shutdown, main cancels the
@@ -280,44 +162,33 @@ async fn main() -> Result<()> {
     // pipeline.
     let cancel = CancellationToken::new();
 
-    // Spawn the supervised input.
-    info!("Creating input server");
-    let input_proto = SocketType::try_from(config.get_source_protocol())
-        .map_err(|e| anyhow::anyhow!("Error parsing source protocol: {e}"))?;
-    let input_handle = spawn_input(
-        input_proto,
-        config.get_source_host().to_string(),
-        config.get_source_port(),
-        bridge_sender_master.clone(),
-        stats_sender_master.clone(),
-        cancel.clone(),
-    );
-
-    // Spawn the supervised output, if configured.
-    let output_handle = if config.is_destination_set() {
-        let rx = bridge_receiver.expect("bridge receiver should exist when destination is set");
-        let host = config
-            .get_destination_host()
-            .clone()
-            .expect("destination host should be set");
-        let port = config
-            .get_destination_port()
-            .expect("destination port should be set");
-        let proto_str = config
-            .get_destination_protocol()
-            .as_deref()
-            .expect("destination protocol should be set");
-        let output_proto = SocketType::try_from(proto_str)
-            .map_err(|e| anyhow::anyhow!("Error parsing destination protocol: {e}"))?;
-
-        info!("Creating output server");
-        Some(spawn_output(output_proto, host, port, rx, cancel.clone()))
-    } else {
-        None
+    let context = Context {
+        config,
+        options: transport_options,
+        sinks: InputSinks {
+            output: Some(bridge_sender_master),
+            stats: stats_sender_master,
+            recorder: recorder_master,
+        },
+        input_status,
+        output_status,
+        cancel: cancel.clone(),
+        gave_up: CancellationToken::new(),
     };
-
-    // Wait for a shutdown signal.
-    shutdown_signal().await;
+    let mut pipeline = Pipeline::start(context, source, destination, bridge_receiver);
+
+    // Wait for a shutdown signal, for an input that ends at EOF to finish,
+    // or for the user to quit the dashboard, and drain the same way in each
+    // case. The dashboard closes first so the drain is logged to the
+    // terminal.
+    let stopped = pipeline.run_until_stopped(&ui_done).await;
+    ui_done.cancel();
+    if let Some(handle) = dashboard_handle
+        && let Err(e) = handle.await
+    {
+        error!("[SHUTDOWN] Dashboard join error: {e}");
+    }
+    exit_on_second_signal(matches!(stopped, Stopped::Signal));
 
     info!("[SHUTDOWN] Cancelling input supervisor");
     cancel.cancel();
@@ -329,27 +200,60 @@ async fn main() -> Result<()> {
     //   2. Drop the master bridge Sender. Now no Sender for the bridge
     //      channel exists; the output's recv() will return None once the
     //      buffered messages are drained. The output supervisor does NOT
-    //      honor the cancel token inside its inner work (see spawn_output),
-    //      so the drain happens uninterrupted.
+    //      honor the cancel token inside its inner work (see the supervisor
+    //      module), so the drain happens uninterrupted.
     //   3. Wait for the output supervisor to drain and exit. watch_queue
     //      returns Ok(()) on channel close and the supervisor treats that as
-    //      terminal (no restart).
+    //      terminal (no restart). Past --drain-timeout the output is stopped
+    //      and what it didn't deliver goes to --spill-file or is dropped.
     //   4. Drop the master stats Sender. The stats watcher's recv() will
     //      return None and it exits cleanly.
-    if let Err(e) = input_handle.await {
+    //
+    // The recorder is closed the same way as the bridge channel, right
+    // after the input supervisor exits, and its writer is awaited so the
+    // capture is complete on disk.
+    let Pipeline {
+        context,
+        input,
+        output,
+    } = pipeline;
+    let joined = match stopped {
+        Stopped::InputFinished(joined) => joined,
+        Stopped::Signal | Stopped::Quit => input.handle.await,
+    };
+    if let Err(e) = joined {
         error!("[SHUTDOWN] Input supervisor join error: {e}");
     }
 
-    drop(bridge_sender_master);
-
-    if let Some(handle) = output_handle
+    let InputSinks {
+        output: bridge_sender_master,
+        stats: stats_sender_master,
+        recorder: recorder_master,
+    } = context.sinks;
+    drop(recorder_master);
+    if let Some(handle) = recorder_handle
         && let Err(e) = handle.await
     {
-        error!("[SHUTDOWN] Output supervisor join error: {e}");
+        error!("[SHUTDOWN] Recorder join error: {e}");
     }
 
+    drop(bridge_sender_master);
+
+    let drain_timeout = context.config.get_drain_timeout();
+    output
+        .drain(
+            (drain_timeout > 0).then(|| Duration::from_secs(drain_timeout)),
+            context.config.get_spill_file().as_deref().map(Path::new),
+        )
+        .await;
+
     drop(stats_sender_master);
 
+    if context.gave_up.is_cancelled() {
+        return Err(anyhow::anyhow!(
+            "Stopped after a supervisor ran out of restart attempts"
+        ));
+    }
     info!("[SHUTDOWN] Clean exit");
     Ok(())
 }
@@ -0,0 +1,253 @@
+// Copyright (c) 2024-2026 Fred Clausen
+//
+// Licensed under the MIT license: https://opensource.org/licenses/MIT
+// Permission is granted to use, copy, modify, and redistribute the work.
+// Full license information available in the project LICENSE file.
+
+//! Named in-process queues, for feeding a bridge from the program embedding
+//! it, reading what it sends, and tests.
+//!
+//! `memory://feed` as an input receives whatever is sent to the queue named
+//! `feed`; as an output it sends into it. The program holds the other end
+//! through `queue`. Queues are bounded, so a full one holds the bridge back
+//! the way a slow peer would, and nothing is dropped.
+
+use anyhow::{Error, Result};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, Mutex, PoisonError};
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::mpsc::{self, Receiver, Sender};
+
+use crate::capture::Recorder;
+use crate::message::Message;
+use crate::pipeline::InputSinks;
+use crate::serverconfig::InputServer;
+use crate::serverconfig::InputServerOptions;
+use crate::serverconfig::OutputServer;
+use crate::serverconfig::OutputServerOptions;
+use crate::serverconfig::TransportOptions;
+use crate::stats::StatsEvent;
+use crate::status::StatusReporter;
+use crate::transport::Transport;
+
+/// How many messages a queue holds unless it was made with `create`.
+pub const DEFAULT_CAPACITY: usize = 1024;
+
+static QUEUES: LazyLock<Mutex<HashMap<String, MemoryQueue>>> = LazyLock::new(Mutex::default);
+
+/// Both ends of one named queue. Clones share them.
+#[derive(Clone)]
+pub struct MemoryQueue {
+    sender: Sender<Message>,
+    /// Held by whoever is receiving, so an input restarted on the same
+    /// queue picks up where the last one stopped.
+    receiver: Arc<tokio::sync::Mutex<Receiver<Message>>>,
+}
+
+impl MemoryQueue {
+    fn new(capacity: usize) -> Self {
+        let (sender, receiver) = mpsc::channel(capacity);
+        Self {
+            sender,
+            receiver: Arc::new(tokio::sync::Mutex::new(receiver)),
+        }
+    }
+
+    /// Queue `message`, waiting while the queue is full.
+    pub async fn send(&self, message: impl Into<Message>) {
+        // The queue holds its own receiver, so the channel never closes.
+        let _ = self.sender.send(message.into()).await;
+    }
+
+    /// Queue `message` if there is room, or hand it back if not.
+    ///
+    /// # Errors
+    ///
+    /// Returns the message if the queue is full.
+    pub fn try_send(&self, message: impl Into<Message>) -> Result<(), Message> {
+        self.sender.try_send(message.into()).map_err(|e| match e {
+            TrySendError::Full(message) | TrySendError::Closed(message) => message,
+        })
+    }
+
+    /// The next message, waiting for one to arrive.
+    pub async fn recv(&self) -> Message {
+        let mut receiver = self.receiver.lock().await;
+        // Never closes; see `send`.
+        receiver.recv().await.unwrap_or_default()
+    }
+
+    /// The next message, if one is queued now.
+    #[must_use]
+    pub fn try_recv(&self) -> Option<Message> {
+        self.receiver.try_lock().ok()?.try_recv().ok()
+    }
+
+    /// How many messages are queued.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.sender.max_capacity() - self.sender.capacity()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// The queue named `name`, made with `DEFAULT_CAPACITY` if there isn't one.
+#[must_use]
+pub fn queue(name: &str) -> MemoryQueue {
+    QUEUES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .entry(name.to_string())
+        .or_insert_with(|| MemoryQueue::new(DEFAULT_CAPACITY))
+        .clone()
+}
+
+/// A new, empty queue named `name` holding up to `capacity` messages, in
+/// place of any queue of that name. Servers already running keep the old
+/// one.
+///
+/// # Panics
+///
+/// Panics if `capacity` is 0.
+#[must_use]
+pub fn create(name: &str, capacity: usize) -> MemoryQueue {
+    let queue = MemoryQueue::new(capacity);
+    QUEUES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(name.to_string(), queue.clone());
+    queue
+}
+
+#[async_trait]
+impl InputServer for InputServerOptions<MemoryQueue> {
+    /// `host` names the queue; `port` is unused.
+    async fn new(
+        host: &str,
+        port: u16,
+        sender: Option<Sender<Message>>,
+        stats: Sender<StatsEvent>,
+        recorder: Option<Recorder>,
+        options: &TransportOptions,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            host: host.to_string(),
+            port,
+            socket: queue(host),
+            sender,
+            stats,
+            recorder,
+            options: options.clone(),
+        })
+    }
+
+    async fn receive_message(self) -> Result<(), Error> {
+        let name = self.format_name();
+        let source = format!("memory://{}", self.host);
+        loop {
+            let message = self.socket.recv().await;
+            debug!("{name}Received: {message}");
+
+            if let Some(recorder) = &self.recorder {
+                recorder.record(&source, &[message.as_bytes()]).await;
+            }
+
+            if let Some(sender) = &self.sender {
+                if let Err(e) = sender.send(message).await {
+                    return Err(Error::msg(format!("{name}Output channel closed: {e}")));
+                }
+                trace!("{name}Message sent to output channel");
+            }
+
+            if let Err(e) = self.stats.send(StatsEvent::Message).await {
+                return Err(Error::msg(format!("{name}Stats channel closed: {e}")));
+            }
+            trace!("{name}Stats sent to channel");
+        }
+    }
+
+    fn format_name(&self) -> String {
+        format!("[memory Input {}] ", self.host)
+    }
+}
+
+#[async_trait]
+impl OutputServer for OutputServerOptions<MemoryQueue> {
+    /// `host` names the queue; `port` is unused.
+    async fn new(host: &str, port: u16, options: &TransportOptions) -> Result<Self, Error> {
+        Ok(Self {
+            host: host.to_string(),
+            port,
+            socket: queue(host),
+            options: options.clone(),
+        })
+    }
+
+    async fn watch_queue(self, receiver: &mut Receiver<Message>) -> Result<(), Error> {
+        let name = self.format_name();
+        while let Some(message) = receiver.recv().await {
+            debug!("{name}Received: {message}");
+            self.socket.send(message).await;
+            trace!("{name}Message queued");
+        }
+
+        // All bridge Senders have been dropped; this happens only during
+        // graceful shutdown.
+        info!("{name}Input channel closed (shutdown); exiting");
+        Ok(())
+    }
+
+    fn format_name(&self) -> String {
+        format!("[memory Output {}] ", self.host)
+    }
+}
+
+/// A named in-process queue on either side, e.g. `memory://feed`. Messages
+/// pass through unchanged.
+pub struct MemoryTransport;
+
+#[async_trait]
+impl Transport for MemoryTransport {
+    fn name(&self) -> &'static str {
+        "memory"
+    }
+
+    fn needs_port(&self) -> bool {
+        false
+    }
+
+    fn supports_input(&self) -> bool {
+        true
+    }
+
+    fn supports_output(&self) -> bool {
+        true
+    }
+
+    async fn run_input(
+        &self,
+        host: &str,
+        port: u16,
+        sinks: InputSinks,
+        options: &TransportOptions,
+        status: &StatusReporter,
+    ) -> Result<()> {
+        crate::transport::serve_input::<MemoryQueue>(host, port, sinks, options, status).await
+    }
+
+    async fn run_output(
+        &self,
+        host: &str,
+        port: u16,
+        receiver: &mut Receiver<Message>,
+        options: &TransportOptions,
+        status: &StatusReporter,
+    ) -> Result<()> {
+        crate::transport::serve_output::<MemoryQueue>(host, port, receiver, options, status).await
+    }
+}
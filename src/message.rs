@@ -0,0 +1,362 @@
+// Copyright (c) 2024-2026 Fred Clausen
+//
+// Licensed under the MIT license: https://opensource.org/licenses/MIT
+// Permission is granted to use, copy, modify, and redistribute the work.
+// Full license information available in the project LICENSE file.
+
+//! Messages as they flow through the bridge, and helpers for reading fields
+//! out of the decoder JSON they carry.
+//!
+//! The bridge itself forwards messages as opaque text; the field helpers are
+//! only used where an output needs to look inside one (topic templates,
+//! filters).
+
+use bytes::{Bytes, BytesMut};
+use serde_json::Value;
+use std::fmt;
+use std::io;
+use std::ops::Deref;
+use std::str::Utf8Error;
+use tokio_util::codec::Decoder;
+
+/// One message, as text that is always valid UTF-8.
+///
+/// The text is held in a `Bytes`, checked once as UTF-8 when the message is
+/// made, so cloning a message for each output it goes to shares it instead
+/// of copying it, and a message framed out of a read buffer is a slice of
+/// that buffer. It derefs to `str`.
+#[derive(Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Message(Bytes);
+
+impl Message {
+    /// A message holding `bytes`, if they are valid UTF-8.
+    ///
+    /// # Errors
+    ///
+    /// Returns where the first invalid byte is if they are not.
+    pub fn from_utf8(bytes: Bytes) -> Result<Self, Utf8Error> {
+        std::str::from_utf8(&bytes)?;
+        Ok(Self(bytes))
+    }
+
+    #[must_use]
+    pub const fn from_static(text: &'static str) -> Self {
+        Self(Bytes::from_static(text.as_bytes()))
+    }
+
+    #[must_use]
+    #[allow(unsafe_code)]
+    pub fn as_str(&self) -> &str {
+        // SAFETY: every constructor takes a `str` or checks the bytes are
+        // UTF-8, and the bytes are never changed after.
+        unsafe { std::str::from_utf8_unchecked(&self.0) }
+    }
+
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    #[must_use]
+    pub fn into_bytes(self) -> Bytes {
+        self.0
+    }
+
+    /// What to write after the message to end its line: nothing if it
+    /// already ends with a newline, or a newline if not.
+    #[must_use]
+    pub fn line_ending(&self) -> &'static [u8] {
+        if self.0.ends_with(b"\n") { b"" } else { b"\n" }
+    }
+}
+
+impl Deref for Message {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for Message {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<[u8]> for Message {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+/// Takes over the string's buffer without copying it.
+impl From<String> for Message {
+    fn from(text: String) -> Self {
+        Self(Bytes::from(text.into_bytes()))
+    }
+}
+
+impl From<&str> for Message {
+    fn from(text: &str) -> Self {
+        Self(Bytes::copy_from_slice(text.as_bytes()))
+    }
+}
+
+impl From<Message> for Bytes {
+    fn from(message: Message) -> Self {
+        message.0
+    }
+}
+
+impl From<Message> for String {
+    fn from(message: Message) -> Self {
+        message.as_str().to_string()
+    }
+}
+
+impl PartialEq<str> for Message {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Message {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialEq<String> for Message {
+    fn eq(&self, other: &String) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<Message> for String {
+    fn eq(&self, other: &Message) -> bool {
+        self == other.as_str()
+    }
+}
+
+/// Frames newline-delimited messages out of a byte stream.
+///
+/// Like `LinesCodec`, the newline and any carriage return before it are
+/// dropped. Unlike it, each message is a slice of the read buffer rather
+/// than a new `String`, and a line that is not valid UTF-8 is converted
+/// lossily with a warning, as `udp` and `tail` do, rather than being an
+/// error: `FramedRead` ends the stream after any error, which would lose
+/// every line after it.
+#[derive(Debug, Default)]
+pub struct MessageCodec {
+    /// How far the buffer has already been searched for a newline.
+    searched: usize,
+}
+
+impl MessageCodec {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { searched: 0 }
+    }
+}
+
+impl Decoder for MessageCodec {
+    type Item = Message;
+    type Error = io::Error;
+
+    fn decode(&mut self, buf: &mut BytesMut) -> Result<Option<Message>, io::Error> {
+        let Some(offset) = buf[self.searched..].iter().position(|b| *b == b'\n') else {
+            self.searched = buf.len();
+            return Ok(None);
+        };
+        let end = self.searched + offset;
+        self.searched = 0;
+        let mut line = buf.split_to(end + 1);
+        line.truncate(end);
+        if line.ends_with(b"\r") {
+            line.truncate(end - 1);
+        }
+        Ok(Some(frame(line)))
+    }
+
+    fn decode_eof(&mut self, buf: &mut BytesMut) -> Result<Option<Message>, io::Error> {
+        if let Some(message) = self.decode(buf)? {
+            return Ok(Some(message));
+        }
+        self.searched = 0;
+        if buf.is_empty() {
+            return Ok(None);
+        }
+        let mut line = buf.split();
+        if line.ends_with(b"\r") {
+            line.truncate(line.len() - 1);
+        }
+        Ok(Some(frame(line)))
+    }
+}
+
+fn frame(line: BytesMut) -> Message {
+    let line = line.freeze();
+    Message::from_utf8(line.clone()).unwrap_or_else(|e| {
+        warn!(
+            "Non-UTF-8 line ({} bytes, error at byte {}); using lossy conversion",
+            line.len(),
+            e.valid_up_to()
+        );
+        Message::from(String::from_utf8_lossy(&line).into_owned())
+    })
+}
+
+/// Which decoder family produced `message`, judged by its shape.
+///
+/// `dumpvdl2` and `dumphfdl` nest everything under a `vdl2`/`hfdl` key,
+/// `vdlm2dec` uses the `acarsdec` layout but names itself in `app.name`,
+/// and anything else is treated as `acarsdec` output.
+#[must_use]
+pub fn message_type(message: &Value) -> &'static str {
+    if message.get("vdl2").is_some() {
+        return "vdl2";
+    }
+    if message.get("hfdl").is_some() {
+        return "hfdl";
+    }
+    match message.pointer("/app/name").and_then(Value::as_str) {
+        Some("vdlm2dec") => "vdl2",
+        _ => "acars",
+    }
+}
+
+/// The aircraft registration, if the message carries one.
+///
+/// `acarsdec` and `vdlm2dec` put it in a top-level `tail`; `dumpvdl2` and
+/// `dumphfdl` put it in a `reg` field of the nested `acars` object, padded
+/// with leading dots.
+#[must_use]
+pub fn tail(message: &Value) -> Option<&str> {
+    let tail = match message.get("tail").and_then(Value::as_str) {
+        Some(tail) => tail,
+        None => find_key(message, "acars")?.get("reg")?.as_str()?,
+    };
+    let tail = tail.trim_start_matches('.').trim();
+    (!tail.is_empty()).then_some(tail)
+}
+
+/// The ACARS layer of a message.
+///
+/// That is the nested `acars` object of a `dumpvdl2` or `dumphfdl` frame, or
+/// the message itself for the flat `acarsdec` and `vdlm2dec` layout. A frame
+/// with no ACARS payload gives the message itself, which then just lacks the
+/// ACARS fields.
+#[must_use]
+pub fn acars(message: &Value) -> &Value {
+    find_key(message, "acars").unwrap_or(message)
+}
+
+/// Look up a field by name.
+///
+/// `type` and `tail` are the derived values above; anything else is a dotted
+/// path into the JSON (`vdl2.freq`, `app.name`). Strings are returned as-is
+/// and numbers and booleans are formatted; objects, arrays and nulls count
+/// as missing.
+#[must_use]
+pub fn field(message: &Value, name: &str) -> Option<String> {
+    match name {
+        "type" => Some(message_type(message).to_string()),
+        "tail" => tail(message).map(str::to_string),
+        path => {
+            let value = path
+                .split('.')
+                .try_fold(message, |value, key| value.get(key))?;
+            match value {
+                Value::String(s) => Some(s.clone()),
+                Value::Number(n) => Some(n.to_string()),
+                Value::Bool(b) => Some(b.to_string()),
+                _ => None,
+            }
+        }
+    }
+}
+
+/// Depth-first search for the first object stored under `key`.
+fn find_key<'a>(value: &'a Value, key: &str) -> Option<&'a Value> {
+    match value {
+        Value::Object(map) => map
+            .get(key)
+            .filter(|found| found.is_object())
+            .or_else(|| map.values().find_map(|child| find_key(child, key))),
+        Value::Array(items) => items.iter().find_map(|child| find_key(child, key)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(input: &[u8]) -> Vec<String> {
+        let mut codec = MessageCodec::new();
+        let mut buf = BytesMut::from(input);
+        let mut texts = Vec::new();
+        while let Some(message) = codec.decode_eof(&mut buf).expect("no error") {
+            texts.push(message.into());
+        }
+        texts
+    }
+
+    #[test]
+    fn crlf_and_lf_both_end_a_line() {
+        assert_eq!(texts(b"one\r\ntwo\nthree\r\n"), ["one", "two", "three"]);
+        assert_eq!(texts(b"\r\n\n"), ["", ""]);
+    }
+
+    #[test]
+    fn the_last_line_needs_no_newline() {
+        assert_eq!(texts(b"one\ntwo"), ["one", "two"]);
+        assert_eq!(texts(b"one\ntwo\r"), ["one", "two"]);
+        assert!(texts(b"").is_empty());
+    }
+
+    #[test]
+    fn a_line_is_only_framed_once_complete() {
+        let mut codec = MessageCodec::new();
+        let mut buf = BytesMut::from(&b"par"[..]);
+        assert!(codec.decode(&mut buf).expect("no error").is_none());
+        buf.extend_from_slice(b"tial\r");
+        assert!(codec.decode(&mut buf).expect("no error").is_none());
+        buf.extend_from_slice(b"\nnext");
+        assert_eq!(
+            codec.decode(&mut buf).expect("no error").expect("a line"),
+            "partial"
+        );
+        assert_eq!(&buf[..], b"next");
+    }
+
+    #[test]
+    fn invalid_utf8_is_replaced_without_ending_the_stream() {
+        assert_eq!(
+            texts(b"one\n\xff\xfe\nthree"),
+            ["one", "\u{fffd}\u{fffd}", "three"]
+        );
+    }
+
+    #[test]
+    fn messages_are_checked_when_made_from_bytes() {
+        assert!(Message::from_utf8(Bytes::from_static(b"\xc3\x28")).is_err());
+        let message = Message::from_utf8(Bytes::from_static("h\u{e9}".as_bytes())).expect("valid");
+        assert_eq!(message, "h\u{e9}");
+        assert_eq!(message.line_ending(), b"\n");
+        assert_eq!(Message::from("line\n").line_ending(), b"");
+    }
+}
@@ -0,0 +1,333 @@
+// Copyright (c) 2024-2026 Fred Clausen
+//
+// Licensed under the MIT license: https://opensource.org/licenses/MIT
+// Permission is granted to use, copy, modify, and redistribute the work.
+// Full license information available in the project LICENSE file.
+
+use anyhow::{Error, Result};
+use async_trait::async_trait;
+use rumqttc::{AsyncClient, Event, EventLoop, Incoming, Outgoing, QoS};
+use std::time::Duration;
+use tokio::sync::mpsc::Receiver;
+
+use crate::config::Config;
+use crate::endpoint::{Endpoint, parse_at_least, parse_flag, parse_setting, unknown_setting};
+use crate::message::{self, Message};
+use crate::serverconfig::OutputServer;
+use crate::serverconfig::OutputServerOptions;
+use crate::serverconfig::TransportOptions;
+use crate::status::StatusReporter;
+use crate::transport::{Checks, Transport};
+
+/// Requests the `AsyncClient` may queue ahead of the event loop.
+const REQUEST_CAPACITY: usize = 64;
+/// How long a graceful shutdown waits for queued publishes to be written
+/// before the connection is dropped anyway.
+const DISCONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Substituted for template fields the message doesn't have.
+const MISSING_FIELD: &str = "unknown";
+
+/// Settings for the MQTT publish output.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MqttOptions {
+    /// Topic for each message. `{field}` placeholders are replaced with
+    /// values from the message; see `message::field` for the names.
+    pub topic_template: String,
+    pub qos: QoS,
+    pub retain: bool,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Defaults to `acars-bridge-<pid>`.
+    pub client_id: Option<String>,
+    pub keep_alive: Duration,
+}
+
+impl Default for MqttOptions {
+    fn default() -> Self {
+        Self {
+            topic_template: "acars/{type}".to_string(),
+            qos: QoS::AtMostOnce,
+            retain: false,
+            username: None,
+            password: None,
+            client_id: None,
+            keep_alive: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Map a `--mqtt-qos` level to the `rumqttc` enum.
+///
+/// # Errors
+///
+/// Returns an error for anything other than 0, 1 or 2.
+pub fn parse_qos(qos: u8) -> Result<QoS> {
+    rumqttc::qos(qos).map_err(|_| Error::msg(format!("Invalid MQTT QoS: {qos}")))
+}
+
+/// The connected client and the event loop that performs its network I/O.
+/// The event loop must be polled for anything to be sent, so it travels with
+/// the client into `watch_queue`.
+pub struct MqttSession {
+    client: AsyncClient,
+    eventloop: EventLoop,
+}
+
+/// Expand `{field}` placeholders in `template` from `raw`. Substituted
+/// values have MQTT wildcard and level separator characters replaced so a
+/// field value can never add topic levels or turn the topic into a filter.
+/// Without placeholders the template is returned untouched and the message
+/// is never parsed.
+fn render_topic(template: &str, raw: &str) -> String {
+    if !template.contains('{') {
+        return template.to_string();
+    }
+
+    let parsed = serde_json::from_str::<serde_json::Value>(raw).ok();
+    let mut topic = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(len) = rest[start..].find('}') else {
+            break;
+        };
+        topic.push_str(&rest[..start]);
+        let name = &rest[start + 1..start + len];
+        let value = parsed
+            .as_ref()
+            .and_then(|message| message::field(message, name))
+            .filter(|value| !value.is_empty())
+            .unwrap_or_else(|| MISSING_FIELD.to_string());
+        topic.extend(value.chars().map(|c| match c {
+            '/' | '+' | '#' | '\0' => '_',
+            c => c,
+        }));
+        rest = &rest[start + len + 1..];
+    }
+    topic.push_str(rest);
+    topic
+}
+
+/// Poll the event loop until the connection fails or a requested disconnect
+/// has been written.
+async fn drive(mut eventloop: EventLoop, name: String) -> Result<(), Error> {
+    loop {
+        match eventloop.poll().await {
+            Ok(Event::Outgoing(Outgoing::Disconnect)) => return Ok(()),
+            Ok(event) => trace!("{name}{event:?}"),
+            Err(e) => return Err(Error::msg(format!("{name}MQTT connection error: {e}"))),
+        }
+    }
+}
+
+#[async_trait]
+impl OutputServer for OutputServerOptions<MqttSession> {
+    async fn new(host: &str, port: u16, options: &TransportOptions) -> Result<Self, Error> {
+        let mqtt = &options.get::<MqttOptions>();
+        let client_id = mqtt
+            .client_id
+            .clone()
+            .unwrap_or_else(|| format!("acars-bridge-{}", std::process::id()));
+        let mut mqtt_options = rumqttc::MqttOptions::new(client_id, host, port);
+        mqtt_options.set_keep_alive(mqtt.keep_alive);
+        if let Some(username) = &mqtt.username {
+            mqtt_options.set_credentials(username, mqtt.password.clone().unwrap_or_default());
+        }
+
+        let (client, mut eventloop) = AsyncClient::new(mqtt_options, REQUEST_CAPACITY);
+
+        // Poll until the broker accepts the connection so a bad address or
+        // rejected credentials fail here, under the supervisor's backoff,
+        // rather than on the first publish.
+        loop {
+            match eventloop.poll().await {
+                Ok(Event::Incoming(Incoming::ConnAck(ack))) => {
+                    debug!("[MQTT Output {host}:{port}] Connected: {ack:?}");
+                    break;
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    return Err(Error::msg(format!(
+                        "[MQTT Output {host}:{port}] Error connecting: {e}"
+                    )));
+                }
+            }
+        }
+
+        Ok(Self {
+            host: host.to_string(),
+            port,
+            socket: MqttSession { client, eventloop },
+            options: options.clone(),
+        })
+    }
+
+    async fn watch_queue(self, receiver: &mut Receiver<Message>) -> Result<(), Error> {
+        let name = self.format_name();
+        let MqttSession { client, eventloop } = self.socket;
+        let mqtt = &self.options.get::<MqttOptions>();
+        let mut driver = tokio::spawn(drive(eventloop, name.clone()));
+
+        loop {
+            tokio::select! {
+                biased;
+                result = &mut driver => {
+                    return match result {
+                        Ok(Err(e)) => Err(e),
+                        Ok(Ok(())) => Err(Error::msg(format!("{name}MQTT event loop stopped unexpectedly"))),
+                        Err(e) => Err(Error::msg(format!("{name}MQTT event loop panicked: {e}"))),
+                    };
+                }
+                message = receiver.recv() => {
+                    let Some(message) = message else {
+                        break;
+                    };
+                    debug!("{name}Received: {message}");
+
+                    let topic = render_topic(&mqtt.topic_template, &message);
+                    if let Err(e) = client
+                        .publish_bytes(topic.as_str(), mqtt.qos, mqtt.retain, message.into_bytes())
+                        .await
+                    {
+                        driver.abort();
+                        return Err(Error::msg(format!(
+                            "{name}Error publishing message to {topic}: {e}"
+                        )));
+                    }
+                    trace!("{name}Message queued for {topic}");
+                }
+            }
+        }
+
+        // All bridge Senders have been dropped; this happens only during
+        // graceful shutdown. Requests are handled in order, so once the
+        // disconnect has gone out every queued publish has been written.
+        info!("{name}Input channel closed (shutdown); disconnecting");
+        if let Err(e) = client.disconnect().await {
+            warn!("{name}Error requesting disconnect: {e}");
+        }
+        match tokio::time::timeout(DISCONNECT_TIMEOUT, &mut driver).await {
+            Ok(Ok(Ok(()))) => {}
+            Ok(Ok(Err(e))) => warn!("{e} during shutdown"),
+            Ok(Err(e)) => warn!("{name}MQTT event loop panicked during shutdown: {e}"),
+            Err(_) => {
+                warn!("{name}Timed out waiting for disconnect; queued messages may be lost");
+                driver.abort();
+            }
+        }
+        Ok(())
+    }
+
+    fn format_name(&self) -> String {
+        format!("[MQTT Output {}:{}] ", self.host, self.port)
+    }
+}
+
+/// MQTT publish to a broker.
+pub struct MqttTransport;
+
+#[async_trait]
+impl Transport for MqttTransport {
+    fn name(&self) -> &'static str {
+        "mqtt"
+    }
+
+    fn supports_output(&self) -> bool {
+        true
+    }
+
+    fn same_options(&self, old: &TransportOptions, new: &TransportOptions) -> bool {
+        old.get::<MqttOptions>() == new.get::<MqttOptions>()
+    }
+
+    fn options_from_config(&self, config: &Config, options: &mut TransportOptions) -> Result<()> {
+        options.set(MqttOptions {
+            topic_template: config.get_mqtt_topic().to_string(),
+            qos: parse_qos(config.get_mqtt_qos())?,
+            retain: config.get_mqtt_retain(),
+            username: config.get_mqtt_username().clone(),
+            password: config.get_mqtt_password().clone(),
+            client_id: config.get_mqtt_client_id().clone(),
+            keep_alive: Duration::from_secs(config.get_mqtt_keep_alive()),
+        });
+        Ok(())
+    }
+
+    fn configure(&self, name: &str, value: &str, options: &mut TransportOptions) -> Result<()> {
+        let mqtt = options.get_mut::<MqttOptions>();
+        match name {
+            "topic" => mqtt.topic_template = value.to_string(),
+            "qos" => mqtt.qos = parse_qos(parse_setting(name, value)?)?,
+            "retain" => mqtt.retain = parse_flag(name, value)?,
+            "username" => mqtt.username = Some(value.to_string()),
+            "password" => mqtt.password = Some(value.to_string()),
+            "client-id" => mqtt.client_id = Some(value.to_string()),
+            "keep-alive" => {
+                mqtt.keep_alive = Duration::from_secs(parse_at_least(name, value, 5)?);
+            }
+            _ => return Err(unknown_setting(self.name(), name)),
+        }
+        Ok(())
+    }
+
+    async fn check(&self, endpoint: &Endpoint, _input: bool) -> Checks {
+        crate::check::resolves(&endpoint.host, endpoint.port).await
+    }
+
+    async fn run_output(
+        &self,
+        host: &str,
+        port: u16,
+        receiver: &mut Receiver<Message>,
+        options: &TransportOptions,
+        status: &StatusReporter,
+    ) -> Result<()> {
+        crate::transport::serve_output::<MqttSession>(host, port, receiver, options, status).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MESSAGE: &str =
+        r#"{"vdl2":{"avlc":{"src":{"addr":"A1B2C3"}}},"flight":"UA/12+#3","station":"kbos"}"#;
+
+    #[test]
+    fn fields_fill_the_template() {
+        assert_eq!(
+            render_topic("acars/{station}/{vdl2.avlc.src.addr}", MESSAGE),
+            "acars/kbos/A1B2C3"
+        );
+    }
+
+    #[test]
+    fn field_values_cannot_add_levels_or_wildcards() {
+        assert_eq!(render_topic("acars/{flight}", MESSAGE), "acars/UA_12__3");
+        assert_eq!(
+            render_topic("acars/{station}", r#"{"station":"a\u0000b"}"#),
+            "acars/a_b"
+        );
+    }
+
+    #[test]
+    fn missing_fields_and_unparsed_messages_are_unknown() {
+        assert_eq!(
+            render_topic("acars/{tail}/{station}", MESSAGE),
+            format!("acars/{MISSING_FIELD}/kbos")
+        );
+        assert_eq!(
+            render_topic("acars/{station}", r#"{"station":""}"#),
+            format!("acars/{MISSING_FIELD}")
+        );
+        assert_eq!(
+            render_topic("acars/{station}", "not json"),
+            format!("acars/{MISSING_FIELD}")
+        );
+    }
+
+    #[test]
+    fn templates_without_placeholders_are_left_alone() {
+        assert_eq!(render_topic("acars/#", "not json"), "acars/#");
+        assert_eq!(render_topic("acars/{station", MESSAGE), "acars/{station");
+    }
+}
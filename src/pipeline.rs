@@ -0,0 +1,458 @@
+// Copyright (c) 2024-2026 Fred Clausen
+//
+// Licensed under the MIT license: https://opensource.org/licenses/MIT
+// Permission is granted to use, copy, modify, and redistribute the work.
+// Full license information available in the project LICENSE file.
+
+//! The input and output supervisors main runs, and how a reload on `SIGHUP`
+//! swaps them.
+//!
+//! A reload diffs the new configuration against what each supervisor was
+//! started with and replaces only the sides that changed. The bridge channel
+//! outlives them all: a replaced input simply stops sending, and a replaced
+//! output hands its receiver to the next one, so messages already queued
+//! carry over.
+
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
+use std::path::Path;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc::{Receiver, Sender};
+use tokio::task::{JoinError, JoinHandle};
+use tokio_util::sync::CancellationToken;
+
+use crate::capture::Recorder;
+use crate::config::Config;
+use crate::endpoint::Endpoint;
+use crate::message::Message;
+use crate::serverconfig::TransportOptions;
+use crate::stats::StatsEvent;
+use crate::status::StatusReporter;
+use crate::supervisor::{Supervise, Supervisor};
+
+/// Where an input server delivers what it receives.
+#[derive(Clone)]
+pub struct InputSinks {
+    /// Master Sender clone for the input->output bridge channel.
+    pub output: Option<Sender<Message>>,
+    /// Master Sender clone for the stats channel.
+    pub stats: Sender<StatsEvent>,
+    /// Capture handle when recording.
+    pub recorder: Option<Recorder>,
+}
+
+/// What every supervisor is started with.
+pub struct Context {
+    /// The configuration the running supervisors were started from.
+    pub config: Config,
+    pub options: TransportOptions,
+    pub sinks: InputSinks,
+    pub input_status: StatusReporter,
+    pub output_status: StatusReporter,
+    /// Cancelled at shutdown.
+    pub cancel: CancellationToken,
+    /// Cancelled by a supervisor that ran out of restart attempts.
+    pub gave_up: CancellationToken,
+}
+
+/// One running supervisor and the endpoint it was started for.
+pub struct Supervised<T> {
+    pub endpoint: Option<Endpoint>,
+    /// Cancelled to stop this supervisor alone, when a reload replaces it.
+    retire: CancellationToken,
+    pub handle: JoinHandle<T>,
+}
+
+/// Why `Pipeline::run_until_stopped` returned.
+pub enum Stopped {
+    /// A `SIGINT` or `SIGTERM` arrived.
+    Signal,
+    /// The user quit the dashboard, or a supervisor gave up.
+    Quit,
+    /// An input that ends at EOF finished, with its supervisor's result.
+    InputFinished(Result<(), JoinError>),
+}
+
+pub struct Pipeline {
+    pub context: Context,
+    pub input: Supervised<()>,
+    /// The output supervisor, or the task discarding messages when there is
+    /// no destination. Either resolves to the bridge receiver.
+    pub output: Supervised<Receiver<Message>>,
+}
+
+impl Context {
+    fn start_input(&self, source: Endpoint) -> Supervised<()> {
+        let label = source.label();
+        info!("Creating input server: {label}");
+        self.input_status.endpoint(label.clone());
+        let supervisor = Supervisor {
+            label: format!("input/{label}"),
+            policy: self.options.source_backoff.clone().unwrap_or_default(),
+            status: self.input_status.clone(),
+            // A child token, so the shutdown stops it as well.
+            stop: self.cancel.child_token(),
+            shutdown: self.cancel.clone(),
+            gave_up: self.gave_up.clone(),
+        };
+        supervise_input(source, self.sinks.clone(), supervisor)
+    }
+
+    fn start_output(
+        &self,
+        destination: Option<Endpoint>,
+        receiver: Receiver<Message>,
+    ) -> Supervised<Receiver<Message>> {
+        let Some(destination) = destination else {
+            info!("No destination set; messages will only be counted");
+            self.output_status.endpoint(String::new());
+            self.output_status.stopped();
+            return discard(receiver);
+        };
+        let label = destination.label();
+        info!("Creating output server: {label}");
+        self.output_status.endpoint(label.clone());
+        let supervisor = Supervisor {
+            label: format!("output/{label}"),
+            policy: self.options.destination_backoff.clone().unwrap_or_default(),
+            status: self.output_status.clone(),
+            // Only this stops the output mid-run; the shutdown lets it drain
+            // the bridge channel first.
+            stop: CancellationToken::new(),
+            shutdown: self.cancel.clone(),
+            gave_up: self.gave_up.clone(),
+        };
+        supervise_output(destination, receiver, supervisor)
+    }
+}
+
+/// Run the input server for `source` under `supervisor`, which its `stop`
+/// token retires.
+pub(crate) fn supervise_input(
+    source: Endpoint,
+    sinks: InputSinks,
+    supervisor: Supervisor,
+) -> Supervised<()> {
+    let retire = supervisor.stop.clone();
+    let task = InputTask {
+        endpoint: source.clone(),
+        sinks,
+    };
+    Supervised {
+        endpoint: Some(source),
+        retire,
+        handle: tokio::spawn(async move {
+            supervisor.run(task).await;
+        }),
+    }
+}
+
+/// Run the output server for `destination` under `supervisor`, reading
+/// from `receiver`.
+pub(crate) fn supervise_output(
+    destination: Endpoint,
+    receiver: Receiver<Message>,
+    supervisor: Supervisor,
+) -> Supervised<Receiver<Message>> {
+    let retire = supervisor.stop.clone();
+    let task = OutputTask {
+        endpoint: destination.clone(),
+        receiver,
+    };
+    Supervised {
+        endpoint: Some(destination),
+        retire,
+        handle: tokio::spawn(async move { supervisor.run(task).await.receiver }),
+    }
+}
+
+/// Stand in for the output when there is no destination.
+fn discard(receiver: Receiver<Message>) -> Supervised<Receiver<Message>> {
+    let retire = CancellationToken::new();
+    Supervised {
+        endpoint: None,
+        retire: retire.clone(),
+        handle: spawn_discard(receiver, retire),
+    }
+}
+
+/// The input server for one endpoint, as its supervisor runs it.
+struct InputTask {
+    endpoint: Endpoint,
+    /// Cloned into each server, so the master copies keep the channels open
+    /// even when a server dies.
+    sinks: InputSinks,
+}
+
+#[async_trait]
+impl Supervise for InputTask {
+    async fn run_once(&mut self, status: &StatusReporter) -> Result<()> {
+        let Endpoint {
+            transport,
+            host,
+            port,
+            options,
+            ..
+        } = &self.endpoint;
+        transport
+            .run_input(host, *port, self.sinks.clone(), options, status)
+            .await
+    }
+
+    fn done_on_success(&self) -> bool {
+        self.endpoint.transport.ends_at_eof()
+    }
+}
+
+/// The output server for one endpoint, as its supervisor runs it. It owns
+/// the bridge receiver and lends it to each server in turn.
+///
+/// The master `Sender` keeps the channel open, so `watch_queue` normally
+/// never sees it close. At shutdown main drops the master after the input
+/// supervisor exits, and the server returns `Ok(())` once it has sent what
+/// was queued, which ends the task rather than restarting it.
+struct OutputTask {
+    endpoint: Endpoint,
+    receiver: Receiver<Message>,
+}
+
+#[async_trait]
+impl Supervise for OutputTask {
+    async fn run_once(&mut self, status: &StatusReporter) -> Result<()> {
+        let Endpoint {
+            transport,
+            host,
+            port,
+            options,
+            ..
+        } = &self.endpoint;
+        transport
+            .run_output(host, *port, &mut self.receiver, options, status)
+            .await
+    }
+
+    fn done_on_success(&self) -> bool {
+        true
+    }
+}
+
+/// Stand in for the output when there is no destination, discarding what
+/// the inputs send, until `retire` is cancelled or the bridge channel closes
+/// at shutdown. Hands the receiver back like an output, so a reload can
+/// start a real output on it.
+fn spawn_discard(
+    mut receiver: Receiver<Message>,
+    retire: CancellationToken,
+) -> JoinHandle<Receiver<Message>> {
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                biased;
+                () = retire.cancelled() => break,
+                message = receiver.recv() => if message.is_none() {
+                    break;
+                },
+            }
+        }
+        receiver
+    })
+}
+
+impl Pipeline {
+    /// Start the supervisors for `source` and `destination`, the output
+    /// reading the bridge channel through `receiver`.
+    #[must_use]
+    pub fn start(
+        context: Context,
+        source: Endpoint,
+        destination: Option<Endpoint>,
+        receiver: Receiver<Message>,
+    ) -> Self {
+        let input = context.start_input(source);
+        let output = context.start_output(destination, receiver);
+        Self {
+            context,
+            input,
+            output,
+        }
+    }
+
+    /// Wait for a shutdown signal, for an input that ends at EOF to finish,
+    /// for a supervisor to give up, or for `ui_done` (the user quit the
+    /// dashboard), reloading on every `SIGHUP` meanwhile.
+    pub async fn run_until_stopped(&mut self, ui_done: &CancellationToken) -> Stopped {
+        let shutdown = crate::cli::shutdown_signal();
+        tokio::pin!(shutdown);
+        let mut hangups = Hangups::new();
+        loop {
+            tokio::select! {
+                () = &mut shutdown => return Stopped::Signal,
+                () = ui_done.cancelled() => return Stopped::Quit,
+                () = self.context.gave_up.cancelled() => return Stopped::Quit,
+                joined = &mut self.input.handle => return Stopped::InputFinished(joined),
+                () = hangups.recv() => self.reload().await,
+            }
+        }
+    }
+
+    async fn reload(&mut self) {
+        info!("[RELOAD] Received SIGHUP; reading the configuration again");
+        let result = match Config::reload() {
+            Ok(config) => self.apply(config).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            error!("[RELOAD] {e:#}; keeping the running configuration");
+        }
+    }
+
+    /// Replace the supervisors whose endpoint or settings differ in
+    /// `config`. Nothing is touched unless all of `config` is valid.
+    async fn apply(&mut self, config: Config) -> Result<()> {
+        let options = crate::cli::build_transport_options(&config)?;
+        let source = crate::cli::parse_source(&config, &options)?;
+        let destination = crate::cli::parse_destination(&config, &options)?;
+        crate::cli::dashboard_requested(&config)?;
+
+        for flag in self.context.config.restart_only_changes(&config) {
+            warn!("[RELOAD] {flag} changed; it only takes effect after a restart");
+        }
+        let input_changed = !self
+            .input
+            .endpoint
+            .as_ref()
+            .is_some_and(|running| running.same_as(&source))
+            || self.context.options.source_backoff != options.source_backoff;
+        let output_changed = match (&self.output.endpoint, &destination) {
+            (Some(running), Some(destination)) => !running.same_as(destination),
+            (running, destination) => running.is_some() != destination.is_some(),
+        } || self.context.options.destination_backoff
+            != options.destination_backoff;
+        self.context.config = config;
+        self.context.options = options;
+
+        if input_changed {
+            info!("[RELOAD] Input changed; replacing it");
+            self.input.retire.cancel();
+            if let Err(e) = (&mut self.input.handle).await {
+                error!("[RELOAD] Input supervisor join error: {e}");
+            }
+            self.input = self.context.start_input(source);
+        } else {
+            info!("[RELOAD] Input unchanged");
+        }
+
+        if output_changed {
+            info!("[RELOAD] Output changed; replacing it");
+            self.output.retire.cancel();
+            // The receiver is only lost if the supervisor panicked, which
+            // is a bug; carry the panic on rather than run without output.
+            let receiver = match (&mut self.output.handle).await {
+                Ok(receiver) => receiver,
+                Err(e) => std::panic::resume_unwind(e.into_panic()),
+            };
+            self.output = self.context.start_output(destination, receiver);
+        } else {
+            info!("[RELOAD] Output unchanged");
+        }
+        Ok(())
+    }
+}
+
+impl Supervised<Receiver<Message>> {
+    /// Wait for the output to deliver what is queued and exit, once the
+    /// bridge channel is closed. After `timeout`, if set, the output is
+    /// stopped where it is. Messages it didn't deliver are appended to
+    /// `spill` if set and dropped otherwise, and counted either way.
+    pub async fn drain(mut self, timeout: Option<Duration>, spill: Option<&Path>) {
+        let finished = match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, &mut self.handle).await.ok(),
+            None => Some((&mut self.handle).await),
+        };
+        let joined = if let Some(joined) = finished {
+            joined
+        } else {
+            warn!("[SHUTDOWN] Output still draining after the drain timeout; stopping it");
+            self.retire.cancel();
+            self.handle.await
+        };
+        match joined {
+            Ok(receiver) => undelivered(receiver, spill).await,
+            Err(e) => error!("[SHUTDOWN] Output supervisor join error: {e}"),
+        }
+    }
+}
+
+/// Take whatever is left in the closed bridge channel once the output has
+/// stopped, and spill or drop it.
+async fn undelivered(mut receiver: Receiver<Message>, spill: Option<&Path>) {
+    let mut messages = Vec::new();
+    while let Some(message) = receiver.recv().await {
+        messages.push(message);
+    }
+    if messages.is_empty() {
+        return;
+    }
+    let count = messages.len();
+    match spill {
+        Some(path) => match spill_to(path, &messages).await {
+            Ok(()) => warn!(
+                "[SHUTDOWN] Wrote {count} undelivered message(s) to {}",
+                path.display()
+            ),
+            Err(e) => error!("[SHUTDOWN] {e:#}; dropped {count} undelivered message(s)"),
+        },
+        None => warn!("[SHUTDOWN] Dropped {count} undelivered message(s)"),
+    }
+}
+
+/// Append `messages` to the file at `path`, one per line.
+async fn spill_to(path: &Path, messages: &[Message]) -> Result<()> {
+    let mut contents = String::new();
+    for message in messages {
+        contents.push_str(message.trim_end_matches(['\r', '\n']));
+        contents.push('\n');
+    }
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await
+        .with_context(|| format!("Error opening spill file {}", path.display()))?;
+    file.write_all(contents.as_bytes())
+        .await
+        .with_context(|| format!("Error writing spill file {}", path.display()))?;
+    file.flush()
+        .await
+        .with_context(|| format!("Error writing spill file {}", path.display()))
+}
+
+/// Resolves on each `SIGHUP`. Never resolves where there is no such signal
+/// or its handler couldn't be installed.
+struct Hangups {
+    #[cfg(unix)]
+    signal: Option<tokio::signal::unix::Signal>,
+}
+
+impl Hangups {
+    fn new() -> Self {
+        Self {
+            #[cfg(unix)]
+            signal: tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())
+                .map_err(|e| error!("Failed to install SIGHUP handler: {e}"))
+                .ok(),
+        }
+    }
+
+    async fn recv(&mut self) {
+        #[cfg(unix)]
+        if let Some(signal) = &mut self.signal {
+            if signal.recv().await.is_some() {
+                return;
+            }
+            self.signal = None;
+        }
+        std::future::pending::<()>().await;
+    }
+}
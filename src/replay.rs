@@ -0,0 +1,265 @@
+// Copyright (c) 2024-2026 Fred Clausen
+//
+// Licensed under the MIT license: https://opensource.org/licenses/MIT
+// Permission is granted to use, copy, modify, and redistribute the work.
+// Full license information available in the project LICENSE file.
+
+use anyhow::{Context, Error, Result};
+use async_trait::async_trait;
+use std::borrow::Cow;
+use std::path::Path;
+use std::time::Duration;
+use tokio::sync::mpsc::Sender;
+use tokio::time::Instant;
+
+use crate::capture::{CaptureReader, CaptureRecord, Recorder};
+use crate::config::Config;
+use crate::endpoint::{Endpoint, parse_flag, parse_setting, unknown_setting};
+use crate::message::Message;
+use crate::pipeline::InputSinks;
+use crate::serverconfig::InputServer;
+use crate::serverconfig::InputServerOptions;
+use crate::serverconfig::TransportOptions;
+use crate::stats::StatsEvent;
+use crate::status::StatusReporter;
+use crate::transport::{Checks, Transport};
+use crate::zmq::ZmqOptions;
+
+/// Settings for the replay input.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReplayOptions {
+    /// Multiplier applied to the recorded timing; `2.0` replays twice as
+    /// fast. `None` sends every message as fast as the bridge takes them.
+    pub speed: Option<f64>,
+    /// Start again from the first record after the last one.
+    pub looping: bool,
+}
+
+impl Default for ReplayOptions {
+    fn default() -> Self {
+        Self {
+            speed: Some(1.0),
+            looping: false,
+        }
+    }
+}
+
+impl InputServerOptions<CaptureReader> {
+    /// Turn a record back into the message its input would have forwarded.
+    fn compose(&self, record: &CaptureRecord, name: &str) -> String {
+        let frames = record
+            .frames
+            .iter()
+            .map(|frame| String::from_utf8_lossy(frame))
+            .collect::<Vec<_>>();
+        let message = if frames.len() == 1 {
+            frames.into_iter().next().unwrap_or_default()
+        } else {
+            Cow::Owned(
+                self.options
+                    .get::<ZmqOptions>()
+                    .frame_mode
+                    .compose(frames, name),
+            )
+        };
+        let message = message
+            .strip_suffix("\r\n")
+            .or_else(|| message.strip_suffix('\n'))
+            .unwrap_or(&message);
+        message.to_string()
+    }
+
+    /// Send one pass over the capture. Returns the number of records sent.
+    async fn replay_once(&mut self, name: &str) -> Result<u64> {
+        let mut first: Option<(u64, Instant)> = None;
+        let mut sent = 0;
+        while let Some(record) = self.socket.next_record().await? {
+            if let Some(speed) = self.options.get::<ReplayOptions>().speed {
+                let (first_ns, started) =
+                    *first.get_or_insert_with(|| (record.timestamp_ns, Instant::now()));
+                let gap = Duration::from_nanos(record.timestamp_ns.saturating_sub(first_ns));
+                let due = Duration::try_from_secs_f64(gap.as_secs_f64() / speed)
+                    .ok()
+                    .and_then(|offset| started.checked_add(offset));
+                if let Some(due) = due {
+                    tokio::time::sleep_until(due).await;
+                } else {
+                    // Only a vanishingly small speed gets here; the record
+                    // is due further off than time can count, so never.
+                    warn!(
+                        "{name}At {speed:e}x the next record is never due; waiting until stopped"
+                    );
+                    std::future::pending::<()>().await;
+                }
+            }
+
+            if let Some(recorder) = &self.recorder {
+                let frames = record.frames.iter().map(Vec::as_slice).collect::<Vec<_>>();
+                recorder.record(&record.source, &frames).await;
+            }
+
+            let message = self.compose(&record, name);
+            debug!("{name}Replaying from {}: {message}", record.source);
+
+            if let Some(sender) = &self.sender {
+                if let Err(e) = sender.send(message.into()).await {
+                    return Err(Error::msg(format!("{name}Output channel closed: {e}")));
+                }
+                trace!("{name}Message sent to sender channel");
+            }
+
+            let event = if record.source.starts_with("zmq://") {
+                StatsEvent::ZmqMessage {
+                    frames: record.frames.len(),
+                }
+            } else {
+                StatsEvent::Message
+            };
+            if let Err(e) = self.stats.send(event).await {
+                return Err(Error::msg(format!("{name}Stats channel closed: {e}")));
+            }
+            trace!("{name}Stats sent to channel");
+            sent += 1;
+        }
+        Ok(sent)
+    }
+}
+
+#[async_trait]
+impl InputServer for InputServerOptions<CaptureReader> {
+    /// `host` is the capture file; `port` is unused.
+    async fn new(
+        host: &str,
+        port: u16,
+        sender: Option<Sender<Message>>,
+        stats: Sender<StatsEvent>,
+        recorder: Option<Recorder>,
+        options: &TransportOptions,
+    ) -> Result<Self, Error> {
+        let socket = CaptureReader::open(Path::new(host))
+            .await
+            .with_context(|| format!("[Replay Input {host}] Error opening capture"))?;
+
+        Ok(Self {
+            host: host.to_string(),
+            port,
+            socket,
+            sender,
+            stats,
+            recorder,
+            options: options.clone(),
+        })
+    }
+
+    async fn receive_message(mut self) -> Result<(), Error> {
+        let name = self.format_name();
+        match self.options.get::<ReplayOptions>().speed {
+            Some(speed) => info!("{name}Replaying at {speed}x recorded speed"),
+            None => info!("{name}Replaying as fast as possible"),
+        }
+
+        let mut total: u64 = 0;
+        loop {
+            let sent = self.replay_once(&name).await?;
+            total += sent;
+            if !self.options.get::<ReplayOptions>().looping || sent == 0 {
+                break;
+            }
+            debug!("{name}End of capture; starting again");
+            self.socket.rewind().await?;
+        }
+
+        // The transport ends at EOF, so this drains the outputs and stops
+        // the bridge rather than starting the replay over.
+        info!("{name}Replay finished after {total} message(s)");
+        Ok(())
+    }
+
+    fn format_name(&self) -> String {
+        format!("[Replay Input {}] ", self.host)
+    }
+}
+
+/// Messages read back from a capture made with `--record`. The host is the
+/// capture file and the port is unused.
+pub struct ReplayTransport;
+
+/// The replay speed multiplier, or `None` to replay as fast as possible.
+pub(crate) fn replay_speed(config: &Config) -> Result<Option<f64>> {
+    let speed = config.get_replay_speed();
+    if !speed.is_finite() || speed < 0.0 {
+        return Err(anyhow::anyhow!(
+            "Replay speed must be 0 or a positive number, not {speed}"
+        ));
+    }
+    Ok((speed > 0.0).then_some(speed))
+}
+
+#[async_trait]
+impl Transport for ReplayTransport {
+    fn name(&self) -> &'static str {
+        "replay"
+    }
+
+    fn needs_port(&self) -> bool {
+        false
+    }
+
+    fn supports_input(&self) -> bool {
+        true
+    }
+
+    fn ends_at_eof(&self) -> bool {
+        true
+    }
+
+    fn same_options(&self, old: &TransportOptions, new: &TransportOptions) -> bool {
+        old.get::<ReplayOptions>() == new.get::<ReplayOptions>()
+            && old.get::<ZmqOptions>() == new.get::<ZmqOptions>()
+    }
+
+    fn options_from_config(&self, config: &Config, options: &mut TransportOptions) -> Result<()> {
+        options.set(ReplayOptions {
+            speed: replay_speed(config)?,
+            looping: config.get_replay_loop(),
+        });
+        Ok(())
+    }
+
+    fn configure(&self, name: &str, value: &str, options: &mut TransportOptions) -> Result<()> {
+        match name {
+            "speed" => {
+                let speed = parse_setting::<f64>(name, value)?;
+                if !speed.is_finite() || speed < 0.0 {
+                    return Err(Error::msg(format!(
+                        "Replay speed must be 0 or a positive number, not {speed}"
+                    )));
+                }
+                options.get_mut::<ReplayOptions>().speed = (speed > 0.0).then_some(speed);
+            }
+            "loop" => options.get_mut::<ReplayOptions>().looping = parse_flag(name, value)?,
+            _ => return Err(unknown_setting(self.name(), name)),
+        }
+        Ok(())
+    }
+
+    async fn check(&self, endpoint: &Endpoint, _input: bool) -> Checks {
+        let host = &endpoint.host;
+        let opened = CaptureReader::open(Path::new(host))
+            .await
+            .map(drop)
+            .with_context(|| format!("Error opening {host}"));
+        vec![("open", opened)]
+    }
+
+    async fn run_input(
+        &self,
+        host: &str,
+        port: u16,
+        sinks: InputSinks,
+        options: &TransportOptions,
+        status: &StatusReporter,
+    ) -> Result<()> {
+        crate::transport::serve_input::<CaptureReader>(host, port, sinks, options, status).await
+    }
+}
@@ -7,24 +7,131 @@
 use anyhow::Error;
 use anyhow::Result;
 use async_trait::async_trait;
+use std::any::{Any, TypeId};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
 use tokio::sync::mpsc::{Receiver, Sender};
 
-pub enum SocketType {
-    Tcp,
-    Udp,
-    Zmq,
+use crate::backoff::BackoffPolicy;
+use crate::capture::Recorder;
+use crate::message::Message;
+use crate::stats::StatsEvent;
+
+/// The settings handed to every server the supervisors build.
+///
+/// Built from `Config` in main, again on each reload, and cloned into each
+/// (re)spawn. Each transport keeps its own settings in a type of its own,
+/// such as `HttpOptions`, which it fills in `Transport::options_from_config`
+/// and `Transport::configure` and its servers read with `get`, so a new
+/// transport adds nothing here.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TransportOptions {
+    /// How the input supervisor, and a `tcp` input, retry. `None` leaves
+    /// each with its own default: `BackoffPolicy::default()` for the
+    /// supervisor and `tcp::standard_reconnect_delays` for a connection.
+    pub source_backoff: Option<BackoffPolicy>,
+    /// How the output supervisor, and a `tcp` output, retry, likewise.
+    pub destination_backoff: Option<BackoffPolicy>,
+    sections: Sections,
+}
+
+impl TransportOptions {
+    /// The settings of type `T`, or its defaults if none were set.
+    #[must_use]
+    pub fn get<T: OptionsSection + Clone + Default>(&self) -> Cow<'_, T> {
+        self.sections
+            .0
+            .get(&TypeId::of::<T>())
+            .and_then(|section| section.as_any().downcast_ref())
+            .map_or_else(|| Cow::Owned(T::default()), Cow::Borrowed)
+    }
+
+    /// The settings of type `T` to change, starting from its defaults if
+    /// none were set.
+    ///
+    /// # Panics
+    ///
+    /// Never in practice: each section is stored under its own type's id.
+    pub fn get_mut<T: OptionsSection + Default>(&mut self) -> &mut T {
+        self.sections
+            .0
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(T::default()))
+            .as_any_mut()
+            .downcast_mut()
+            .expect("sections are keyed by their type")
+    }
+
+    /// Replace the settings of type `T`.
+    pub fn set<T: OptionsSection>(&mut self, section: T) {
+        self.sections.0.insert(TypeId::of::<T>(), Box::new(section));
+    }
+
+    /// These options with the settings of type `T` replaced.
+    #[must_use]
+    pub fn with<T: OptionsSection>(mut self, section: T) -> Self {
+        self.set(section);
+        self
+    }
 }
 
-impl TryFrom<&str> for SocketType {
-    type Error = Error;
+/// One transport's settings in `TransportOptions`. Any type that is
+/// `Clone`, `Debug` and `PartialEq` is one.
+pub trait OptionsSection: Any + std::fmt::Debug + Send + Sync {
+    fn clone_section(&self) -> Box<dyn OptionsSection>;
+    fn same_section(&self, other: &dyn OptionsSection) -> bool;
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: Any + Clone + std::fmt::Debug + PartialEq + Send + Sync> OptionsSection for T {
+    fn clone_section(&self) -> Box<dyn OptionsSection> {
+        Box::new(self.clone())
+    }
+
+    fn same_section(&self, other: &dyn OptionsSection) -> bool {
+        other.as_any().downcast_ref::<Self>() == Some(self)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Each transport's settings, by their type.
+#[derive(Default)]
+struct Sections(BTreeMap<TypeId, Box<dyn OptionsSection>>);
+
+impl Clone for Sections {
+    fn clone(&self) -> Self {
+        Self(
+            self.0
+                .iter()
+                .map(|(id, section)| (*id, (**section).clone_section()))
+                .collect(),
+        )
+    }
+}
+
+impl PartialEq for Sections {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.len() == other.0.len()
+            && self.0.iter().all(|(id, section)| {
+                other
+                    .0
+                    .get(id)
+                    .is_some_and(|other| (**section).same_section(&**other))
+            })
+    }
+}
 
-    fn try_from(s: &str) -> Result<Self> {
-        match s.to_lowercase().as_str() {
-            "tcp" => Ok(Self::Tcp),
-            "udp" => Ok(Self::Udp),
-            "zmq" => Ok(Self::Zmq),
-            _ => Err(Error::msg(format!("Unknown Socket Type: {s}"))),
-        }
+impl std::fmt::Debug for Sections {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.0.values()).finish()
     }
 }
 
@@ -32,35 +139,65 @@ pub struct InputServerOptions<T> {
     pub host: String,
     pub port: u16,
     pub socket: T,
-    pub sender: Option<Sender<String>>,
-    pub stats: Sender<u8>,
+    pub sender: Option<Sender<Message>>,
+    pub stats: Sender<StatsEvent>,
+    /// Set when recording; the input adds every raw message to the capture.
+    pub recorder: Option<Recorder>,
+    pub options: TransportOptions,
 }
 
 pub struct OutputServerOptions<T> {
     pub host: String,
     pub port: u16,
     pub socket: T,
+    pub options: TransportOptions,
 }
 
+/// A server that receives messages and hands them to the bridge.
+///
+/// Every built-in input implements this for an `InputServerOptions`, and a
+/// program embedding the bridge can implement it for a type of its own and
+/// add it with `Bridge::input_server`. The supervisor builds a fresh server
+/// with `new` for each (re)start, then runs `receive_message`.
 #[async_trait]
 pub trait InputServer {
+    /// Connect, bind or open whatever the server reads from. Each message
+    /// goes to `sender` (`None` when nothing reads them), and a
+    /// `StatsEvent` for it to `stats`. When `recorder` is set the raw bytes
+    /// go to it as well.
     async fn new(
         host: &str,
         port: u16,
-        sender: Option<Sender<String>>,
-        stats: Sender<u8>,
+        sender: Option<Sender<Message>>,
+        stats: Sender<StatsEvent>,
+        recorder: Option<Recorder>,
+        options: &TransportOptions,
     ) -> Result<Self, Error>
     where
         Self: Sized;
+    /// Receive until the source ends or fails. `Ok(())` ends the input for
+    /// good if its transport `ends_at_eof`, and restarts it otherwise; an
+    /// error restarts it after a backoff.
     async fn receive_message(self) -> Result<(), Error>;
+    /// The prefix for its log lines, e.g. `[UDP Input 0.0.0.0:5550] `.
     fn format_name(&self) -> String;
 }
 
+/// A server that sends what the bridge receives on somewhere else.
+///
+/// Like `InputServer`, a program embedding the bridge can implement it for
+/// a type of its own and add it with `Bridge::output_server`.
 #[async_trait]
 pub trait OutputServer {
-    async fn new(host: &str, port: u16) -> Result<Self, Error>
+    /// Connect, bind or open whatever the server writes to.
+    async fn new(host: &str, port: u16, options: &TransportOptions) -> Result<Self, Error>
     where
         Self: Sized;
-    async fn watch_queue(self, receiver: &mut Receiver<String>) -> Result<(), Error>;
+    /// Send each message from `receiver` until it closes, then return
+    /// `Ok(())`; it closes only at shutdown, once everything queued has been
+    /// received. An error restarts the server after a backoff, with the
+    /// same receiver, so a message taken but not sent is lost.
+    async fn watch_queue(self, receiver: &mut Receiver<Message>) -> Result<(), Error>;
+    /// The prefix for its log lines, e.g. `[UDP Output router:5550] `.
     fn format_name(&self) -> String;
 }
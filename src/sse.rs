@@ -0,0 +1,414 @@
+// Copyright (c) 2024-2026 Fred Clausen
+//
+// Licensed under the MIT license: https://opensource.org/licenses/MIT
+// Permission is granted to use, copy, modify, and redistribute the work.
+// Full license information available in the project LICENSE file.
+
+use anyhow::{Context, Error, Result};
+use async_trait::async_trait;
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc::Receiver;
+use tokio_util::sync::CancellationToken;
+
+use crate::config::Config;
+use crate::endpoint::{Endpoint, parse_at_least, parse_setting, unknown_setting};
+use crate::filter::MessageFilter;
+use crate::listener::accept_clients;
+use crate::message::Message;
+use crate::serverconfig::OutputServer;
+use crate::serverconfig::OutputServerOptions;
+use crate::serverconfig::TransportOptions;
+use crate::status::StatusReporter;
+use crate::transport::{Checks, Transport};
+
+/// Largest request head we accept from a client.
+const MAX_REQUEST_BYTES: usize = 8192;
+/// How long a client gets to send its request head.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Comment lines sent on an idle stream so proxies don't time it out.
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+/// Request headers we look at; anything beyond this many is an error.
+const MAX_HEADERS: usize = 64;
+
+/// Settings for the Server-Sent Events output.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SseOptions {
+    /// Recent messages kept for clients that reconnect with
+    /// `Last-Event-ID`.
+    pub history: usize,
+    /// Messages buffered per client before it starts skipping.
+    pub client_buffer: usize,
+}
+
+impl Default for SseOptions {
+    fn default() -> Self {
+        Self {
+            history: 100,
+            client_buffer: 256,
+        }
+    }
+}
+
+/// The next event id, shared by every SSE output in the process. Ids start
+/// at the time the first one is needed, in nanoseconds since the Unix
+/// epoch, so they keep increasing across restarts of the output and of the
+/// bridge, and a client's `Last-Event-ID` from an earlier run never matches
+/// an unrelated event.
+static NEXT_ID: LazyLock<AtomicU64> = LazyLock::new(|| {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos());
+    AtomicU64::new(u64::try_from(now).unwrap_or(u64::MAX / 2).max(1))
+});
+
+/// The bound socket for the SSE output.
+pub struct SseListener(TcpListener);
+
+/// One message and the event id it was sent with.
+type Event = (u64, Message);
+
+/// Ring buffer of the most recent events, shared with every client task.
+struct History {
+    events: Mutex<VecDeque<Event>>,
+    capacity: usize,
+}
+
+impl History {
+    fn push(&self, event: Event) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut events = self
+            .events
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        if events.len() == self.capacity {
+            events.pop_front();
+        }
+        events.push_back(event);
+    }
+
+    /// Events a client resuming after `last_id` missed. An id that has
+    /// already fallen out of the buffer, including one from before a
+    /// restart, replays everything we still hold. Returns `None` for an id
+    /// newer than any we hold, which we never issued, in which case the
+    /// client starts fresh.
+    fn since(&self, last_id: u64) -> Option<Vec<Event>> {
+        let events = self
+            .events
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        let (newest, _) = events.back()?;
+        if last_id > *newest {
+            return None;
+        }
+        Some(
+            events
+                .iter()
+                .filter(|(id, _)| *id > last_id)
+                .cloned()
+                .collect(),
+        )
+    }
+}
+
+/// What the client asked for in its request head.
+struct StreamRequest {
+    filter: MessageFilter,
+    last_event_id: Option<u64>,
+}
+
+/// Read and parse the request head. Returns the HTTP status to reply with
+/// when the request can't be served.
+async fn read_request(stream: &mut TcpStream) -> Result<StreamRequest, &'static str> {
+    let mut buf = Vec::with_capacity(1024);
+    let mut chunk = [0; 1024];
+    loop {
+        let read = tokio::time::timeout(REQUEST_TIMEOUT, stream.read(&mut chunk))
+            .await
+            .map_err(|_| "408 Request Timeout")?
+            .map_err(|_| "400 Bad Request")?;
+        if read == 0 {
+            return Err("400 Bad Request");
+        }
+        buf.extend_from_slice(&chunk[..read]);
+
+        let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+        let mut request = httparse::Request::new(&mut headers);
+        match request.parse(&buf) {
+            Ok(httparse::Status::Complete(_)) => {
+                if request.method != Some("GET") {
+                    return Err("405 Method Not Allowed");
+                }
+                let query = request
+                    .path
+                    .and_then(|path| path.split_once('?'))
+                    .map_or("", |(_, query)| query);
+                let last_event_id = request
+                    .headers
+                    .iter()
+                    .find(|header| header.name.eq_ignore_ascii_case("Last-Event-ID"))
+                    .and_then(|header| std::str::from_utf8(header.value).ok())
+                    .and_then(|value| value.trim().parse().ok());
+                return Ok(StreamRequest {
+                    filter: MessageFilter::from_pairs(url::form_urlencoded::parse(
+                        query.as_bytes(),
+                    )),
+                    last_event_id,
+                });
+            }
+            Ok(httparse::Status::Partial) if buf.len() < MAX_REQUEST_BYTES => {}
+            Ok(httparse::Status::Partial) => return Err("431 Request Header Fields Too Large"),
+            Err(_) => return Err("400 Bad Request"),
+        }
+    }
+}
+
+/// Format one message as an SSE event. Multi-line messages become several
+/// `data:` lines, which the client joins back together with newlines.
+fn format_event(id: u64, message: &str) -> String {
+    let mut event = format!("id: {id}\n");
+    for line in message.trim_end_matches(['\r', '\n']).split('\n') {
+        let _ = writeln!(event, "data: {}", line.trim_end_matches('\r'));
+    }
+    event.push('\n');
+    event
+}
+
+/// Serve one event stream until the client goes away or the broadcast
+/// channel closes.
+async fn serve_client(
+    mut stream: TcpStream,
+    peer: SocketAddr,
+    mut live: broadcast::Receiver<Event>,
+    history: Arc<History>,
+    name: String,
+) {
+    let request = match read_request(&mut stream).await {
+        Ok(request) => request,
+        Err(status) => {
+            debug!("{name}Rejecting request from {peer}: {status}");
+            let _ = stream
+                .write_all(
+                    format!("HTTP/1.1 {status}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                        .as_bytes(),
+                )
+                .await;
+            return;
+        }
+    };
+
+    let head = "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: keep-alive\r\nAccess-Control-Allow-Origin: *\r\n\r\n";
+    if stream.write_all(head.as_bytes()).await.is_err() {
+        return;
+    }
+    info!(
+        "{name}Client {peer} connected, filter: {}, resuming after: {:?}",
+        request.filter, request.last_event_id
+    );
+
+    // `live` was subscribed before this snapshot was taken, so anything
+    // published in between shows up in both. Track the last id sent and
+    // skip live events at or below it.
+    let (mut last_sent, replay) = match request
+        .last_event_id
+        .and_then(|last_id| Some((last_id, history.since(last_id)?)))
+    {
+        Some((last_id, replay)) => (last_id, replay),
+        None => (0, Vec::new()),
+    };
+    if !replay.is_empty() {
+        debug!("{name}Replaying {} event(s) to {peer}", replay.len());
+    }
+
+    let mut keepalive = tokio::time::interval(KEEPALIVE_INTERVAL);
+    keepalive.tick().await;
+    let mut replay = replay.into_iter();
+    loop {
+        let (id, message) = if let Some(event) = replay.next() {
+            event
+        } else {
+            tokio::select! {
+                event = live.recv() => match event {
+                    Ok(event) => event,
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!("{name}Client {peer} is too slow; skipped {skipped} message(s)");
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                },
+                _ = keepalive.tick() => {
+                    if stream.write_all(b": keepalive\n\n").await.is_err() {
+                        break;
+                    }
+                    continue;
+                }
+            }
+        };
+
+        if id <= last_sent {
+            continue;
+        }
+        last_sent = id;
+        if !request.filter.matches(&message) {
+            continue;
+        }
+        if let Err(e) = stream
+            .write_all(format_event(id, &message).as_bytes())
+            .await
+        {
+            debug!("{name}Error sending to {peer}: {e}");
+            break;
+        }
+        keepalive.reset();
+    }
+    info!("{name}Client {peer} disconnected");
+}
+
+#[async_trait]
+impl OutputServer for OutputServerOptions<SseListener> {
+    async fn new(host: &str, port: u16, options: &TransportOptions) -> Result<Self, Error> {
+        let socket = TcpListener::bind((host, port))
+            .await
+            .with_context(|| format!("[SSE Output {host}:{port}] Error binding"))?;
+
+        Ok(Self {
+            host: host.to_string(),
+            port,
+            socket: SseListener(socket),
+            options: options.clone(),
+        })
+    }
+
+    async fn watch_queue(self, receiver: &mut Receiver<Message>) -> Result<(), Error> {
+        let name = self.format_name();
+        info!("{name}Listening for SSE clients");
+
+        let history = Arc::new(History {
+            events: Mutex::new(VecDeque::with_capacity(
+                self.options.get::<SseOptions>().history,
+            )),
+            capacity: self.options.get::<SseOptions>().history,
+        });
+        let (broadcaster, template) =
+            broadcast::channel::<Event>(self.options.get::<SseOptions>().client_buffer);
+        let cancel = CancellationToken::new();
+        let client_name = name.clone();
+        let client_history = history.clone();
+        let mut acceptor = tokio::spawn(accept_clients(
+            self.socket.0,
+            name.clone(),
+            cancel.clone(),
+            move |stream, peer| {
+                serve_client(
+                    stream,
+                    peer,
+                    template.resubscribe(),
+                    client_history.clone(),
+                    client_name.clone(),
+                )
+            },
+        ));
+
+        loop {
+            tokio::select! {
+                biased;
+                result = &mut acceptor => {
+                    return match result {
+                        Ok(Err(e)) => Err(e),
+                        Ok(Ok(())) => Err(Error::msg(format!("{name}Listener stopped unexpectedly"))),
+                        Err(e) => Err(Error::msg(format!("{name}Listener panicked: {e}"))),
+                    };
+                }
+                message = receiver.recv() => {
+                    let Some(message) = message else {
+                        break;
+                    };
+                    debug!("{name}Received: {message}");
+                    let event = (NEXT_ID.fetch_add(1, Ordering::Relaxed), message);
+                    history.push(event.clone());
+                    // Err only means nobody is connected right now.
+                    let _ = broadcaster.send(event);
+                }
+            }
+        }
+
+        // All bridge Senders have been dropped; this happens only during
+        // graceful shutdown. Closing the broadcast channel ends each stream
+        // once the client has been sent what is buffered for it.
+        info!("{name}Input channel closed (shutdown); closing client connections");
+        drop(broadcaster);
+        cancel.cancel();
+        match acceptor.await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => warn!("{e} during shutdown"),
+            Err(e) => warn!("{name}Listener panicked during shutdown: {e}"),
+        }
+        Ok(())
+    }
+
+    fn format_name(&self) -> String {
+        format!("[SSE Output {}:{}] ", self.host, self.port)
+    }
+}
+
+/// HTTP endpoint streaming Server-Sent Events. Listens like `websocket`.
+pub struct SseTransport;
+
+#[async_trait]
+impl Transport for SseTransport {
+    fn name(&self) -> &'static str {
+        "sse"
+    }
+
+    fn supports_output(&self) -> bool {
+        true
+    }
+
+    fn same_options(&self, old: &TransportOptions, new: &TransportOptions) -> bool {
+        old.get::<SseOptions>() == new.get::<SseOptions>()
+    }
+
+    fn options_from_config(&self, config: &Config, options: &mut TransportOptions) -> Result<()> {
+        options.set(SseOptions {
+            history: config.get_sse_history(),
+            client_buffer: config.get_sse_client_buffer(),
+        });
+        Ok(())
+    }
+
+    fn configure(&self, name: &str, value: &str, options: &mut TransportOptions) -> Result<()> {
+        match name {
+            "history" => options.get_mut::<SseOptions>().history = parse_setting(name, value)?,
+            "client-buffer" => {
+                options.get_mut::<SseOptions>().client_buffer = parse_at_least(name, value, 1)?;
+            }
+            _ => return Err(unknown_setting(self.name(), name)),
+        }
+        Ok(())
+    }
+
+    async fn check(&self, endpoint: &Endpoint, _input: bool) -> Checks {
+        crate::check::binds(&endpoint.host, endpoint.port).await
+    }
+
+    async fn run_output(
+        &self,
+        host: &str,
+        port: u16,
+        receiver: &mut Receiver<Message>,
+        options: &TransportOptions,
+        status: &StatusReporter,
+    ) -> Result<()> {
+        crate::transport::serve_output::<SseListener>(host, port, receiver, options, status).await
+    }
+}
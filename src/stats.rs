@@ -4,41 +4,199 @@
 // Permission is granted to use, copy, modify, and redistribute the work.
 // Full license information available in the project LICENSE file.
 
-use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, Mutex, PoisonError};
 use tokio::sync::mpsc::Receiver;
+use tokio::sync::watch;
+
+use crate::status::SupervisorStatus;
+
+/// Number of frames-per-message buckets tracked for ZMQ input: 1, 2, 3 and
+/// 4-or-more frames.
+pub const ZMQ_FRAME_BUCKETS: usize = 4;
+
+/// An event reported by an input server over the stats channel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StatsEvent {
+    /// One message was received.
+    Message,
+    /// One multipart `ZeroMQ` message with `frames` frames was received.
+    /// Counts as a message as well.
+    ZmqMessage { frames: usize },
+}
+
+/// Read-only view of the running totals, for the dashboard.
+#[derive(Clone)]
+pub struct StatsCounters {
+    total_all_time: Arc<AtomicU64>,
+    zmq_frames: Arc<[AtomicU64; ZMQ_FRAME_BUCKETS]>,
+}
+
+impl StatsCounters {
+    /// Messages received since start.
+    #[must_use]
+    pub fn total(&self) -> u64 {
+        self.total_all_time.load(Ordering::Relaxed)
+    }
+
+    /// ZMQ messages received since start with 1, 2, 3 and 4-or-more frames.
+    #[must_use]
+    pub fn zmq_frames(&self) -> [u64; ZMQ_FRAME_BUCKETS] {
+        std::array::from_fn(|bucket| self.zmq_frames[bucket].load(Ordering::Relaxed))
+    }
+}
+
+/// How many messages an output wrote per flush, for outputs that batch their
+/// writes (`tcp`). Kept for each output endpoint across its restarts.
+#[derive(Debug, Default)]
+pub struct BatchCounters {
+    batches: AtomicU64,
+    messages: AtomicU64,
+    largest: AtomicU64,
+}
+
+impl BatchCounters {
+    /// Count one batch of `messages`.
+    pub fn record(&self, messages: usize) {
+        let messages = u64::try_from(messages).unwrap_or(u64::MAX);
+        self.batches.fetch_add(1, Ordering::Relaxed);
+        self.messages.fetch_add(messages, Ordering::Relaxed);
+        self.largest.fetch_max(messages, Ordering::Relaxed);
+    }
+
+    /// Batches flushed since start.
+    #[must_use]
+    pub fn batches(&self) -> u64 {
+        self.batches.load(Ordering::Relaxed)
+    }
+
+    /// Messages written in all of them.
+    #[must_use]
+    pub fn messages(&self) -> u64 {
+        self.messages.load(Ordering::Relaxed)
+    }
+
+    /// Messages in the largest one.
+    #[must_use]
+    pub fn largest(&self) -> u64 {
+        self.largest.load(Ordering::Relaxed)
+    }
+
+    /// For the log, e.g. `120 batch(es), 4.5 message(s) on average, largest
+    /// 64`.
+    #[must_use]
+    pub fn summary(&self) -> String {
+        let batches = self.batches();
+        // In tenths, to show one decimal place.
+        let average = (self.messages() * 10).checked_div(batches).unwrap_or(0);
+        format!(
+            "{batches} batch(es), {}.{} message(s) on average, largest {}",
+            average / 10,
+            average % 10,
+            self.largest()
+        )
+    }
+}
+
+/// An output's name and its batch counters.
+pub type NamedBatchCounters = (String, Arc<BatchCounters>);
+
+static BATCHES: LazyLock<Mutex<Vec<NamedBatchCounters>>> = LazyLock::new(Mutex::default);
+
+/// The batch counters for the output named `name`, e.g. `tcp router:5550`,
+/// made the first time they are asked for.
+#[must_use]
+pub fn batch_counters(name: &str) -> Arc<BatchCounters> {
+    let mut batches = BATCHES.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some((_, counters)) = batches.iter().find(|(known, _)| known == name) {
+        return counters.clone();
+    }
+    let counters = Arc::new(BatchCounters::default());
+    batches.push((name.to_string(), counters.clone()));
+    counters
+}
+
+/// The batch counters of every output that has made them, by name, oldest
+/// first.
+#[must_use]
+pub fn all_batch_counters() -> Vec<NamedBatchCounters> {
+    BATCHES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+/// Called with every event on the stats channel, e.g. to feed an embedding
+/// program's own metrics. Sinks run on the stats task, so they must not
+/// block.
+pub type StatsSink = Box<dyn FnMut(StatsEvent) + Send>;
+
 // A struct to hold the stats
 
 pub struct Stats {
     total_all_time: Arc<AtomicU64>,
     total_since_last: Arc<AtomicU64>,
-    receiver: Receiver<u8>,
+    zmq_frames: Arc<[AtomicU64; ZMQ_FRAME_BUCKETS]>,
+    receiver: Receiver<StatsEvent>,
+    /// Supervisors whose state is logged with the counts, by name.
+    supervisors: Vec<(&'static str, watch::Receiver<SupervisorStatus>)>,
+    sinks: Vec<StatsSink>,
 }
 
 impl Stats {
     #[must_use]
-    pub fn new(receiver: Receiver<u8>) -> Self {
+    pub fn new(receiver: Receiver<StatsEvent>) -> Self {
         Self {
             total_all_time: Arc::new(AtomicU64::new(0)),
             total_since_last: Arc::new(AtomicU64::new(0)),
+            zmq_frames: Arc::new(std::array::from_fn(|_| AtomicU64::new(0))),
             receiver,
+            supervisors: Vec::new(),
+            sinks: Vec::new(),
         }
     }
 
-    pub fn run(mut self, print_interval: u64) {
+    /// Log the state of the supervisor behind `status` as `name` with each
+    /// round of counts.
+    pub fn watch(&mut self, name: &'static str, status: watch::Receiver<SupervisorStatus>) {
+        self.supervisors.push((name, status));
+    }
+
+    /// Hand every event to `sink` as well as counting it.
+    pub fn sink(&mut self, sink: StatsSink) {
+        self.sinks.push(sink);
+    }
+
+    #[must_use]
+    pub fn counters(&self) -> StatsCounters {
+        StatsCounters {
+            total_all_time: self.total_all_time.clone(),
+            zmq_frames: self.zmq_frames.clone(),
+        }
+    }
+
+    /// Count events from here on, and log the counts every `print_interval`
+    /// minutes if set.
+    pub fn run(mut self, print_interval: Option<u64>) {
         // clone the Arcs so we can pass them to the print_stats function
         let total_all_time_context = self.total_all_time.clone();
         let total_since_last_context = self.total_since_last.clone();
+        let zmq_frames_context = self.zmq_frames.clone();
+        let supervisors = std::mem::take(&mut self.supervisors);
 
         trace!("[STATS] Starting stats thread");
-        tokio::spawn(async move {
-            print_stats_to_console(
-                total_all_time_context,
-                total_since_last_context,
-                print_interval,
-            )
-            .await;
-        });
+        if let Some(print_interval) = print_interval {
+            tokio::spawn(async move {
+                print_stats_to_console(
+                    total_all_time_context,
+                    total_since_last_context,
+                    zmq_frames_context,
+                    &supervisors,
+                    print_interval,
+                )
+                .await;
+            });
+        }
 
         tokio::spawn(async move {
             self.watch_message_queue().await;
@@ -46,9 +204,15 @@ impl Stats {
     }
 
     async fn watch_message_queue(&mut self) {
-        while self.receiver.recv().await.is_some() {
-            trace!("[STATS] Received message from queue");
+        while let Some(event) = self.receiver.recv().await {
+            trace!("[STATS] Received {event:?} from queue");
             self.increment();
+            if let StatsEvent::ZmqMessage { frames } = event {
+                self.record_zmq_frames(frames);
+            }
+            for sink in &mut self.sinks {
+                sink(event);
+            }
         }
         // All Senders have been dropped. Under normal operation main retains
         // a master Sender, so this only happens during graceful shutdown
@@ -62,11 +226,20 @@ impl Stats {
         self.total_all_time.fetch_add(1, Ordering::Relaxed);
         self.total_since_last.fetch_add(1, Ordering::Relaxed);
     }
+
+    fn record_zmq_frames(&self, frames: usize) {
+        // Zero-frame messages can't come out of tmq; fold them into the
+        // first bucket rather than indexing out of range.
+        let bucket = frames.clamp(1, ZMQ_FRAME_BUCKETS) - 1;
+        self.zmq_frames[bucket].fetch_add(1, Ordering::Relaxed);
+    }
 }
 
 async fn print_stats_to_console(
     total_all_time_context: Arc<AtomicU64>,
     total_since_last_context: Arc<AtomicU64>,
+    zmq_frames_context: Arc<[AtomicU64; ZMQ_FRAME_BUCKETS]>,
+    supervisors: &[(&'static str, watch::Receiver<SupervisorStatus>)],
     print_interval: u64,
 ) {
     // print interval is in minutes, so we need to convert it to seconds.
@@ -96,5 +269,30 @@ async fn print_stats_to_console(
             if print_interval > 1 { "s" } else { "" },
             total_since_last
         );
+
+        // Only ZMQ inputs report frame counts; stay quiet for TCP/UDP.
+        let zmq_frames: Vec<u64> = zmq_frames_context
+            .iter()
+            .map(|count| count.load(Ordering::Relaxed))
+            .collect();
+        if zmq_frames.iter().any(|&count| count > 0) {
+            info!(
+                "[STATS] ZMQ frames per message since container start: 1: {}, 2: {}, 3: {}, 4+: {}",
+                zmq_frames[0], zmq_frames[1], zmq_frames[2], zmq_frames[3]
+            );
+        }
+
+        for (name, counters) in all_batch_counters() {
+            if counters.batches() > 0 {
+                info!(
+                    "[STATS] Output batches since container start, {name}: {}",
+                    counters.summary()
+                );
+            }
+        }
+
+        for (name, status) in supervisors {
+            info!("[STATS] {name}: {}", status.borrow().summary());
+        }
     }
 }
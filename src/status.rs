@@ -0,0 +1,153 @@
+// Copyright (c) 2024-2026 Fred Clausen
+//
+// Licensed under the MIT license: https://opensource.org/licenses/MIT
+// Permission is granted to use, copy, modify, and redistribute the work.
+// Full license information available in the project LICENSE file.
+
+//! What each supervisor is doing, for the dashboard.
+
+use std::fmt::Write;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::time::Instant;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SupervisorState {
+    /// Building the server: connecting, binding or opening its file.
+    Starting,
+    /// The server is up and handling messages.
+    Running,
+    /// Waiting this long before the next restart.
+    Backoff { delay: Duration },
+    /// The supervisor has exited.
+    Stopped,
+}
+
+impl std::fmt::Display for SupervisorState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Starting => write!(f, "starting"),
+            Self::Running => write!(f, "running"),
+            Self::Backoff { delay } => write!(f, "backoff {delay:.1?}"),
+            Self::Stopped => write!(f, "stopped"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SupervisorStatus {
+    /// Protocol and address, e.g. `udp 0.0.0.0:5550`; empty when this side
+    /// has nothing configured.
+    pub endpoint: String,
+    pub state: SupervisorState,
+    /// When `state` was entered.
+    pub since: Instant,
+    pub restarts: u64,
+    pub last_error: Option<String>,
+}
+
+impl SupervisorStatus {
+    /// One line for the log, e.g. `udp 0.0.0.0:5550 running for 00:05:00,
+    /// 2 restart(s)`.
+    #[must_use]
+    pub fn summary(&self) -> String {
+        if self.endpoint.is_empty() {
+            return "none".to_string();
+        }
+        let mut summary = format!(
+            "{} {} for {}, {} restart(s)",
+            self.endpoint,
+            self.state,
+            format_elapsed(self.since.elapsed()),
+            self.restarts
+        );
+        if let Some(error) = &self.last_error {
+            let _ = write!(summary, ", last error: {error}");
+        }
+        summary
+    }
+}
+
+/// `HH:MM:SS`.
+#[must_use]
+pub fn format_elapsed(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+/// The handle a supervisor reports its state through. Reporting costs next
+/// to nothing when nobody is watching.
+#[derive(Clone, Debug)]
+pub struct StatusReporter {
+    sender: watch::Sender<SupervisorStatus>,
+}
+
+impl Default for StatusReporter {
+    fn default() -> Self {
+        Self {
+            sender: watch::Sender::new(SupervisorStatus {
+                endpoint: String::new(),
+                state: SupervisorState::Starting,
+                since: Instant::now(),
+                restarts: 0,
+                last_error: None,
+            }),
+        }
+    }
+}
+
+impl StatusReporter {
+    #[must_use]
+    pub fn subscribe(&self) -> watch::Receiver<SupervisorStatus> {
+        self.sender.subscribe()
+    }
+
+    /// A supervisor for `endpoint` is taking over, at startup or after a
+    /// reload. The restart count and last error start over with it.
+    pub fn endpoint(&self, endpoint: String) {
+        self.sender.send_modify(|status| {
+            status.endpoint = endpoint;
+            status.state = SupervisorState::Starting;
+            status.since = Instant::now();
+            status.restarts = 0;
+            status.last_error = None;
+        });
+    }
+
+    fn set(&self, state: SupervisorState) {
+        self.sender.send_modify(|status| {
+            status.state = state;
+            status.since = Instant::now();
+        });
+    }
+
+    /// A new server is being built. Every start after the first counts as a
+    /// restart.
+    pub fn starting(&self) {
+        self.sender.send_modify(|status| {
+            if status.state != SupervisorState::Starting {
+                status.restarts += 1;
+            }
+        });
+        self.set(SupervisorState::Starting);
+    }
+
+    pub fn running(&self) {
+        self.set(SupervisorState::Running);
+    }
+
+    /// The server exited, with `error` if it failed, and the supervisor is
+    /// waiting `delay` before restarting it.
+    pub fn backoff(&self, delay: Duration, error: Option<String>) {
+        self.sender.send_modify(|status| {
+            if error.is_some() {
+                status.last_error = error;
+            }
+        });
+        self.set(SupervisorState::Backoff { delay });
+    }
+
+    pub fn stopped(&self) {
+        self.set(SupervisorState::Stopped);
+    }
+}
@@ -0,0 +1,203 @@
+// Copyright (c) 2024-2026 Fred Clausen
+//
+// Licensed under the MIT license: https://opensource.org/licenses/MIT
+// Permission is granted to use, copy, modify, and redistribute the work.
+// Full license information available in the project LICENSE file.
+
+use anyhow::{Error, Result};
+use async_trait::async_trait;
+use tokio::io::{AsyncWriteExt, BufWriter, Stdin, Stdout};
+use tokio::sync::mpsc::{Receiver, Sender};
+use tokio_stream::StreamExt;
+use tokio_util::codec::FramedRead;
+
+use crate::capture::Recorder;
+use crate::message::{Message, MessageCodec};
+use crate::pipeline::InputSinks;
+use crate::serverconfig::InputServer;
+use crate::serverconfig::InputServerOptions;
+use crate::serverconfig::OutputServer;
+use crate::serverconfig::OutputServerOptions;
+use crate::serverconfig::TransportOptions;
+use crate::stats::StatsEvent;
+use crate::status::StatusReporter;
+use crate::transport::Transport;
+
+#[async_trait]
+impl InputServer for InputServerOptions<Stdin> {
+    /// `host` and `port` are unused.
+    async fn new(
+        host: &str,
+        port: u16,
+        sender: Option<Sender<Message>>,
+        stats: Sender<StatsEvent>,
+        recorder: Option<Recorder>,
+        options: &TransportOptions,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            host: host.to_string(),
+            port,
+            socket: tokio::io::stdin(),
+            sender,
+            stats,
+            recorder,
+            options: options.clone(),
+        })
+    }
+
+    /// Forward lines until end of input. Returning `Ok(())` at EOF tells the
+    /// supervisor the input is finished, which starts the graceful drain.
+    async fn receive_message(self) -> Result<(), Error> {
+        let name = self.format_name();
+        let mut lines = FramedRead::new(self.socket, MessageCodec::new());
+
+        while let Some(result) = lines.next().await {
+            let line = match result {
+                Ok(l) => l,
+                Err(e) => return Err(Error::msg(format!("{name}Error reading: {e}"))),
+            };
+            if line.is_empty() {
+                continue;
+            }
+
+            debug!("{name}Received: {line}");
+
+            if let Some(recorder) = &self.recorder {
+                recorder.record("stdin://", &[line.as_bytes()]).await;
+            }
+
+            if let Some(sender) = &self.sender {
+                if let Err(e) = sender.send(line).await {
+                    return Err(Error::msg(format!("{name}Output channel closed: {e}")));
+                }
+                trace!("{name}Message sent to output channel");
+            }
+
+            if let Err(e) = self.stats.send(StatsEvent::Message).await {
+                return Err(Error::msg(format!("{name}Stats channel closed: {e}")));
+            }
+            trace!("{name}Stats sent to channel");
+        }
+
+        info!("{name}End of input");
+        Ok(())
+    }
+
+    fn format_name(&self) -> String {
+        "[stdin Input] ".to_string()
+    }
+}
+
+#[async_trait]
+impl OutputServer for OutputServerOptions<Stdout> {
+    /// `host` and `port` are unused.
+    async fn new(host: &str, port: u16, options: &TransportOptions) -> Result<Self, Error> {
+        Ok(Self {
+            host: host.to_string(),
+            port,
+            socket: tokio::io::stdout(),
+            options: options.clone(),
+        })
+    }
+
+    async fn watch_queue(self, receiver: &mut Receiver<Message>) -> Result<(), Error> {
+        let name = self.format_name();
+        let mut writer = BufWriter::new(self.socket);
+
+        while let Some(message) = receiver.recv().await {
+            debug!("{name}Received: {message}");
+            let mut pending = Some(message);
+
+            // Write out whatever else is already queued, then flush once, so
+            // a pipe reader sees each message promptly without a write per
+            // line on a busy feed.
+            while let Some(message) = pending.take() {
+                let line = message.trim_end_matches(['\r', '\n']);
+                let written = async {
+                    writer.write_all(line.as_bytes()).await?;
+                    writer.write_all(b"\n").await
+                };
+                if let Err(e) = written.await {
+                    return Err(Error::msg(format!("{name}Error writing to stdout: {e}")));
+                }
+                pending = receiver.try_recv().ok();
+            }
+            if let Err(e) = writer.flush().await {
+                return Err(Error::msg(format!("{name}Error writing to stdout: {e}")));
+            }
+            trace!("{name}Messages written to stdout");
+        }
+
+        // All bridge Senders have been dropped; this happens only during
+        // graceful shutdown. Return Ok(()) so the output supervisor treats it
+        // as a terminal, clean exit rather than a failure to restart.
+        info!("{name}Input channel closed (shutdown); exiting");
+        Ok(())
+    }
+
+    fn format_name(&self) -> String {
+        "[stdout Output] ".to_string()
+    }
+}
+
+/// Line-delimited standard input on the input side, standard output on the
+/// output side. Neither host nor port is used, and the input ends at end of
+/// file instead of being restarted.
+pub struct StdioTransport;
+
+#[async_trait]
+impl Transport for StdioTransport {
+    fn name(&self) -> &'static str {
+        "stdin"
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        &["stdout"]
+    }
+
+    fn needs_host(&self) -> bool {
+        false
+    }
+
+    fn needs_port(&self) -> bool {
+        false
+    }
+
+    fn supports_input(&self) -> bool {
+        true
+    }
+
+    fn supports_output(&self) -> bool {
+        true
+    }
+
+    fn ends_at_eof(&self) -> bool {
+        true
+    }
+
+    fn uses_terminal(&self) -> bool {
+        true
+    }
+
+    async fn run_input(
+        &self,
+        host: &str,
+        port: u16,
+        sinks: InputSinks,
+        options: &TransportOptions,
+        status: &StatusReporter,
+    ) -> Result<()> {
+        crate::transport::serve_input::<Stdin>(host, port, sinks, options, status).await
+    }
+
+    async fn run_output(
+        &self,
+        host: &str,
+        port: u16,
+        receiver: &mut Receiver<Message>,
+        options: &TransportOptions,
+        status: &StatusReporter,
+    ) -> Result<()> {
+        crate::transport::serve_output::<Stdout>(host, port, receiver, options, status).await
+    }
+}
@@ -0,0 +1,112 @@
+// Copyright (c) 2024-2026 Fred Clausen
+//
+// Licensed under the MIT license: https://opensource.org/licenses/MIT
+// Permission is granted to use, copy, modify, and redistribute the work.
+// Full license information available in the project LICENSE file.
+
+//! Running a task under supervision: restarting it after a backoff when it
+//! exits, reporting what it is doing, and stopping it when asked.
+//!
+//! A task can be stopped two ways. `stop` aborts whatever it is doing at
+//! once, which suits an input, or an output being replaced. `shutdown` only
+//! keeps it from being started again, so an output in the middle of
+//! draining the bridge channel gets to finish.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use tokio_util::sync::CancellationToken;
+
+use crate::backoff::BackoffPolicy;
+use crate::status::StatusReporter;
+
+/// Work a `Supervisor` can run, again and again.
+#[async_trait]
+pub trait Supervise: Send + 'static {
+    /// Run the work once, until it returns: build the server, report
+    /// `status.running()` once it is up, and serve.
+    async fn run_once(&mut self, status: &StatusReporter) -> Result<()>;
+
+    /// Whether returning `Ok` from `run_once` means the work is done, rather
+    /// than that it should be started again.
+    fn done_on_success(&self) -> bool;
+}
+
+/// How one task is run. See the module docs for `stop` and `shutdown`.
+pub struct Supervisor {
+    /// Names the task in log lines, e.g. `input/0.0.0.0:5550`.
+    pub label: String,
+    pub policy: BackoffPolicy,
+    pub status: StatusReporter,
+    pub stop: CancellationToken,
+    pub shutdown: CancellationToken,
+    /// Cancelled if the task fails more times in a row than the policy
+    /// allows, after which the supervisor exits.
+    pub gave_up: CancellationToken,
+}
+
+impl Supervisor {
+    /// Run `task` until it is done, stopped or given up on, and hand it
+    /// back for anything it still holds.
+    pub async fn run<T: Supervise>(self, mut task: T) -> T {
+        self.supervise(&mut task).await;
+        task
+    }
+
+    async fn supervise<T: Supervise>(&self, task: &mut T) {
+        let label = &self.label;
+        let mut backoff = self.policy.delays();
+        // The first start happens even after a shutdown, so an output whose
+        // input ended at once still drains the bridge channel; the shutdown
+        // only keeps it from being restarted.
+        loop {
+            let started = tokio::time::Instant::now();
+            self.status.starting();
+
+            let result = tokio::select! {
+                biased;
+                () = self.stop.cancelled() => None,
+                r = task.run_once(&self.status) => Some(r),
+            };
+
+            let error = match result {
+                None => {
+                    info!("[SUPERVISOR][{label}] Stopped; exiting");
+                    break;
+                }
+                Some(Ok(())) if task.done_on_success() => {
+                    info!("[SUPERVISOR][{label}] Finished; exiting");
+                    break;
+                }
+                Some(Ok(())) => {
+                    info!("[SUPERVISOR][{label}] Task exited gracefully; restarting");
+                    None
+                }
+                Some(Err(e)) => {
+                    error!("[SUPERVISOR][{label}] Task failed: {e}; restarting");
+                    Some(e.to_string())
+                }
+            };
+
+            let Some(delay) = backoff.after(started.elapsed()) else {
+                error!("[SUPERVISOR][{label}] Out of restart attempts; giving up");
+                self.gave_up.cancel();
+                break;
+            };
+            info!("[SUPERVISOR][{label}] Sleeping {delay:.1?} before restart");
+            self.status.backoff(delay, error);
+            tokio::select! {
+                biased;
+                () = self.shutdown.cancelled() => {
+                    info!("[SUPERVISOR][{label}] Cancelled during backoff; exiting");
+                    break;
+                }
+                () = self.stop.cancelled() => {
+                    info!("[SUPERVISOR][{label}] Stopped during backoff; exiting");
+                    break;
+                }
+                () = tokio::time::sleep(delay) => {}
+            }
+        }
+        self.status.stopped();
+    }
+}
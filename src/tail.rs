@@ -0,0 +1,362 @@
+// Copyright (c) 2024-2026 Fred Clausen
+//
+// Licensed under the MIT license: https://opensource.org/licenses/MIT
+// Permission is granted to use, copy, modify, and redistribute the work.
+// Full license information available in the project LICENSE file.
+
+use anyhow::{Context, Error, Result};
+use async_trait::async_trait;
+use std::io::{ErrorKind, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio::sync::mpsc::Sender;
+
+use crate::capture::Recorder;
+use crate::config::Config;
+use crate::endpoint::{Endpoint, parse_at_least, unknown_setting};
+use crate::message::Message;
+use crate::pipeline::InputSinks;
+use crate::serverconfig::InputServer;
+use crate::serverconfig::InputServerOptions;
+use crate::serverconfig::TransportOptions;
+use crate::stats::StatsEvent;
+use crate::status::StatusReporter;
+use crate::transport::{Checks, Transport};
+
+/// Bytes read from the file at a time.
+const READ_CHUNK: usize = 64 * 1024;
+
+/// Settings for the file-tail input.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TailOptions {
+    /// Where the read offset is kept between runs. Defaults to the followed
+    /// file's path with `.offset` appended.
+    pub state_file: Option<PathBuf>,
+    /// With no saved offset, start at the beginning of the file rather than
+    /// the end.
+    pub from_beginning: bool,
+    /// How often the file is checked for new lines, rotation and truncation.
+    pub poll_interval: Duration,
+}
+
+impl Default for TailOptions {
+    fn default() -> Self {
+        Self {
+            state_file: None,
+            from_beginning: false,
+            poll_interval: Duration::from_millis(250),
+        }
+    }
+}
+
+/// Which file a path currently refers to. A rotated log gets a new inode
+/// even when it keeps its name.
+#[cfg(unix)]
+fn identity(metadata: &std::fs::Metadata) -> (u64, u64) {
+    use std::os::unix::fs::MetadataExt;
+    (metadata.dev(), metadata.ino())
+}
+
+/// Without inodes rotation can't be told apart from truncation, which is
+/// still detected.
+#[cfg(not(unix))]
+const fn identity(_metadata: &std::fs::Metadata) -> (u64, u64) {
+    (0, 0)
+}
+
+/// The file being followed and how far into it we are.
+pub struct TailedFile {
+    path: PathBuf,
+    state_path: PathBuf,
+    file: File,
+    identity: (u64, u64),
+    /// Bytes of the file already forwarded. Always at the start of a line.
+    offset: u64,
+}
+
+/// Parse a state file: `<device> <inode> <offset>`.
+fn parse_state(state: &str) -> Option<((u64, u64), u64)> {
+    let mut fields = state.split_whitespace().map(str::parse::<u64>);
+    let device = fields.next()?.ok()?;
+    let inode = fields.next()?.ok()?;
+    let offset = fields.next()?.ok()?;
+    Some(((device, inode), offset))
+}
+
+impl TailedFile {
+    async fn open(path: &Path, options: &TailOptions) -> Result<Self> {
+        let mut file = File::open(path).await?;
+        let metadata = file.metadata().await?;
+        let identity = identity(&metadata);
+        let state_path = options.state_file.clone().unwrap_or_else(|| {
+            let mut state_path = path.as_os_str().to_owned();
+            state_path.push(".offset");
+            PathBuf::from(state_path)
+        });
+
+        // Resume where the last run stopped if the state is for this same
+        // file and still fits in it. If the file was rotated while we were
+        // down, the new one is read from the start.
+        let saved = tokio::fs::read_to_string(&state_path)
+            .await
+            .ok()
+            .and_then(|state| parse_state(&state));
+        let offset = match saved {
+            Some((saved_identity, offset))
+                if saved_identity == identity && offset <= metadata.len() =>
+            {
+                offset
+            }
+            Some(_) => 0,
+            None if options.from_beginning => 0,
+            None => metadata.len(),
+        };
+        file.seek(SeekFrom::Start(offset)).await?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            state_path,
+            file,
+            identity,
+            offset,
+        })
+    }
+
+    /// Write the offset out, replacing the state file atomically.
+    async fn save(&self) -> std::io::Result<()> {
+        let mut temp = self.state_path.as_os_str().to_owned();
+        temp.push(".tmp");
+        let state = format!("{} {} {}\n", self.identity.0, self.identity.1, self.offset);
+        tokio::fs::write(&temp, state).await?;
+        tokio::fs::rename(&temp, &self.state_path).await
+    }
+}
+
+impl InputServerOptions<TailedFile> {
+    /// Forward one line, without its line ending. Blank lines are skipped.
+    async fn forward(&self, line: &[u8], name: &str, source: &str) -> Result<()> {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if line.is_empty() {
+            return Ok(());
+        }
+
+        if let Some(recorder) = &self.recorder {
+            recorder.record(source, &[line]).await;
+        }
+
+        let line = match std::str::from_utf8(line) {
+            Ok(line) => std::borrow::Cow::Borrowed(line),
+            Err(e) => {
+                warn!(
+                    "{name}Non-UTF-8 line ({} bytes, error at byte {}); using lossy conversion",
+                    line.len(),
+                    e.valid_up_to()
+                );
+                String::from_utf8_lossy(line)
+            }
+        };
+        debug!("{name}Received: {line}");
+
+        if let Some(sender) = &self.sender {
+            if let Err(e) = sender.send(line.as_ref().into()).await {
+                return Err(Error::msg(format!("{name}Output channel closed: {e}")));
+            }
+            trace!("{name}Message sent to output channel");
+        }
+
+        if let Err(e) = self.stats.send(StatsEvent::Message).await {
+            return Err(Error::msg(format!("{name}Stats channel closed: {e}")));
+        }
+        trace!("{name}Stats sent to channel");
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl InputServer for InputServerOptions<TailedFile> {
+    /// `host` is the file to follow; `port` is unused.
+    async fn new(
+        host: &str,
+        port: u16,
+        sender: Option<Sender<Message>>,
+        stats: Sender<StatsEvent>,
+        recorder: Option<Recorder>,
+        options: &TransportOptions,
+    ) -> Result<Self, Error> {
+        let socket = TailedFile::open(Path::new(host), &options.get::<TailOptions>())
+            .await
+            .with_context(|| format!("[Tail Input {host}] Error opening file"))?;
+
+        Ok(Self {
+            host: host.to_string(),
+            port,
+            socket,
+            sender,
+            stats,
+            recorder,
+            options: options.clone(),
+        })
+    }
+
+    async fn receive_message(mut self) -> Result<(), Error> {
+        let name = self.format_name();
+        let source = format!("tail://{}", self.socket.path.display());
+        info!(
+            "{name}Following from offset {}; state in {}",
+            self.socket.offset,
+            self.socket.state_path.display()
+        );
+
+        let mut chunk = vec![0; READ_CHUNK];
+        // Bytes read past `offset` that don't end in a newline yet.
+        let mut partial = Vec::new();
+        let mut saved_offset = self.socket.offset;
+        loop {
+            // Forward every complete line written since the last poll.
+            loop {
+                let read = self.socket.file.read(&mut chunk).await?;
+                if read == 0 {
+                    break;
+                }
+                partial.extend_from_slice(&chunk[..read]);
+                while let Some(end) = partial.iter().position(|&byte| byte == b'\n') {
+                    let line = partial.drain(..=end).collect::<Vec<_>>();
+                    self.socket.offset += line.len() as u64;
+                    self.forward(&line[..line.len() - 1], &name, &source)
+                        .await?;
+                }
+            }
+
+            if self.socket.offset != saved_offset {
+                match self.socket.save().await {
+                    Ok(()) => saved_offset = self.socket.offset,
+                    Err(e) => warn!(
+                        "{name}Error saving offset to {}: {e}",
+                        self.socket.state_path.display()
+                    ),
+                }
+            }
+
+            // The old file has been read to the end, so a rotation can be
+            // followed now without losing anything.
+            match tokio::fs::metadata(&self.socket.path).await {
+                Ok(metadata) if identity(&metadata) != self.socket.identity => {
+                    if !partial.is_empty() {
+                        // The writer moved on without ending its last line.
+                        self.forward(&std::mem::take(&mut partial), &name, &source)
+                            .await?;
+                    }
+                    info!("{name}File was rotated; following the new file");
+                    self.socket.file = File::open(&self.socket.path).await?;
+                    self.socket.identity = identity(&metadata);
+                    self.socket.offset = 0;
+                    saved_offset = u64::MAX;
+                    continue;
+                }
+                Ok(metadata) if metadata.len() < self.socket.offset + partial.len() as u64 => {
+                    warn!("{name}File was truncated; reading from the start");
+                    self.socket.file.seek(SeekFrom::Start(0)).await?;
+                    self.socket.offset = 0;
+                    partial.clear();
+                    saved_offset = u64::MAX;
+                    continue;
+                }
+                Ok(_) => {}
+                // Rotated away and not yet recreated; keep watching the old
+                // file until a new one appears.
+                Err(e) if e.kind() == ErrorKind::NotFound => {}
+                Err(e) => warn!("{name}Error checking file: {e}"),
+            }
+
+            tokio::time::sleep(self.options.get::<TailOptions>().poll_interval).await;
+        }
+    }
+
+    fn format_name(&self) -> String {
+        format!("[Tail Input {}] ", self.host)
+    }
+}
+
+/// Lines appended to a local file, followed like `tail -F`. The host is the
+/// file and the port is unused.
+pub struct TailTransport;
+
+pub(crate) fn tail_from_beginning(config: &Config) -> Result<bool> {
+    match config.get_tail_start().to_lowercase().as_str() {
+        "end" => Ok(false),
+        "beginning" => Ok(true),
+        other => Err(anyhow::anyhow!("Unknown tail start: {other}")),
+    }
+}
+
+#[async_trait]
+impl Transport for TailTransport {
+    fn name(&self) -> &'static str {
+        "tail"
+    }
+
+    fn needs_port(&self) -> bool {
+        false
+    }
+
+    fn supports_input(&self) -> bool {
+        true
+    }
+
+    fn same_options(&self, old: &TransportOptions, new: &TransportOptions) -> bool {
+        old.get::<TailOptions>() == new.get::<TailOptions>()
+    }
+
+    fn options_from_config(&self, config: &Config, options: &mut TransportOptions) -> Result<()> {
+        options.set(TailOptions {
+            state_file: config.get_tail_state_file().as_ref().map(Into::into),
+            from_beginning: tail_from_beginning(config)?,
+            poll_interval: Duration::from_millis(config.get_tail_poll_ms()),
+        });
+        Ok(())
+    }
+
+    fn configure(&self, name: &str, value: &str, options: &mut TransportOptions) -> Result<()> {
+        let tail = options.get_mut::<TailOptions>();
+        match name {
+            "start" => {
+                tail.from_beginning = match value.to_lowercase().as_str() {
+                    "end" => false,
+                    "beginning" => true,
+                    other => return Err(Error::msg(format!("Unknown tail start: {other}"))),
+                };
+            }
+            "state-file" => tail.state_file = Some(PathBuf::from(value)),
+            "poll-ms" => {
+                tail.poll_interval = Duration::from_millis(parse_at_least(name, value, 10)?);
+            }
+            _ => return Err(unknown_setting(self.name(), name)),
+        }
+        Ok(())
+    }
+
+    async fn check(&self, endpoint: &Endpoint, _input: bool) -> Checks {
+        let host = &endpoint.host;
+        let opened = std::fs::File::open(host)
+            .map(drop)
+            .with_context(|| format!("Error opening {host}"));
+        let mut checks = vec![("open", opened)];
+        if let Some(state_file) = &endpoint.options.get::<TailOptions>().state_file {
+            checks.push(("state file", crate::check::writable_file(state_file)));
+        }
+        checks
+    }
+
+    async fn run_input(
+        &self,
+        host: &str,
+        port: u16,
+        sinks: InputSinks,
+        options: &TransportOptions,
+        status: &StatusReporter,
+    ) -> Result<()> {
+        crate::transport::serve_input::<TailedFile>(host, port, sinks, options, status).await
+    }
+}
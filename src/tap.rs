@@ -0,0 +1,267 @@
+// Copyright (c) 2024-2026 Fred Clausen
+//
+// Licensed under the MIT license: https://opensource.org/licenses/MIT
+// Permission is granted to use, copy, modify, and redistribute the work.
+// Full license information available in the project LICENSE file.
+
+//! The `tap` subcommand's output: one summary line per message on the
+//! terminal, in place of a destination.
+
+use anyhow::{Error, Result};
+use async_trait::async_trait;
+use serde_json::Value;
+use std::fmt::Write as _;
+use std::io::IsTerminal;
+use tokio::io::{AsyncWriteExt, BufWriter, Stdout};
+use tokio::sync::mpsc::Receiver;
+
+use crate::config::{Command, Config};
+use crate::filter::MessageFilter;
+use crate::message::{self, Message};
+use crate::serverconfig::OutputServer;
+use crate::serverconfig::OutputServerOptions;
+use crate::serverconfig::TransportOptions;
+use crate::status::StatusReporter;
+use crate::transport::Transport;
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const DIM: &str = "\x1b[2m";
+
+/// Settings for the `tap` subcommand.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TapOptions {
+    /// Messages that don't match aren't printed.
+    pub filter: MessageFilter,
+    /// Color the output with ANSI escapes.
+    pub color: bool,
+    /// Print the full JSON, indented, under each summary line.
+    pub json: bool,
+}
+
+/// Resolve a `--color` setting.
+///
+/// # Errors
+///
+/// Returns an error if `mode` isn't `auto`, `always` or `never`.
+pub fn parse_color(mode: &str) -> Result<bool> {
+    match mode.to_lowercase().as_str() {
+        "auto" => Ok(std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none()),
+        "always" => Ok(true),
+        "never" => Ok(false),
+        other => Err(Error::msg(format!("Unknown color mode: {other}"))),
+    }
+}
+
+/// Where tapped messages are printed.
+pub struct TapPrinter {
+    writer: BufWriter<Stdout>,
+}
+
+const fn type_color(kind: &str) -> &'static str {
+    match kind.as_bytes() {
+        b"acars" => "\x1b[32m",
+        b"vdl2" => "\x1b[36m",
+        b"hfdl" => "\x1b[35m",
+        _ => "\x1b[33m",
+    }
+}
+
+/// Tuned frequency in MHz. `dumpvdl2` and `dumphfdl` give it in Hz inside
+/// their frame object; `acarsdec` and `vdlm2dec` give MHz at the top level.
+fn frequency(message: &Value, kind: &str) -> Option<String> {
+    let mhz = match message.get(kind).and_then(|frame| frame.get("freq")) {
+        Some(hz) => hz.as_f64()? / 1_000_000.0,
+        None => message.get("freq")?.as_f64()?,
+    };
+    Some(format!("{mhz:.3}"))
+}
+
+/// The receiving station's name, if the decoder was given one.
+fn station<'a>(message: &'a Value, kind: &str) -> Option<&'a str> {
+    message
+        .get(kind)
+        .and_then(|frame| frame.get("station"))
+        .or_else(|| message.get("station_id"))
+        .and_then(Value::as_str)
+}
+
+/// A string field of the ACARS layer.
+fn acars_str<'a>(acars: &'a Value, key: &str) -> &'a str {
+    acars.get(key).and_then(Value::as_str).unwrap_or("-")
+}
+
+impl TapOptions {
+    fn paint(&self, out: &mut String, style: &str, text: &str) {
+        if self.color {
+            let _ = write!(out, "{style}{text}{RESET}");
+        } else {
+            out.push_str(text);
+        }
+    }
+
+    /// The lines printed for `raw`, ending in a newline.
+    #[must_use]
+    pub fn format(&self, raw: &str) -> String {
+        self.format_at(raw, chrono::Local::now())
+    }
+
+    /// The lines for `raw` as if it had arrived at `time`.
+    #[must_use]
+    pub fn format_at(&self, raw: &str, time: chrono::DateTime<chrono::Local>) -> String {
+        let mut out = String::new();
+        self.paint(&mut out, DIM, &time.format("%H:%M:%S%.3f").to_string());
+        out.push(' ');
+
+        let Ok(parsed) = serde_json::from_str::<Value>(raw) else {
+            self.paint(&mut out, type_color("raw"), "RAW  ");
+            let _ = writeln!(out, " {}", raw.trim_end());
+            return out;
+        };
+
+        let kind = message::message_type(&parsed);
+        let acars = message::acars(&parsed);
+        let text = acars
+            .get("text")
+            .or_else(|| acars.get("msg_text"))
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .replace(['\r', '\n'], " ");
+
+        self.paint(
+            &mut out,
+            &format!("{BOLD}{}", type_color(kind)),
+            &format!("{:<5}", kind.to_uppercase()),
+        );
+        let fields = format!(
+            " {:<8} {:>8} {:<8} {:<7} {:<2} {}",
+            station(&parsed, kind).unwrap_or("-"),
+            frequency(&parsed, kind).unwrap_or_else(|| "-".to_string()),
+            message::tail(&parsed).unwrap_or("-"),
+            acars_str(acars, "flight"),
+            acars_str(acars, "label"),
+            text.trim()
+        );
+        out.push_str(fields.trim_end());
+        out.push('\n');
+
+        if self.json
+            && let Ok(pretty) = serde_json::to_string_pretty(&parsed)
+        {
+            for line in pretty.lines() {
+                let _ = writeln!(out, "    {line}");
+            }
+        }
+        out
+    }
+}
+
+#[async_trait]
+impl OutputServer for OutputServerOptions<TapPrinter> {
+    /// `host` and `port` are unused.
+    async fn new(host: &str, port: u16, options: &TransportOptions) -> Result<Self, Error> {
+        Ok(Self {
+            host: host.to_string(),
+            port,
+            socket: TapPrinter {
+                writer: BufWriter::new(tokio::io::stdout()),
+            },
+            options: options.clone(),
+        })
+    }
+
+    async fn watch_queue(mut self, receiver: &mut Receiver<Message>) -> Result<(), Error> {
+        let name = self.format_name();
+        let tap = &self.options.get::<TapOptions>();
+        info!("{name}Printing messages, filter: {}", tap.filter);
+
+        while let Some(message) = receiver.recv().await {
+            let mut pending = Some(message);
+
+            // Print whatever else is already queued, then flush once.
+            while let Some(message) = pending.take() {
+                if tap.filter.matches(&message) {
+                    if let Err(e) = self
+                        .socket
+                        .writer
+                        .write_all(tap.format(&message).as_bytes())
+                        .await
+                    {
+                        return Err(Error::msg(format!("{name}Error writing to stdout: {e}")));
+                    }
+                } else {
+                    trace!("{name}Filtered out: {message}");
+                }
+                pending = receiver.try_recv().ok();
+            }
+            if let Err(e) = self.socket.writer.flush().await {
+                return Err(Error::msg(format!("{name}Error writing to stdout: {e}")));
+            }
+        }
+
+        // All bridge Senders have been dropped; this happens only during
+        // graceful shutdown. Return Ok(()) so the output supervisor treats it
+        // as a terminal, clean exit rather than a failure to restart.
+        info!("{name}Input channel closed (shutdown); exiting");
+        Ok(())
+    }
+
+    fn format_name(&self) -> String {
+        "[Tap Output] ".to_string()
+    }
+}
+
+/// Messages printed for reading, from the `tap` subcommand. Not in the
+/// registry, so `--destination-protocol` can't select it.
+pub struct TapTransport;
+
+#[async_trait]
+impl Transport for TapTransport {
+    fn name(&self) -> &'static str {
+        "tap"
+    }
+
+    fn needs_host(&self) -> bool {
+        false
+    }
+
+    fn needs_port(&self) -> bool {
+        false
+    }
+
+    fn supports_output(&self) -> bool {
+        true
+    }
+
+    fn uses_terminal(&self) -> bool {
+        true
+    }
+
+    fn same_options(&self, old: &TransportOptions, new: &TransportOptions) -> bool {
+        old.get::<TapOptions>() == new.get::<TapOptions>()
+    }
+
+    /// Only with the `tap` subcommand, which has the flags.
+    fn options_from_config(&self, config: &Config, options: &mut TransportOptions) -> Result<()> {
+        if let Some(Command::Tap(args)) = config.get_command() {
+            options.set(TapOptions {
+                filter: MessageFilter::parse(&args.filters)
+                    .map_err(|e| anyhow::anyhow!("Error parsing tap filter: {e}"))?,
+                color: parse_color(&args.color)?,
+                json: args.json,
+            });
+        }
+        Ok(())
+    }
+
+    async fn run_output(
+        &self,
+        host: &str,
+        port: u16,
+        receiver: &mut Receiver<Message>,
+        options: &TransportOptions,
+        status: &StatusReporter,
+    ) -> Result<()> {
+        crate::transport::serve_output::<TapPrinter>(host, port, receiver, options, status).await
+    }
+}
@@ -11,6 +11,7 @@ use sdre_stubborn_io::StubbornTcpStream;
 use sdre_stubborn_io::config::DurationIterator;
 use sdre_stubborn_io::tokio::StubbornIo;
 use std::net::SocketAddr;
+use std::num::NonZeroUsize;
 use std::time::Duration;
 use tokio::io::AsyncWriteExt;
 use tokio::io::BufWriter;
@@ -18,13 +19,51 @@ use tokio::net::TcpStream;
 use tokio::net::lookup_host;
 use tokio::sync::mpsc::Receiver;
 use tokio::sync::mpsc::Sender;
+use tokio::sync::mpsc::error::TryRecvError;
+use tokio::time::Instant;
 use tokio_stream::StreamExt;
-use tokio_util::codec::{Framed, LinesCodec};
+use tokio_util::codec::FramedRead;
 
+use crate::backoff::BackoffPolicy;
+use crate::capture::Recorder;
+use crate::config::Config;
+use crate::endpoint::{Endpoint, parse_setting, unknown_setting};
+use crate::message::{Message, MessageCodec};
+use crate::pipeline::InputSinks;
 use crate::serverconfig::InputServer;
 use crate::serverconfig::InputServerOptions;
 use crate::serverconfig::OutputServer;
 use crate::serverconfig::OutputServerOptions;
+use crate::serverconfig::TransportOptions;
+use crate::stats::{self, StatsEvent};
+use crate::status::StatusReporter;
+use crate::transport::{Checks, Transport};
+
+/// Capacity of the TCP output's write buffer, so a batch goes to the socket
+/// in few writes.
+const WRITE_BUFFER: usize = 64 * 1024;
+
+/// `--tcp-batch-size` when not given.
+const DEFAULT_BATCH_SIZE: NonZeroUsize = NonZeroUsize::new(64).expect("64 is not zero");
+
+/// Settings for the TCP output.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TcpOptions {
+    /// Most messages written before they are flushed to the socket.
+    pub batch_size: NonZeroUsize,
+    /// How long a partial batch waits for more messages once the queue is
+    /// empty. Zero flushes as soon as nothing more is queued.
+    pub batch_delay: Duration,
+}
+
+impl Default for TcpOptions {
+    fn default() -> Self {
+        Self {
+            batch_size: DEFAULT_BATCH_SIZE,
+            batch_delay: Duration::ZERO,
+        }
+    }
+}
 
 /// Resolve a `host:port` pair into the first available `SocketAddr`.
 ///
@@ -52,8 +91,10 @@ impl InputServer for InputServerOptions<StubbornIo<TcpStream>> {
     async fn new(
         host: &str,
         port: u16,
-        sender: Option<Sender<String>>,
-        stats: Sender<u8>,
+        sender: Option<Sender<Message>>,
+        stats: Sender<StatsEvent>,
+        recorder: Option<Recorder>,
+        options: &TransportOptions,
     ) -> Result<Self, Error> {
         let addr = resolve_host(host, port)
             .await
@@ -61,7 +102,7 @@ impl InputServer for InputServerOptions<StubbornIo<TcpStream>> {
 
         let stream = StubbornTcpStream::connect_with_options(
             addr,
-            reconnect_options(&format!("{host}:{port}")),
+            reconnect_options(&format!("{host}:{port}"), options.source_backoff.as_ref()),
         )
         .await
         .map_err(|e| Error::msg(format!("[TCP Input {host}:{port}] Error connecting: {e}")))?;
@@ -73,33 +114,38 @@ impl InputServer for InputServerOptions<StubbornIo<TcpStream>> {
             socket: stream,
             sender,
             stats,
+            recorder,
+            options: options.clone(),
         })
     }
 
     async fn receive_message(self) -> Result<(), Error> {
         let name = self.format_name();
-        let reader = tokio::io::BufReader::new(self.socket);
-        let mut lines = Framed::new(reader, LinesCodec::new());
+        // Lines arrive without the newline, so that is what gets recorded.
+        let source = format!("tcp://{}:{}", self.host, self.port);
+        // Each line is a slice of the read buffer, handed on without a copy.
+        let mut lines = FramedRead::new(self.socket, MessageCodec::new());
 
         while let Some(result) = lines.next().await {
             let line = match result {
                 Ok(l) => l,
-                Err(e) => {
-                    warn!("{name}Decode error, skipping line: {e}");
-                    continue;
-                }
+                Err(e) => return Err(Error::msg(format!("{name}Error reading: {e}"))),
             };
 
             debug!("{name}Received: {line}");
 
+            if let Some(recorder) = &self.recorder {
+                recorder.record(&source, &[line.as_bytes()]).await;
+            }
+
             if let Some(sender) = &self.sender {
-                if let Err(e) = sender.send(line.clone()).await {
+                if let Err(e) = sender.send(line).await {
                     return Err(Error::msg(format!("{name}Output channel closed: {e}")));
                 }
                 trace!("{name}Message sent to output channel");
             }
 
-            if let Err(e) = self.stats.send(1).await {
+            if let Err(e) = self.stats.send(StatsEvent::Message).await {
                 return Err(Error::msg(format!("{name}Stats channel closed: {e}")));
             }
             trace!("{name}Stats sent to channel");
@@ -116,14 +162,17 @@ impl InputServer for InputServerOptions<StubbornIo<TcpStream>> {
 
 #[async_trait]
 impl OutputServer for OutputServerOptions<StubbornIo<TcpStream>> {
-    async fn new(host: &str, port: u16) -> Result<Self, Error> {
+    async fn new(host: &str, port: u16, options: &TransportOptions) -> Result<Self, Error> {
         let addr = resolve_host(host, port)
             .await
             .with_context(|| format!("[TCP Output {host}:{port}] DNS resolution failed"))?;
 
         let stream: StubbornIo<TcpStream> = StubbornTcpStream::connect_with_options(
             addr,
-            reconnect_options(&format!("{host}:{port}")),
+            reconnect_options(
+                &format!("{host}:{port}"),
+                options.destination_backoff.as_ref(),
+            ),
         )
         .await
         .map_err(|e| Error::msg(format!("[TCP Output {host}:{port}] Error connecting: {e}")))?;
@@ -133,35 +182,68 @@ impl OutputServer for OutputServerOptions<StubbornIo<TcpStream>> {
             host: host.to_string(),
             port,
             socket: stream,
+            options: options.clone(),
         })
     }
 
-    async fn watch_queue(self, receiver: &mut Receiver<String>) -> Result<(), Error> {
+    async fn watch_queue(self, receiver: &mut Receiver<Message>) -> Result<(), Error> {
         let name = self.format_name();
-        let mut writer: BufWriter<StubbornIo<TcpStream>> = BufWriter::new(self.socket);
+        let TcpOptions {
+            batch_size,
+            batch_delay,
+        } = self.options.get::<TcpOptions>().into_owned();
+        let batches = stats::batch_counters(&format!("tcp {}:{}", self.host, self.port));
+        let mut writer: BufWriter<StubbornIo<TcpStream>> =
+            BufWriter::with_capacity(WRITE_BUFFER, self.socket);
         while let Some(line) = receiver.recv().await {
-            debug!("{name}Received: {line}");
-
-            // verify we have a newline
-            let line = if line.ends_with('\n') {
-                line
-            } else {
-                format!("{line}\n")
-            };
+            // Write whatever else is queued, and whatever arrives within
+            // `batch_delay`, up to `batch_size`, then flush them together
+            // rather than one write to the socket per message.
+            let deadline = Instant::now() + batch_delay;
+            let mut batch = 0;
+            let mut pending = Some(line);
+            while let Some(line) = pending.take() {
+                debug!("{name}Received: {line}");
 
-            if let Err(e) = writer.write_all(line.as_bytes()).await {
-                return Err(Error::msg(format!(
-                    "{name}Error sending message to consumer: {e}"
-                )));
+                // End the line unless it already is, without copying it to
+                // append the newline.
+                let written = async {
+                    writer.write_all(line.as_bytes()).await?;
+                    writer.write_all(line.line_ending()).await
+                };
+                if let Err(e) = written.await {
+                    // The writer, and what it buffered of this batch, goes
+                    // with the connection.
+                    return Err(Error::msg(format!(
+                        "{name}Error sending message to consumer: {e}; dropped up to {} \
+                         message(s) of this batch",
+                        batch + 1
+                    )));
+                }
+                batch += 1;
+                if batch == batch_size.get() {
+                    break;
+                }
+                pending = match receiver.try_recv() {
+                    Ok(line) => Some(line),
+                    Err(TryRecvError::Empty) if !batch_delay.is_zero() => {
+                        tokio::time::timeout_at(deadline, receiver.recv())
+                            .await
+                            .ok()
+                            .flatten()
+                    }
+                    Err(_) => None,
+                };
             }
-            debug!("{name}Message sent to consumer");
 
             if let Err(e) = writer.flush().await {
                 return Err(Error::msg(format!(
-                    "{name}Error flushing message to consumer: {e}"
+                    "{name}Error flushing message to consumer: {e}; dropped up to {batch} \
+                     message(s) of this batch"
                 )));
             }
-            trace!("{name}Flushed message to consumer");
+            batches.record(batch);
+            trace!("{name}Flushed {batch} message(s) to consumer");
         }
 
         // recv() returning None means all bridge Senders have been dropped,
@@ -178,41 +260,100 @@ impl OutputServer for OutputServerOptions<StubbornIo<TcpStream>> {
     }
 }
 
-pub fn reconnect_options(host: &str) -> ReconnectOptions {
-    // `with_exit_if_first_connect_fails(false)` is the default in 0.7 and has
-    // been dropped from this builder chain accordingly.
-    ReconnectOptions::new()
-        .with_retries_generator(get_our_standard_reconnect_strategy)
-        .with_connection_name(host)
+/// Connect, and reconnect once a connection is lost, with the waits
+/// `policy` gives, or `standard_reconnect_delays` without one.
+///
+/// `with_exit_if_first_connect_fails(false)` is the default in 0.7: a first
+/// connect that fails is retried on the same schedule rather than returned
+/// to the supervisor.
+#[must_use]
+pub fn reconnect_options(host: &str, policy: Option<&BackoffPolicy>) -> ReconnectOptions {
+    let options = ReconnectOptions::new().with_connection_name(host);
+    match policy.cloned() {
+        Some(policy) => options.with_retries_generator(move || policy.delays()),
+        None => options.with_retries_generator(standard_reconnect_delays),
+    }
 }
 
-fn get_our_standard_reconnect_strategy() -> DurationIterator {
-    let initial_attempts = vec![
-        Duration::from_secs(5),
-        Duration::from_secs(5),
-        Duration::from_secs(5),
-        Duration::from_secs(5),
-        Duration::from_secs(5),
-        Duration::from_secs(5),
-        Duration::from_secs(5),
-        Duration::from_secs(5),
-        Duration::from_secs(5),
-        Duration::from_secs(5),
-        Duration::from_secs(5),
-        Duration::from_secs(5),
-        Duration::from_secs(5),
-        Duration::from_secs(5),
-        Duration::from_secs(10),
-        Duration::from_secs(20),
-        Duration::from_secs(30),
-        Duration::from_secs(40),
-        Duration::from_secs(50),
-        Duration::from_mins(1),
-    ];
-
-    let repeat = std::iter::repeat(Duration::from_mins(1));
-
-    let forever_iterator = initial_attempts.into_iter().chain(repeat);
-
-    Box::new(forever_iterator)
+/// The waits between TCP reconnects when no backoff is configured: every 5
+/// seconds for the first 14 attempts, then 10, 20, 30, 40 and 50 seconds,
+/// then once a minute for good.
+#[must_use]
+pub fn standard_reconnect_delays() -> DurationIterator {
+    let initial_attempts = std::iter::repeat_n(Duration::from_secs(5), 14)
+        .chain([10, 20, 30, 40, 50].map(Duration::from_secs))
+        .chain(std::iter::repeat(Duration::from_mins(1)));
+    Box::new(initial_attempts)
+}
+
+pub struct TcpTransport;
+
+#[async_trait]
+impl Transport for TcpTransport {
+    fn name(&self) -> &'static str {
+        "tcp"
+    }
+
+    fn supports_input(&self) -> bool {
+        true
+    }
+
+    fn supports_output(&self) -> bool {
+        true
+    }
+
+    fn same_options(&self, old: &TransportOptions, new: &TransportOptions) -> bool {
+        old.get::<TcpOptions>() == new.get::<TcpOptions>()
+    }
+
+    fn options_from_config(&self, config: &Config, options: &mut TransportOptions) -> Result<()> {
+        options.set(TcpOptions {
+            batch_size: config.get_tcp_batch_size(),
+            batch_delay: Duration::from_millis(config.get_tcp_batch_delay_ms()),
+        });
+        Ok(())
+    }
+
+    /// The settings only affect an output.
+    fn configure(&self, name: &str, value: &str, options: &mut TransportOptions) -> Result<()> {
+        let tcp = options.get_mut::<TcpOptions>();
+        match name {
+            "batch-size" => tcp.batch_size = parse_setting(name, value)?,
+            "batch-delay-ms" => {
+                tcp.batch_delay = Duration::from_millis(parse_setting(name, value)?);
+            }
+            _ => return Err(unknown_setting(self.name(), name)),
+        }
+        Ok(())
+    }
+
+    async fn check(&self, endpoint: &Endpoint, _input: bool) -> Checks {
+        crate::check::resolves(&endpoint.host, endpoint.port).await
+    }
+
+    async fn run_input(
+        &self,
+        host: &str,
+        port: u16,
+        sinks: InputSinks,
+        options: &TransportOptions,
+        status: &StatusReporter,
+    ) -> Result<()> {
+        crate::transport::serve_input::<StubbornIo<TcpStream>>(host, port, sinks, options, status)
+            .await
+    }
+
+    async fn run_output(
+        &self,
+        host: &str,
+        port: u16,
+        receiver: &mut Receiver<Message>,
+        options: &TransportOptions,
+        status: &StatusReporter,
+    ) -> Result<()> {
+        crate::transport::serve_output::<StubbornIo<TcpStream>>(
+            host, port, receiver, options, status,
+        )
+        .await
+    }
 }
@@ -0,0 +1,389 @@
+// Copyright (c) 2024-2026 Fred Clausen
+//
+// Licensed under the MIT license: https://opensource.org/licenses/MIT
+// Permission is granted to use, copy, modify, and redistribute the work.
+// Full license information available in the project LICENSE file.
+
+//! The protocols the bridge can run, looked up by name.
+//!
+//! Each protocol's module implements `Transport` for a unit struct: what it
+//! needs from an endpoint, the settings it keeps in `TransportOptions` and
+//! how the flags and an endpoint URL set them, what `check` verifies before
+//! a start, and how to run it on either side. Nothing else dispatches on the
+//! protocol. The built-in protocols are listed in `Registry::builtin`; a
+//! program embedding the bridge adds its own with `register`, or runs a
+//! server of its own through `InputServerTransport` and
+//! `OutputServerTransport` without a name.
+
+use anyhow::{Error, Result};
+use async_trait::async_trait;
+use std::marker::PhantomData;
+use std::sync::{LazyLock, PoisonError, RwLock, RwLockReadGuard};
+use tokio::sync::mpsc::Receiver;
+
+use crate::config::Config;
+use crate::endpoint::Endpoint;
+use crate::message::Message;
+use crate::pipeline::InputSinks;
+use crate::serverconfig::{
+    InputServer, InputServerOptions, OutputServer, OutputServerOptions, TransportOptions,
+};
+use crate::status::StatusReporter;
+
+/// The outcome of each check a transport ran on an endpoint, by what was
+/// checked, e.g. `resolve`.
+pub type Checks = Vec<(&'static str, Result<()>)>;
+
+#[async_trait]
+pub trait Transport: Send + Sync {
+    /// The protocol name that selects this transport.
+    fn name(&self) -> &'static str;
+
+    /// Other names that select it, e.g. `stdout` for `stdin`.
+    fn aliases(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    fn needs_host(&self) -> bool {
+        true
+    }
+
+    fn needs_port(&self) -> bool {
+        true
+    }
+
+    fn supports_input(&self) -> bool {
+        false
+    }
+
+    fn supports_output(&self) -> bool {
+        false
+    }
+
+    /// Whether the input finishes for good when its server returns `Ok(())`.
+    /// The input supervisor stops instead of restarting it, and main starts
+    /// the graceful drain as it would for a signal.
+    fn ends_at_eof(&self) -> bool {
+        false
+    }
+
+    /// Whether it reads or writes the terminal, which the dashboard needs
+    /// for itself.
+    fn uses_terminal(&self) -> bool {
+        false
+    }
+
+    /// Whether a server would run the same with `new` as with `old`,
+    /// comparing only the sections of the options it reads. A reload
+    /// restarts a supervisor only when this is false or its endpoint
+    /// changed.
+    fn same_options(&self, _old: &TransportOptions, _new: &TransportOptions) -> bool {
+        true
+    }
+
+    /// Set this transport's settings in `options` from the command line
+    /// flags, for every endpoint to start from. A transport keeps its
+    /// settings in a type of its own, which its servers read back with
+    /// `TransportOptions::get`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the first flag that doesn't parse.
+    fn options_from_config(&self, _config: &Config, _options: &mut TransportOptions) -> Result<()> {
+        Ok(())
+    }
+
+    /// Apply a setting given with an endpoint URL, from its query or as a
+    /// `+name` on its scheme (with the value `true`), to that endpoint's
+    /// `options`. The URL's path comes as `path`, and its user and password
+    /// as `username` and `password`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for a setting this transport doesn't take or a value
+    /// that doesn't parse.
+    fn configure(&self, name: &str, _value: &str, _options: &mut TransportOptions) -> Result<()> {
+        Err(crate::endpoint::unknown_setting(self.name(), name))
+    }
+
+    /// What `check` verifies about an endpoint before a start: that its host
+    /// resolves, its port binds, its file opens. Stops at the first failure
+    /// that makes the rest pointless.
+    async fn check(&self, _endpoint: &Endpoint, _input: bool) -> Checks {
+        Vec::new()
+    }
+
+    /// Build the input server and run it until it returns.
+    async fn run_input(
+        &self,
+        host: &str,
+        port: u16,
+        _sinks: InputSinks,
+        _options: &TransportOptions,
+        _status: &StatusReporter,
+    ) -> Result<()> {
+        Err(Error::msg(format!(
+            "input/{host}:{port} {} cannot be used as an input",
+            self.name()
+        )))
+    }
+
+    /// Build the output server and run it until it returns.
+    async fn run_output(
+        &self,
+        host: &str,
+        port: u16,
+        _receiver: &mut Receiver<Message>,
+        _options: &TransportOptions,
+        _status: &StatusReporter,
+    ) -> Result<()> {
+        Err(Error::msg(format!(
+            "output/{host}:{port} {} cannot be used as an output",
+            self.name()
+        )))
+    }
+}
+
+impl PartialEq for dyn Transport {
+    fn eq(&self, other: &Self) -> bool {
+        self.name() == other.name()
+    }
+}
+
+impl std::fmt::Debug for dyn Transport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The transports `--source-protocol` and `--destination-protocol` can
+/// select. `generate` and `tap` aren't among them; only their subcommands
+/// start them.
+pub struct Registry {
+    transports: Vec<&'static dyn Transport>,
+}
+
+impl Registry {
+    /// Every protocol the bridge ships with.
+    #[must_use]
+    pub fn builtin() -> Self {
+        let mut registry = Self {
+            transports: Vec::new(),
+        };
+        registry.register(&crate::tcp::TcpTransport);
+        registry.register(&crate::udp::UdpTransport);
+        registry.register(&crate::zmq::ZmqTransport);
+        registry.register(&crate::zmq::ZmqPipelineTransport);
+        registry.register(&crate::http::HttpTransport);
+        registry.register(&crate::mqtt::MqttTransport);
+        registry.register(&crate::websocket::WebSocketTransport);
+        registry.register(&crate::sse::SseTransport);
+        registry.register(&crate::file::FileTransport);
+        registry.register(&crate::replay::ReplayTransport);
+        registry.register(&crate::tail::TailTransport);
+        registry.register(&crate::stdio::StdioTransport);
+        registry.register(&crate::memory::MemoryTransport);
+        registry
+    }
+
+    /// Make `transport` selectable by its name and aliases, in place of any
+    /// transport registered under the same name before.
+    pub fn register(&mut self, transport: &'static dyn Transport) {
+        self.transports
+            .retain(|registered| registered.name() != transport.name());
+        self.transports.push(transport);
+    }
+
+    /// Every registered transport, in the order they were registered.
+    pub fn transports(&self) -> impl Iterator<Item = &'static dyn Transport> + '_ {
+        self.transports.iter().copied()
+    }
+
+    /// The transport `protocol` names, ignoring case.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error listing the known names if none matches.
+    pub fn find(&self, protocol: &str) -> Result<&'static dyn Transport> {
+        let protocol = protocol.to_lowercase();
+        self.transports
+            .iter()
+            .find(|transport| {
+                transport.name() == protocol || transport.aliases().contains(&protocol.as_str())
+            })
+            .copied()
+            .ok_or_else(|| {
+                let known: Vec<&str> = self
+                    .transports
+                    .iter()
+                    .flat_map(|transport| {
+                        std::iter::once(transport.name()).chain(transport.aliases().iter().copied())
+                    })
+                    .collect();
+                Error::msg(format!(
+                    "Unknown protocol {protocol}; expected one of {}",
+                    known.join(", ")
+                ))
+            })
+    }
+}
+
+static REGISTRY: LazyLock<RwLock<Registry>> = LazyLock::new(|| RwLock::new(Registry::builtin()));
+
+/// The registry the bridge looks protocols up in.
+pub fn registry() -> RwLockReadGuard<'static, Registry> {
+    REGISTRY.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Make `transport` selectable in endpoint URLs and protocol flags, in place
+/// of any transport with the same name.
+///
+/// Endpoints are looked up when they are parsed, so register before
+/// `Bridge::start`, or, in the binary, before the configuration is read; the
+/// flags of every registered transport are read then too.
+pub fn register(transport: &'static dyn Transport) {
+    REGISTRY
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .register(transport);
+}
+
+/// Build an input server of type `T` and run it until it returns. Most
+/// transports' `run_input` is this for their server type.
+///
+/// # Errors
+///
+/// Returns the error the server failed to start or run with.
+pub async fn serve_input<T>(
+    host: &str,
+    port: u16,
+    sinks: InputSinks,
+    options: &TransportOptions,
+    status: &StatusReporter,
+) -> Result<()>
+where
+    InputServerOptions<T>: InputServer + Send,
+{
+    run_input_server::<InputServerOptions<T>>(host, port, sinks, options, status).await
+}
+
+/// Build the input server `S`, of any type, and run it until it returns.
+///
+/// # Errors
+///
+/// Returns the error the server failed to start or run with.
+pub async fn run_input_server<S: InputServer + Send>(
+    host: &str,
+    port: u16,
+    sinks: InputSinks,
+    options: &TransportOptions,
+    status: &StatusReporter,
+) -> Result<()> {
+    let InputSinks {
+        output,
+        stats,
+        recorder,
+    } = sinks;
+    let server = S::new(host, port, output, stats, recorder, options).await?;
+    status.running();
+    server.receive_message().await
+}
+
+/// Build an output server of type `T` and run it until it returns.
+///
+/// # Errors
+///
+/// Returns the error the server failed to start or run with.
+pub async fn serve_output<T>(
+    host: &str,
+    port: u16,
+    receiver: &mut Receiver<Message>,
+    options: &TransportOptions,
+    status: &StatusReporter,
+) -> Result<()>
+where
+    OutputServerOptions<T>: OutputServer + Send,
+{
+    run_output_server::<OutputServerOptions<T>>(host, port, receiver, options, status).await
+}
+
+/// Build the output server `S`, of any type, and run it until it returns.
+///
+/// # Errors
+///
+/// Returns the error the server failed to start or run with.
+pub async fn run_output_server<S: OutputServer + Send>(
+    host: &str,
+    port: u16,
+    receiver: &mut Receiver<Message>,
+    options: &TransportOptions,
+    status: &StatusReporter,
+) -> Result<()> {
+    let server = S::new(host, port, options).await?;
+    status.running();
+    server.watch_queue(receiver).await
+}
+
+/// Runs an `InputServer` defined outside the bridge as an input, for
+/// `Bridge::input_server`. It is named after the server's type.
+pub struct InputServerTransport<S>(PhantomData<fn() -> S>);
+
+impl<S> Default for InputServerTransport<S> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+#[async_trait]
+impl<S: InputServer + Send + 'static> Transport for InputServerTransport<S> {
+    fn name(&self) -> &'static str {
+        std::any::type_name::<S>()
+    }
+
+    fn supports_input(&self) -> bool {
+        true
+    }
+
+    async fn run_input(
+        &self,
+        host: &str,
+        port: u16,
+        sinks: InputSinks,
+        options: &TransportOptions,
+        status: &StatusReporter,
+    ) -> Result<()> {
+        run_input_server::<S>(host, port, sinks, options, status).await
+    }
+}
+
+/// Runs an `OutputServer` defined outside the bridge as an output, for
+/// `Bridge::output_server`.
+pub struct OutputServerTransport<S>(PhantomData<fn() -> S>);
+
+impl<S> Default for OutputServerTransport<S> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+#[async_trait]
+impl<S: OutputServer + Send + 'static> Transport for OutputServerTransport<S> {
+    fn name(&self) -> &'static str {
+        std::any::type_name::<S>()
+    }
+
+    fn supports_output(&self) -> bool {
+        true
+    }
+
+    async fn run_output(
+        &self,
+        host: &str,
+        port: u16,
+        receiver: &mut Receiver<Message>,
+        options: &TransportOptions,
+        status: &StatusReporter,
+    ) -> Result<()> {
+        run_output_server::<S>(host, port, receiver, options, status).await
+    }
+}
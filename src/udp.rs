@@ -10,26 +10,38 @@ use std::net::SocketAddr;
 use tokio::net::{ToSocketAddrs, UdpSocket};
 use tokio::sync::mpsc::{Receiver, Sender};
 
+use crate::capture::Recorder;
+use crate::endpoint::{Endpoint, host_port};
+use crate::message::Message;
+use crate::pipeline::InputSinks;
 use crate::serverconfig::InputServer;
 use crate::serverconfig::InputServerOptions;
 use crate::serverconfig::OutputServer;
 use crate::serverconfig::OutputServerOptions;
+use crate::serverconfig::TransportOptions;
+use crate::stats::StatsEvent;
+use crate::status::StatusReporter;
+use crate::transport::{Checks, Transport};
 
 #[async_trait]
 impl InputServer for InputServerOptions<UdpSocket> {
     async fn new(
         host: &str,
         port: u16,
-        sender: Option<Sender<String>>,
-        stats: Sender<u8>,
+        sender: Option<Sender<Message>>,
+        stats: Sender<StatsEvent>,
+        recorder: Option<Recorder>,
+        options: &TransportOptions,
     ) -> Result<Self, Error> {
-        let socket = UdpSocket::bind(format!("{host}:{port}")).await?;
+        let socket = UdpSocket::bind(host_port(host, port)).await?;
         Ok(Self {
             host: host.to_string(),
             port,
             socket,
             sender,
             stats,
+            recorder,
+            options: options.clone(),
         })
     }
 
@@ -37,12 +49,18 @@ impl InputServer for InputServerOptions<UdpSocket> {
         let mut buf = [0; 8192];
         loop {
             match self.socket.recv_from(&mut buf).await {
-                Ok((size, _)) => {
+                Ok((size, peer)) => {
                     if size == 0 {
                         warn!("{}Received empty message", self.format_name());
                         continue;
                     }
 
+                    if let Some(recorder) = &self.recorder {
+                        recorder
+                            .record(&format!("udp://{peer}"), &[&buf[..size]])
+                            .await;
+                    }
+
                     // ACARS payloads are 7-bit ASCII in practice, but warn
                     // loudly if non-UTF-8 bytes arrive so silent corruption
                     // is visible. Fall back to lossy conversion so the
@@ -64,7 +82,7 @@ impl InputServer for InputServerOptions<UdpSocket> {
                     debug!("{}Received: {}", self.format_name(), composed_message);
 
                     if let Some(sender) = &self.sender {
-                        if let Err(e) = sender.send(composed_message.to_string()).await {
+                        if let Err(e) = sender.send(composed_message.as_ref().into()).await {
                             return Err(Error::msg(format!(
                                 "{}Output channel closed: {}",
                                 self.format_name(),
@@ -74,7 +92,7 @@ impl InputServer for InputServerOptions<UdpSocket> {
                         trace!("{}Message sent to sender channel", self.format_name());
                     }
 
-                    if let Err(e) = self.stats.send(1).await {
+                    if let Err(e) = self.stats.send(StatsEvent::Message).await {
                         return Err(Error::msg(format!(
                             "{}Stats channel closed: {}",
                             self.format_name(),
@@ -102,7 +120,7 @@ impl InputServer for InputServerOptions<UdpSocket> {
 
 #[async_trait]
 impl OutputServer for OutputServerOptions<UdpSocket> {
-    async fn new(host: &str, port: u16) -> Result<Self, Error> {
+    async fn new(host: &str, port: u16, options: &TransportOptions) -> Result<Self, Error> {
         let socket = UdpSocket::bind("0.0.0.0:0").await?;
         // Validate that the destination resolves at construction time so we
         // fail fast (and let the supervisor back off + retry) rather than
@@ -118,10 +136,11 @@ impl OutputServer for OutputServerOptions<UdpSocket> {
             host: host.to_string(),
             port,
             socket,
+            options: options.clone(),
         })
     }
 
-    async fn watch_queue(self, receiver: &mut Receiver<String>) -> Result<(), Error> {
+    async fn watch_queue(self, receiver: &mut Receiver<Message>) -> Result<(), Error> {
         // Resolve the destination once. The previous implementation passed a
         // host:port string to send_to on every datagram, which forced DNS
         // resolution per call. Resolving once at task start eliminates that
@@ -139,6 +158,7 @@ impl OutputServer for OutputServerOptions<UdpSocket> {
             })?;
         debug!("{}Resolved destination to {}", self.format_name(), dest);
 
+        let mut datagram = Vec::new();
         loop {
             let Some(message) = receiver.recv().await else {
                 // All bridge Senders have been dropped; this happens only
@@ -152,11 +172,16 @@ impl OutputServer for OutputServerOptions<UdpSocket> {
             };
             debug!("{}Received: {}", self.format_name(), message);
 
-            // verify we have a newline
-            let message = if message.ends_with('\n') {
-                message
+            // A datagram has to be one buffer, so a message without its
+            // newline is copied into one reused for every send rather than
+            // into a new string each time.
+            let bytes = if message.line_ending().is_empty() {
+                message.as_bytes()
             } else {
-                format!("{message}\n")
+                datagram.clear();
+                datagram.extend_from_slice(message.as_bytes());
+                datagram.extend_from_slice(message.line_ending());
+                &datagram
             };
 
             // Send the entire message as a single UDP datagram. The
@@ -171,7 +196,6 @@ impl OutputServer for OutputServerOptions<UdpSocket> {
             // The hard ceiling here is the UDP payload max
             // (~65507 bytes); messages larger than that produce
             // EMSGSIZE, which we log and drop.
-            let bytes = message.as_bytes();
             match self.socket.send_to(bytes, &dest).await {
                 Ok(n) if n < bytes.len() => {
                     // Per POSIX, a UDP send_to either transmits the
@@ -215,3 +239,57 @@ async fn resolve_first<A: ToSocketAddrs>(addr: A) -> Result<SocketAddr, Error> {
         .next()
         .ok_or_else(|| Error::msg("DNS resolution returned no addresses"))
 }
+
+pub struct UdpTransport;
+
+#[async_trait]
+impl Transport for UdpTransport {
+    fn name(&self) -> &'static str {
+        "udp"
+    }
+
+    fn supports_input(&self) -> bool {
+        true
+    }
+
+    fn supports_output(&self) -> bool {
+        true
+    }
+
+    /// An input must also be able to bind its address; an output only sends
+    /// to it.
+    async fn check(&self, endpoint: &Endpoint, input: bool) -> Checks {
+        let (host, port) = (endpoint.host.as_str(), endpoint.port);
+        let mut checks = crate::check::resolves(host, port).await;
+        if input && checks.iter().all(|(_, result)| result.is_ok()) {
+            let bound = UdpSocket::bind(host_port(host, port))
+                .await
+                .map(drop)
+                .map_err(|e| Error::msg(format!("Error binding: {e}")));
+            checks.push(("bind", bound));
+        }
+        checks
+    }
+
+    async fn run_input(
+        &self,
+        host: &str,
+        port: u16,
+        sinks: InputSinks,
+        options: &TransportOptions,
+        status: &StatusReporter,
+    ) -> Result<()> {
+        crate::transport::serve_input::<UdpSocket>(host, port, sinks, options, status).await
+    }
+
+    async fn run_output(
+        &self,
+        host: &str,
+        port: u16,
+        receiver: &mut Receiver<Message>,
+        options: &TransportOptions,
+        status: &StatusReporter,
+    ) -> Result<()> {
+        crate::transport::serve_output::<UdpSocket>(host, port, receiver, options, status).await
+    }
+}
@@ -0,0 +1,255 @@
+// Copyright (c) 2024-2026 Fred Clausen
+//
+// Licensed under the MIT license: https://opensource.org/licenses/MIT
+// Permission is granted to use, copy, modify, and redistribute the work.
+// Full license information available in the project LICENSE file.
+
+use anyhow::{Context, Error, Result};
+use async_trait::async_trait;
+use futures::{SinkExt, StreamExt};
+use std::net::SocketAddr;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc::Receiver;
+use tokio_tungstenite::tungstenite::handshake::server::{
+    Callback, ErrorResponse, Request, Response,
+};
+use tokio_tungstenite::tungstenite::{Message as Frame, Utf8Bytes};
+use tokio_util::sync::CancellationToken;
+
+use crate::config::Config;
+use crate::endpoint::{Endpoint, parse_at_least, unknown_setting};
+use crate::filter::MessageFilter;
+use crate::listener::accept_clients;
+use crate::message::Message;
+use crate::serverconfig::OutputServer;
+use crate::serverconfig::OutputServerOptions;
+use crate::serverconfig::TransportOptions;
+use crate::status::StatusReporter;
+use crate::transport::{Checks, Transport};
+
+/// Settings for the WebSocket server output.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WebSocketOptions {
+    /// Messages buffered per client. A client that falls further behind
+    /// than this skips the oldest messages instead of slowing everyone
+    /// else down.
+    pub client_buffer: usize,
+}
+
+impl Default for WebSocketOptions {
+    fn default() -> Self {
+        Self { client_buffer: 256 }
+    }
+}
+
+/// The bound socket for the WebSocket output.
+pub struct WebSocketListener(TcpListener);
+
+/// Parse the filter from the query string of a client's connect URL, e.g.
+/// `ws://bridge:8080/?type=vdl2&tail=N123AB`.
+fn filter_from_request(request: &Request) -> MessageFilter {
+    request
+        .uri()
+        .query()
+        .map(|query| MessageFilter::from_pairs(url::form_urlencoded::parse(query.as_bytes())))
+        .unwrap_or_default()
+}
+
+/// Handshake callback that records the client's filter.
+struct CaptureFilter<'a>(&'a mut MessageFilter);
+
+impl Callback for CaptureFilter<'_> {
+    fn on_request(self, request: &Request, response: Response) -> Result<Response, ErrorResponse> {
+        *self.0 = filter_from_request(request);
+        Ok(response)
+    }
+}
+
+/// A text frame sharing the message's bytes.
+#[allow(unsafe_code)]
+fn text_frame(message: Message) -> Frame {
+    // SAFETY: a message is always valid UTF-8.
+    Frame::Text(unsafe { Utf8Bytes::from_bytes_unchecked(message.into_bytes()) })
+}
+
+/// Serve one browser connection until it disconnects or the broadcast
+/// channel closes.
+async fn serve_client(
+    stream: TcpStream,
+    peer: SocketAddr,
+    mut messages: broadcast::Receiver<Message>,
+    name: String,
+) {
+    let mut filter = MessageFilter::default();
+    let websocket =
+        match tokio_tungstenite::accept_hdr_async(stream, CaptureFilter(&mut filter)).await {
+            Ok(websocket) => websocket,
+            Err(e) => {
+                warn!("{name}WebSocket handshake with {peer} failed: {e}");
+                return;
+            }
+        };
+    info!("{name}Client {peer} connected, filter: {filter}");
+
+    let (mut sink, mut incoming) = websocket.split();
+    loop {
+        tokio::select! {
+            message = messages.recv() => match message {
+                Ok(message) => {
+                    if !filter.matches(&message) {
+                        continue;
+                    }
+                    if let Err(e) = sink.send(text_frame(message)).await {
+                        debug!("{name}Error sending to {peer}: {e}");
+                        break;
+                    }
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("{name}Client {peer} is too slow; skipped {skipped} message(s)");
+                }
+                Err(RecvError::Closed) => {
+                    let _ = sink.send(Frame::Close(None)).await;
+                    break;
+                }
+            },
+            // Browsers don't send anything we act on; just notice when they
+            // go away. tungstenite answers pings on its own.
+            frame = incoming.next() => match frame {
+                Some(Ok(Frame::Close(_)) | Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+    info!("{name}Client {peer} disconnected");
+}
+
+#[async_trait]
+impl OutputServer for OutputServerOptions<WebSocketListener> {
+    async fn new(host: &str, port: u16, options: &TransportOptions) -> Result<Self, Error> {
+        let socket = TcpListener::bind((host, port))
+            .await
+            .with_context(|| format!("[WebSocket Output {host}:{port}] Error binding"))?;
+
+        Ok(Self {
+            host: host.to_string(),
+            port,
+            socket: WebSocketListener(socket),
+            options: options.clone(),
+        })
+    }
+
+    async fn watch_queue(self, receiver: &mut Receiver<Message>) -> Result<(), Error> {
+        let name = self.format_name();
+        info!("{name}Listening for WebSocket clients");
+
+        let (broadcaster, template) =
+            broadcast::channel::<Message>(self.options.get::<WebSocketOptions>().client_buffer);
+        let cancel = CancellationToken::new();
+        let client_name = name.clone();
+        let mut acceptor = tokio::spawn(accept_clients(
+            self.socket.0,
+            name.clone(),
+            cancel.clone(),
+            move |stream, peer| {
+                serve_client(stream, peer, template.resubscribe(), client_name.clone())
+            },
+        ));
+
+        loop {
+            tokio::select! {
+                biased;
+                result = &mut acceptor => {
+                    return match result {
+                        Ok(Err(e)) => Err(e),
+                        Ok(Ok(())) => Err(Error::msg(format!("{name}Listener stopped unexpectedly"))),
+                        Err(e) => Err(Error::msg(format!("{name}Listener panicked: {e}"))),
+                    };
+                }
+                message = receiver.recv() => {
+                    let Some(message) = message else {
+                        break;
+                    };
+                    debug!("{name}Received: {message}");
+                    // Err only means nobody is connected right now.
+                    let _ = broadcaster.send(message);
+                }
+            }
+        }
+
+        // All bridge Senders have been dropped; this happens only during
+        // graceful shutdown. Closing the broadcast channel lets each client
+        // send what it has buffered and then a Close frame.
+        info!("{name}Input channel closed (shutdown); closing client connections");
+        drop(broadcaster);
+        cancel.cancel();
+        match acceptor.await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => warn!("{e} during shutdown"),
+            Err(e) => warn!("{name}Listener panicked during shutdown: {e}"),
+        }
+        Ok(())
+    }
+
+    fn format_name(&self) -> String {
+        format!("[WebSocket Output {}:{}] ", self.host, self.port)
+    }
+}
+
+/// WebSocket server that browsers connect to. Unlike most protocols the
+/// bridge listens rather than connects.
+pub struct WebSocketTransport;
+
+#[async_trait]
+impl Transport for WebSocketTransport {
+    fn name(&self) -> &'static str {
+        "websocket"
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        &["ws"]
+    }
+
+    fn supports_output(&self) -> bool {
+        true
+    }
+
+    fn same_options(&self, old: &TransportOptions, new: &TransportOptions) -> bool {
+        old.get::<WebSocketOptions>() == new.get::<WebSocketOptions>()
+    }
+
+    fn options_from_config(&self, config: &Config, options: &mut TransportOptions) -> Result<()> {
+        options.set(WebSocketOptions {
+            client_buffer: config.get_ws_client_buffer(),
+        });
+        Ok(())
+    }
+
+    fn configure(&self, name: &str, value: &str, options: &mut TransportOptions) -> Result<()> {
+        match name {
+            "client-buffer" => {
+                options.get_mut::<WebSocketOptions>().client_buffer =
+                    parse_at_least(name, value, 1)?;
+            }
+            _ => return Err(unknown_setting(self.name(), name)),
+        }
+        Ok(())
+    }
+
+    async fn check(&self, endpoint: &Endpoint, _input: bool) -> Checks {
+        crate::check::binds(&endpoint.host, endpoint.port).await
+    }
+
+    async fn run_output(
+        &self,
+        host: &str,
+        port: u16,
+        receiver: &mut Receiver<Message>,
+        options: &TransportOptions,
+        status: &StatusReporter,
+    ) -> Result<()> {
+        crate::transport::serve_output::<WebSocketListener>(host, port, receiver, options, status)
+            .await
+    }
+}
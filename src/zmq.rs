@@ -6,20 +6,242 @@
 
 use anyhow::{Error, Result};
 use async_trait::async_trait;
-use futures::SinkExt;
-use futures::StreamExt;
+use futures::{Sink, SinkExt, Stream, StreamExt};
+use std::borrow::Cow;
 use std::sync::OnceLock;
-use tmq::Context;
-use tmq::publish;
 use tmq::publish::Publish;
-use tmq::subscribe;
+use tmq::pull::Pull;
+use tmq::push::Push;
 use tmq::subscribe::Subscribe;
+use tmq::{Context, FromZmqSocket, Multipart, SocketBuilder, TmqError};
+use tmq::{publish, pull, push, subscribe};
 use tokio::sync::mpsc::{Receiver, Sender};
 
+use crate::capture::Recorder;
+use crate::config::Config;
+use crate::endpoint::{Endpoint, host_port, parse_at_least, parse_flag, unknown_setting};
+use crate::message::Message;
+use crate::pipeline::InputSinks;
 use crate::serverconfig::InputServer;
 use crate::serverconfig::InputServerOptions;
 use crate::serverconfig::OutputServer;
 use crate::serverconfig::OutputServerOptions;
+use crate::serverconfig::TransportOptions;
+use crate::stats::StatsEvent;
+use crate::status::StatusReporter;
+use crate::transport::{Checks, Transport};
+
+/// JSON key under which `ZmqFrameMode::Metadata` stores the leading frames.
+const METADATA_FRAMES_KEY: &str = "zmq_frames";
+
+/// How a multipart `ZeroMQ` message is collapsed into a single bridged
+/// message.
+///
+/// Publishers that use `ZeroMQ` topic filtering send the topic as its own
+/// frame ahead of the JSON body. Joining every frame with a space (the
+/// historical behavior, and still the default) turns that into
+/// `topic {"json": ...}`, which downstream JSON parsers reject.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ZmqFrameMode {
+    /// Join every frame with a single space.
+    #[default]
+    Join,
+    /// Forward only the last frame.
+    Last,
+    /// Drop the first (topic) frame and join the remaining frames with a
+    /// space. Single-frame messages have no topic and are forwarded as-is.
+    DropFirst,
+    /// Forward the last frame as the body and carry the preceding frames as
+    /// a `zmq_frames` array inside the body's JSON object. Bodies that are
+    /// not JSON objects are forwarded unchanged.
+    Metadata,
+}
+
+impl TryFrom<&str> for ZmqFrameMode {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "join" => Ok(Self::Join),
+            "last" => Ok(Self::Last),
+            "drop-first" => Ok(Self::DropFirst),
+            "metadata" => Ok(Self::Metadata),
+            _ => Err(Error::msg(format!("Unknown ZMQ frame mode: {s}"))),
+        }
+    }
+}
+
+impl ZmqFrameMode {
+    /// Collapse the decoded frames of one multipart message into the string
+    /// forwarded to the bridge channel. `name` is only used for log context.
+    pub fn compose(self, mut frames: Vec<Cow<'_, str>>, name: &str) -> String {
+        match self {
+            Self::Join => frames.join(" "),
+            Self::Last => frames.pop().map(Cow::into_owned).unwrap_or_default(),
+            Self::DropFirst => {
+                if frames.len() > 1 {
+                    frames[1..].join(" ")
+                } else {
+                    frames.join(" ")
+                }
+            }
+            Self::Metadata => {
+                let Some(body) = frames.pop() else {
+                    return String::new();
+                };
+                if frames.is_empty() {
+                    return body.into_owned();
+                }
+
+                // Checked as a whole, but spliced as text, so the decoder's
+                // key order and number formatting reach the output as sent.
+                let object = body.trim_end();
+                let (Some(inner), Ok(_)) = (
+                    object.strip_suffix('}'),
+                    serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(object),
+                ) else {
+                    warn!(
+                        "{name}Body frame is not a JSON object; forwarding without frame metadata"
+                    );
+                    return body.into_owned();
+                };
+                let separator = if inner.trim_end().ends_with('{') {
+                    ""
+                } else {
+                    ","
+                };
+                let metadata = serde_json::Value::from(
+                    frames.into_iter().map(Cow::into_owned).collect::<Vec<_>>(),
+                );
+                format!(
+                    "{}{separator}\"{METADATA_FRAMES_KEY}\":{metadata}}}",
+                    inner.trim_end()
+                )
+            }
+        }
+    }
+}
+
+/// Tuning for every `ZeroMQ` socket the bridge creates.
+///
+/// Every `None` leaves the libzmq default in place. The high-water mark is
+/// applied as the receive HWM on input sockets and the send HWM on output
+/// sockets, since each socket only moves messages in one direction.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ZmqOptions {
+    pub frame_mode: ZmqFrameMode,
+    /// Maximum number of messages queued per peer before the socket drops
+    /// (SUB/PUB) or blocks (PUSH/PULL). `0` means unlimited.
+    pub high_water_mark: Option<i32>,
+    /// How long unsent messages are kept after the socket is closed, in
+    /// milliseconds. `-1` waits forever.
+    pub linger_ms: Option<i32>,
+    /// Initial delay between reconnect attempts, in milliseconds.
+    pub reconnect_interval_ms: Option<i32>,
+    /// Upper bound for the exponential reconnect delay, in milliseconds.
+    /// `0` disables the backoff and keeps retrying at the initial interval.
+    pub reconnect_interval_max_ms: Option<i32>,
+    /// Enables TCP keepalive with this idle time, in seconds.
+    pub tcp_keepalive_idle_secs: Option<i32>,
+    /// Interval between keepalive probes, in seconds.
+    pub tcp_keepalive_interval_secs: Option<i32>,
+    /// Number of unanswered probes before the connection is dropped.
+    pub tcp_keepalive_count: Option<i32>,
+    /// Listen on the address instead of connecting to it, so peers connect
+    /// to the bridge. Only set per endpoint, with `zmq+bind://`.
+    pub bind: bool,
+}
+
+/// Which way messages flow through a socket, used to pick the send or
+/// receive high-water mark.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Direction {
+    Input,
+    Output,
+}
+
+impl ZmqOptions {
+    /// Apply a setting from a `protocol` endpoint URL. The names are those of
+    /// the `--zmq-*` flags without the prefix.
+    fn configure(&mut self, protocol: &str, name: &str, value: &str) -> Result<()> {
+        match name {
+            "bind" => self.bind = parse_flag(name, value)?,
+            "frame-mode" => self.frame_mode = ZmqFrameMode::try_from(value)?,
+            "hwm" => self.high_water_mark = Some(parse_at_least(name, value, 0)?),
+            "linger-ms" => self.linger_ms = Some(parse_at_least(name, value, -1)?),
+            "reconnect-ivl-ms" => {
+                self.reconnect_interval_ms = Some(parse_at_least(name, value, 0)?);
+            }
+            "reconnect-ivl-max-ms" => {
+                self.reconnect_interval_max_ms = Some(parse_at_least(name, value, 0)?);
+            }
+            "tcp-keepalive-idle" => {
+                self.tcp_keepalive_idle_secs = Some(parse_at_least(name, value, 1)?);
+            }
+            "tcp-keepalive-intvl" => {
+                self.tcp_keepalive_interval_secs = Some(parse_at_least(name, value, 1)?);
+            }
+            "tcp-keepalive-cnt" => {
+                self.tcp_keepalive_count = Some(parse_at_least(name, value, 1)?);
+            }
+            _ => return Err(unknown_setting(protocol, name)),
+        }
+        Ok(())
+    }
+
+    /// Build the socket with these options and bind or connect it to
+    /// `host:port`.
+    fn open<T: FromZmqSocket<T>>(
+        &self,
+        builder: SocketBuilder<T>,
+        direction: Direction,
+        host: &str,
+        port: u16,
+    ) -> Result<T, TmqError> {
+        let address = format!("tcp://{}", host_port(host, port));
+        let builder = self.apply(builder, direction).set_ipv6(host.contains(':'));
+        if self.bind {
+            builder.bind(&address)
+        } else {
+            builder.connect(&address)
+        }
+    }
+
+    /// Apply the configured socket options to `builder` before it connects.
+    /// `tmq` defers option errors until `connect`, so nothing is lost by not
+    /// checking each setter here.
+    fn apply<T: FromZmqSocket<T>>(
+        &self,
+        mut builder: SocketBuilder<T>,
+        direction: Direction,
+    ) -> SocketBuilder<T> {
+        if let Some(hwm) = self.high_water_mark {
+            builder = match direction {
+                Direction::Input => builder.set_rcvhwm(hwm),
+                Direction::Output => builder.set_sndhwm(hwm),
+            };
+        }
+        if let Some(linger) = self.linger_ms {
+            builder = builder.set_linger(linger);
+        }
+        if let Some(interval) = self.reconnect_interval_ms {
+            builder = builder.set_reconnect_ivl(interval);
+        }
+        if let Some(interval) = self.reconnect_interval_max_ms {
+            builder = builder.set_reconnect_ivl_max(interval);
+        }
+        if let Some(idle) = self.tcp_keepalive_idle_secs {
+            builder = builder.set_tcp_keepalive(1).set_tcp_keepalive_idle(idle);
+        }
+        if let Some(interval) = self.tcp_keepalive_interval_secs {
+            builder = builder.set_tcp_keepalive_intvl(interval);
+        }
+        if let Some(count) = self.tcp_keepalive_count {
+            builder = builder.set_tcp_keepalive_cnt(count);
+        }
+        builder
+    }
+}
 
 /// Return the process-wide `ZeroMQ` `Context`, creating it on first use.
 ///
@@ -33,16 +255,142 @@ fn zmq_context() -> &'static Context {
     CONTEXT.get_or_init(Context::new)
 }
 
+/// Read multipart messages from `socket` and forward them to the bridge and
+/// stats channels. Shared by the SUB and PULL inputs, which differ only in
+/// how the socket is created.
+async fn forward_messages<S>(
+    mut socket: S,
+    name: &str,
+    sender: Option<&Sender<Message>>,
+    stats: &Sender<StatsEvent>,
+    recorder: Option<&Recorder>,
+    source: &str,
+    frame_mode: ZmqFrameMode,
+) -> Result<(), Error>
+where
+    S: Stream<Item = Result<Multipart, TmqError>> + Unpin + Send,
+{
+    while let Some(msg) = socket.next().await {
+        let message = match msg {
+            Ok(message) => message,
+            Err(e) => {
+                error!("{name}Error: {e:?}");
+                continue;
+            }
+        };
+
+        if let Some(recorder) = recorder {
+            let frames = message.iter().map(|item| &**item).collect::<Vec<&[u8]>>();
+            recorder.record(source, &frames).await;
+        }
+
+        // ACARS payloads are 7-bit ASCII in practice, but warn loudly if a
+        // frame contains non-UTF-8 bytes so silent corruption is visible.
+        // Fall back to lossy conversion so the bridge still forwards
+        // something. The frames are then collapsed according to the
+        // configured frame mode.
+        let frame_count = message.len();
+        let frames = message
+            .iter()
+            .map(|item| match std::str::from_utf8(item) {
+                Ok(s) => Cow::Borrowed(s),
+                Err(e) => {
+                    warn!(
+                        "{}Non-UTF-8 frame ({} bytes, error at byte {}); using lossy conversion",
+                        name,
+                        item.len(),
+                        e.valid_up_to()
+                    );
+                    String::from_utf8_lossy(item)
+                }
+            })
+            .collect::<Vec<_>>();
+        let mut composed_message = frame_mode.compose(frames, name);
+
+        debug!(
+            "{}Received ({} frame{}): {}",
+            name,
+            frame_count,
+            if frame_count == 1 { "" } else { "s" },
+            composed_message
+        );
+        let stripped = composed_message
+            .strip_suffix("\r\n")
+            .or_else(|| composed_message.strip_suffix('\n'))
+            .map_or(composed_message.len(), str::len);
+        composed_message.truncate(stripped);
+
+        if let Some(sender) = sender {
+            if let Err(e) = sender.send(composed_message.into()).await {
+                return Err(Error::msg(format!("{name}Output channel closed: {e}")));
+            }
+            trace!("{name}Message sent to sender channel");
+        }
+
+        if let Err(e) = stats
+            .send(StatsEvent::ZmqMessage {
+                frames: frame_count,
+            })
+            .await
+        {
+            return Err(Error::msg(format!("{name}Stats channel closed: {e}")));
+        }
+        trace!("{name}Stats sent to channel");
+    }
+
+    // A tmq receive stream should not normally end on its own. If we get
+    // here, something unexpected happened (context dropped, etc.). Surface
+    // as Err so the supervisor logs at error! level rather than info! and
+    // treats it as an abnormal restart.
+    Err(Error::msg(format!(
+        "{name}ZMQ receive stream ended unexpectedly"
+    )))
+}
+
+/// Send every message from the bridge channel as a single-frame message on
+/// `socket`. Shared by the PUB and PUSH outputs.
+async fn send_messages<S>(
+    mut socket: S,
+    name: &str,
+    receiver: &mut Receiver<Message>,
+) -> Result<(), Error>
+where
+    S: Sink<Multipart, Error = TmqError> + Unpin + Send,
+{
+    while let Some(message) = receiver.recv().await {
+        debug!("{name}Received: {message}");
+
+        let message_zmq = Multipart::from(vec![message.as_bytes()]);
+
+        if let Err(e) = socket.send(message_zmq).await {
+            return Err(Error::msg(format!(
+                "{name}Error sending message to consumer: {e}"
+            )));
+        }
+        trace!("{name}Message sent to consumer");
+    }
+
+    // All bridge Senders have been dropped; this happens only during
+    // graceful shutdown. Return Ok(()) so the output supervisor treats it as
+    // a terminal, clean exit rather than a failure to restart.
+    info!("{name}Input channel closed (shutdown); exiting");
+    Ok(())
+}
+
 #[async_trait]
 impl InputServer for InputServerOptions<Subscribe> {
     async fn new(
         host: &str,
         port: u16,
-        sender: Option<Sender<String>>,
-        stats: Sender<u8>,
+        sender: Option<Sender<Message>>,
+        stats: Sender<StatsEvent>,
+        recorder: Option<Recorder>,
+        options: &TransportOptions,
     ) -> Result<Self, Error> {
-        let address = format!("tcp://{host}:{port}");
-        let socket = subscribe(zmq_context()).connect(&address)?.subscribe(b"")?;
+        let socket = options
+            .get::<ZmqOptions>()
+            .open(subscribe(zmq_context()), Direction::Input, host, port)?
+            .subscribe(b"")?;
 
         Ok(Self {
             host: host.to_string(),
@@ -50,122 +398,351 @@ impl InputServer for InputServerOptions<Subscribe> {
             socket,
             sender,
             stats,
+            recorder,
+            options: options.clone(),
         })
     }
 
-    async fn receive_message(mut self) -> Result<(), Error> {
-        while let Some(msg) = self.socket.next().await {
-            let message = match msg {
-                Ok(message) => message,
-                Err(e) => {
-                    error!("{}Error: {:?}", self.format_name(), e);
-                    continue;
-                }
-            };
+    async fn receive_message(self) -> Result<(), Error> {
+        let name = self.format_name();
+        forward_messages(
+            self.socket,
+            &name,
+            self.sender.as_ref(),
+            &self.stats,
+            self.recorder.as_ref(),
+            &format!("zmq://{}:{}", self.host, self.port),
+            self.options.get::<ZmqOptions>().frame_mode,
+        )
+        .await
+    }
 
-            // Join all frames with spaces. ACARS payloads are 7-bit ASCII in
-            // practice, but warn loudly if a frame contains non-UTF-8 bytes
-            // so silent corruption is visible. Fall back to lossy conversion
-            // so the bridge still forwards something.
-            let composed_message = message
-                .iter()
-                .map(|item| match std::str::from_utf8(item) {
-                    Ok(s) => std::borrow::Cow::Borrowed(s),
-                    Err(e) => {
-                        warn!(
-                            "{}Non-UTF-8 frame ({} bytes, error at byte {}); using lossy conversion",
-                            self.format_name(),
-                            item.len(),
-                            e.valid_up_to()
-                        );
-                        String::from_utf8_lossy(item)
-                    }
-                })
-                .collect::<Vec<_>>()
-                .join(" ");
-
-            debug!("{}Received: {}", self.format_name(), composed_message);
-            let stripped = composed_message
-                .strip_suffix("\r\n")
-                .or_else(|| composed_message.strip_suffix('\n'))
-                .unwrap_or(&composed_message);
-
-            if let Some(sender) = &self.sender {
-                if let Err(e) = sender.send(stripped.to_string()).await {
-                    return Err(Error::msg(format!(
-                        "{}Output channel closed: {}",
-                        self.format_name(),
-                        e
-                    )));
-                }
-                trace!("{}Message sent to sender channel", self.format_name());
-            }
+    fn format_name(&self) -> String {
+        format!("[ZMQ Input {}:{}] ", self.host, self.port)
+    }
+}
 
-            if let Err(e) = self.stats.send(1).await {
-                return Err(Error::msg(format!(
-                    "{}Stats channel closed: {}",
-                    self.format_name(),
-                    e
-                )));
-            }
-            trace!("{}Stats sent to channel", self.format_name());
-        }
+#[async_trait]
+impl InputServer for InputServerOptions<Pull> {
+    async fn new(
+        host: &str,
+        port: u16,
+        sender: Option<Sender<Message>>,
+        stats: Sender<StatsEvent>,
+        recorder: Option<Recorder>,
+        options: &TransportOptions,
+    ) -> Result<Self, Error> {
+        let socket =
+            options
+                .get::<ZmqOptions>()
+                .open(pull(zmq_context()), Direction::Input, host, port)?;
+
+        Ok(Self {
+            host: host.to_string(),
+            port,
+            socket,
+            sender,
+            stats,
+            recorder,
+            options: options.clone(),
+        })
+    }
 
-        // A tmq Subscribe stream should not normally end on its own. If we
-        // get here, something unexpected happened (context dropped, etc.).
-        // Surface as Err so the supervisor logs at error! level rather than
-        // info! and treats it as an abnormal restart.
-        Err(Error::msg(format!(
-            "{}ZMQ subscribe stream ended unexpectedly",
-            self.format_name()
-        )))
+    async fn receive_message(self) -> Result<(), Error> {
+        let name = self.format_name();
+        forward_messages(
+            self.socket,
+            &name,
+            self.sender.as_ref(),
+            &self.stats,
+            self.recorder.as_ref(),
+            &format!("zmq://{}:{}", self.host, self.port),
+            self.options.get::<ZmqOptions>().frame_mode,
+        )
+        .await
     }
 
     fn format_name(&self) -> String {
-        format!("[ZMQ Input {}:{}] ", self.host, self.port)
+        format!("[ZMQ PULL Input {}:{}] ", self.host, self.port)
     }
 }
 
 #[async_trait]
 impl OutputServer for OutputServerOptions<Publish> {
-    async fn new(host: &str, port: u16) -> Result<Self, Error> {
-        let address = format!("tcp://{host}:{port}");
-        let socket = publish(zmq_context()).connect(&address)?;
+    async fn new(host: &str, port: u16, options: &TransportOptions) -> Result<Self, Error> {
+        let socket = options.get::<ZmqOptions>().open(
+            publish(zmq_context()),
+            Direction::Output,
+            host,
+            port,
+        )?;
 
         Ok(Self {
             host: host.to_string(),
             port,
             socket,
+            options: options.clone(),
         })
     }
 
-    async fn watch_queue(mut self, receiver: &mut Receiver<String>) -> Result<(), Error> {
-        while let Some(message) = receiver.recv().await {
-            debug!("{}Received: {}", self.format_name(), message);
+    async fn watch_queue(self, receiver: &mut Receiver<Message>) -> Result<(), Error> {
+        let name = self.format_name();
+        send_messages(self.socket, &name, receiver).await
+    }
 
-            let message_zmq = vec![&message];
+    fn format_name(&self) -> String {
+        format!("[ZMQ Output {}:{}] ", self.host, self.port)
+    }
+}
 
-            if let Err(e) = self.socket.send(message_zmq).await {
-                return Err(Error::msg(format!(
-                    "{}Error sending message to consumer: {}",
-                    self.format_name(),
-                    e
-                )));
-            }
-            trace!("{}Message sent to consumer", self.format_name());
+#[async_trait]
+impl OutputServer for OutputServerOptions<Push> {
+    async fn new(host: &str, port: u16, options: &TransportOptions) -> Result<Self, Error> {
+        let socket =
+            options
+                .get::<ZmqOptions>()
+                .open(push(zmq_context()), Direction::Output, host, port)?;
+
+        Ok(Self {
+            host: host.to_string(),
+            port,
+            socket,
+            options: options.clone(),
+        })
+    }
+
+    async fn watch_queue(self, receiver: &mut Receiver<Message>) -> Result<(), Error> {
+        let name = self.format_name();
+        send_messages(self.socket, &name, receiver).await
+    }
+
+    fn format_name(&self) -> String {
+        format!("[ZMQ PUSH Output {}:{}] ", self.host, self.port)
+    }
+}
+
+/// SUB on the input side, PUB on the output side.
+pub struct ZmqTransport;
+
+pub(crate) fn zmq_frame_mode(config: &Config) -> Result<ZmqFrameMode> {
+    ZmqFrameMode::try_from(config.get_zmq_frame_mode())
+        .map_err(|e| anyhow::anyhow!("Error parsing ZMQ frame mode: {e}"))
+}
+
+/// Both `zmq` transports, and `replay` for its frame mode, share the one
+/// `ZmqOptions`; an endpoint URL sets `bind`.
+fn zmq_options_from_config(config: &Config, options: &mut TransportOptions) -> Result<()> {
+    options.set(ZmqOptions {
+        frame_mode: zmq_frame_mode(config)?,
+        high_water_mark: config.get_zmq_hwm(),
+        linger_ms: config.get_zmq_linger_ms(),
+        reconnect_interval_ms: config.get_zmq_reconnect_ivl_ms(),
+        reconnect_interval_max_ms: config.get_zmq_reconnect_ivl_max_ms(),
+        tcp_keepalive_idle_secs: config.get_zmq_tcp_keepalive_idle(),
+        tcp_keepalive_interval_secs: config.get_zmq_tcp_keepalive_intvl(),
+        tcp_keepalive_count: config.get_zmq_tcp_keepalive_cnt(),
+        bind: false,
+    });
+    Ok(())
+}
+
+#[async_trait]
+impl Transport for ZmqTransport {
+    fn name(&self) -> &'static str {
+        "zmq"
+    }
+
+    fn supports_input(&self) -> bool {
+        true
+    }
+
+    fn supports_output(&self) -> bool {
+        true
+    }
+
+    fn same_options(&self, old: &TransportOptions, new: &TransportOptions) -> bool {
+        old.get::<ZmqOptions>() == new.get::<ZmqOptions>()
+    }
+
+    fn options_from_config(&self, config: &Config, options: &mut TransportOptions) -> Result<()> {
+        zmq_options_from_config(config, options)
+    }
+
+    fn configure(&self, name: &str, value: &str, options: &mut TransportOptions) -> Result<()> {
+        options
+            .get_mut::<ZmqOptions>()
+            .configure(self.name(), name, value)
+    }
+
+    async fn check(&self, endpoint: &Endpoint, _input: bool) -> Checks {
+        if endpoint.options.get::<ZmqOptions>().bind {
+            crate::check::binds(&endpoint.host, endpoint.port).await
+        } else {
+            crate::check::resolves(&endpoint.host, endpoint.port).await
         }
+    }
+
+    async fn run_input(
+        &self,
+        host: &str,
+        port: u16,
+        sinks: InputSinks,
+        options: &TransportOptions,
+        status: &StatusReporter,
+    ) -> Result<()> {
+        crate::transport::serve_input::<Subscribe>(host, port, sinks, options, status).await
+    }
+
+    async fn run_output(
+        &self,
+        host: &str,
+        port: u16,
+        receiver: &mut Receiver<Message>,
+        options: &TransportOptions,
+        status: &StatusReporter,
+    ) -> Result<()> {
+        crate::transport::serve_output::<Publish>(host, port, receiver, options, status).await
+    }
+}
 
-        // All bridge Senders have been dropped; this happens only during
-        // graceful shutdown. Return Ok(()) so the output supervisor treats it
-        // as a terminal, clean exit rather than a failure to restart.
-        info!(
-            "{}Input channel closed (shutdown); exiting",
-            self.format_name()
+/// PULL on the input side, PUSH on the output side. Unlike SUB/PUB, a PUSH
+/// socket blocks instead of dropping when the peer falls behind, so no
+/// message is lost to a slow consumer.
+pub struct ZmqPipelineTransport;
+
+#[async_trait]
+impl Transport for ZmqPipelineTransport {
+    fn name(&self) -> &'static str {
+        "zmq-push"
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        &["zmq-pull"]
+    }
+
+    fn supports_input(&self) -> bool {
+        true
+    }
+
+    fn supports_output(&self) -> bool {
+        true
+    }
+
+    fn same_options(&self, old: &TransportOptions, new: &TransportOptions) -> bool {
+        old.get::<ZmqOptions>() == new.get::<ZmqOptions>()
+    }
+
+    fn options_from_config(&self, config: &Config, options: &mut TransportOptions) -> Result<()> {
+        zmq_options_from_config(config, options)
+    }
+
+    fn configure(&self, name: &str, value: &str, options: &mut TransportOptions) -> Result<()> {
+        options
+            .get_mut::<ZmqOptions>()
+            .configure(self.name(), name, value)
+    }
+
+    async fn check(&self, endpoint: &Endpoint, _input: bool) -> Checks {
+        if endpoint.options.get::<ZmqOptions>().bind {
+            crate::check::binds(&endpoint.host, endpoint.port).await
+        } else {
+            crate::check::resolves(&endpoint.host, endpoint.port).await
+        }
+    }
+
+    async fn run_input(
+        &self,
+        host: &str,
+        port: u16,
+        sinks: InputSinks,
+        options: &TransportOptions,
+        status: &StatusReporter,
+    ) -> Result<()> {
+        crate::transport::serve_input::<Pull>(host, port, sinks, options, status).await
+    }
+
+    async fn run_output(
+        &self,
+        host: &str,
+        port: u16,
+        receiver: &mut Receiver<Message>,
+        options: &TransportOptions,
+        status: &StatusReporter,
+    ) -> Result<()> {
+        crate::transport::serve_output::<Push>(host, port, receiver, options, status).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compose(mode: ZmqFrameMode, frames: &[&str]) -> String {
+        mode.compose(frames.iter().copied().map(Cow::Borrowed).collect(), "")
+    }
+
+    const TOPIC: &str = "acars";
+    const BODY: &str = r#"{"freq":131.55}"#;
+
+    #[test]
+    fn join_keeps_every_frame() {
+        assert_eq!(
+            compose(ZmqFrameMode::Join, &[TOPIC, BODY]),
+            format!("{TOPIC} {BODY}")
         );
-        Ok(())
+        assert_eq!(compose(ZmqFrameMode::Join, &[BODY]), BODY);
     }
 
-    fn format_name(&self) -> String {
-        format!("[ZMQ Output {}:{}] ", self.host, self.port)
+    #[test]
+    fn last_keeps_only_the_body() {
+        assert_eq!(compose(ZmqFrameMode::Last, &[TOPIC, "x", BODY]), BODY);
+        assert_eq!(compose(ZmqFrameMode::Last, &[]), "");
+    }
+
+    #[test]
+    fn drop_first_drops_only_a_topic() {
+        assert_eq!(
+            compose(ZmqFrameMode::DropFirst, &[TOPIC, "x", BODY]),
+            format!("x {BODY}")
+        );
+        assert_eq!(compose(ZmqFrameMode::DropFirst, &[BODY]), BODY);
+    }
+
+    #[test]
+    fn metadata_moves_the_topic_into_the_body() {
+        let composed = compose(ZmqFrameMode::Metadata, &[TOPIC, "x", BODY]);
+        let value: serde_json::Value = serde_json::from_str(&composed).expect("JSON");
+        assert_eq!(
+            value,
+            serde_json::json!({"freq": 131.55, METADATA_FRAMES_KEY: [TOPIC, "x"]})
+        );
+        assert_eq!(compose(ZmqFrameMode::Metadata, &[BODY]), BODY);
+        assert_eq!(
+            compose(ZmqFrameMode::Metadata, &[TOPIC, "[1,2]"]),
+            "[1,2]",
+            "a body that isn't an object is forwarded as it is"
+        );
+    }
+
+    #[test]
+    fn metadata_leaves_the_body_as_the_decoder_wrote_it() {
+        let body = "{\"zeta\":1, \"freq\":131.550,\"text\":\"}\"}\n";
+        assert_eq!(
+            compose(ZmqFrameMode::Metadata, &[TOPIC, body]),
+            r#"{"zeta":1, "freq":131.550,"text":"}","zmq_frames":["acars"]}"#
+        );
+        assert_eq!(
+            compose(ZmqFrameMode::Metadata, &[TOPIC, "{ }"]),
+            r#"{"zmq_frames":["acars"]}"#
+        );
+    }
+
+    #[test]
+    fn modes_parse_by_name() {
+        assert_eq!(
+            ZmqFrameMode::try_from("Drop-First").expect("valid"),
+            ZmqFrameMode::DropFirst
+        );
+        assert!(ZmqFrameMode::try_from("first").is_err());
     }
 }
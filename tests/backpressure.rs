@@ -0,0 +1,133 @@
+// Copyright (c) 2024-2026 Fred Clausen
+//
+// Licensed under the MIT license: https://opensource.org/licenses/MIT
+// Permission is granted to use, copy, modify, and redistribute the work.
+// Full license information available in the project LICENSE file.
+
+//! A slow output holds the input back through the bounded channels in
+//! between, rather than the bridge buffering without limit or dropping, but
+//! only for the stall timeout when other outputs are waiting too.
+
+mod common;
+
+use acars_bridge::{Bridge, memory};
+use std::time::Duration;
+
+use common::{messages, unique_name, within};
+
+const CAPACITY: usize = 4;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn a_stalled_output_holds_the_input_back_without_dropping() {
+    let (input_name, output_name) = (unique_name("in"), unique_name("out"));
+    let input = memory::create(&input_name, CAPACITY);
+    let output = memory::create(&output_name, CAPACITY);
+    let bridge = Bridge::new()
+        .input(format!("memory://{input_name}"))
+        .output(format!("memory://{output_name}"))
+        .channel_capacity(CAPACITY)
+        .stall_timeout(None)
+        .start()
+        .expect("starting the bridge");
+
+    // Nothing reads the output, so sends stall once every queue and channel
+    // in between is full.
+    let sent = messages(200);
+    let mut accepted = 0;
+    for message in &sent {
+        let queued = input.send(message.clone());
+        if tokio::time::timeout(Duration::from_millis(300), queued)
+            .await
+            .is_err()
+        {
+            break;
+        }
+        accepted += 1;
+    }
+    assert!(accepted < sent.len(), "the input was never held back");
+    // The input and output queues, the bridge and output channels, and a
+    // message in hand at each of the input, fan-out and output.
+    assert!(
+        accepted <= 4 * CAPACITY + 3,
+        "{accepted} messages were taken in before the input was held back"
+    );
+    assert_eq!(output.len(), CAPACITY);
+
+    // Once the output is read again, the rest flow through in order.
+    let remaining = sent[accepted..].to_vec();
+    let feeder = tokio::spawn(async move {
+        for message in remaining {
+            input.send(message).await;
+        }
+    });
+    let mut received = Vec::with_capacity(sent.len());
+    while received.len() < sent.len() {
+        received.push(within("the next message", output.recv()).await);
+    }
+    assert_eq!(received, sent);
+    within("the feeder", feeder).await.expect("feeder");
+
+    let counters = bridge.counters();
+    within("the bridge to shut down", bridge.shutdown())
+        .await
+        .expect("shutting down");
+    assert_eq!(counters.total(), sent.len() as u64);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn a_stalled_output_does_not_hold_the_others_back() {
+    let input_name = unique_name("in");
+    let (stalled_name, healthy_name) = (unique_name("stalled"), unique_name("healthy"));
+    let input = memory::queue(&input_name);
+    let stalled = memory::create(&stalled_name, CAPACITY);
+    let healthy = memory::queue(&healthy_name);
+    let bridge = Bridge::new()
+        .input(format!("memory://{input_name}"))
+        .output(format!("memory://{stalled_name}"))
+        .output(format!("memory://{healthy_name}"))
+        .channel_capacity(CAPACITY)
+        .stall_timeout(Some(Duration::from_millis(100)))
+        .start()
+        .expect("starting the bridge");
+
+    // Nothing reads the stalled output, yet every message reaches the other
+    // in order, with only the first stall waited out.
+    let sent = messages(100);
+    let feeder = tokio::spawn({
+        let sent = sent.clone();
+        async move {
+            for message in sent {
+                input.send(message).await;
+            }
+        }
+    });
+    let mut received = Vec::with_capacity(sent.len());
+    while received.len() < sent.len() {
+        received.push(within("the next message", healthy.recv()).await);
+    }
+    assert_eq!(received, sent);
+    within("the feeder", feeder).await.expect("feeder");
+    assert_eq!(stalled.len(), CAPACITY);
+
+    // Once it is read again, it gets new messages too.
+    while stalled.try_recv().is_some() {}
+    let queue = memory::queue(&input_name);
+    within("the stalled output to catch up", async {
+        loop {
+            queue.send("caught up").await;
+            if tokio::time::timeout(Duration::from_millis(100), async {
+                while stalled.recv().await != "caught up" {}
+            })
+            .await
+            .is_ok()
+            {
+                break;
+            }
+        }
+    })
+    .await;
+
+    within("the bridge to shut down", bridge.shutdown())
+        .await
+        .expect("shutting down");
+}
@@ -0,0 +1,80 @@
+// Copyright (c) 2024-2026 Fred Clausen
+//
+// Licensed under the MIT license: https://opensource.org/licenses/MIT
+// Permission is granted to use, copy, modify, and redistribute the work.
+// Full license information available in the project LICENSE file.
+
+//! The TCP output flushes what is queued in batches, bounded by the batch
+//! size and held open for the batch delay, without losing or reordering
+//! anything.
+
+mod common;
+
+use acars_bridge::Bridge;
+use acars_bridge::stats::{self, BatchCounters};
+use std::sync::Arc;
+use std::time::Duration;
+
+use common::{Collector, Feed, connect, messages, unique_name, within};
+
+/// The collector's URL with `settings` added, and the batch counters of the
+/// output writing to it.
+fn batched(collector: &Collector, settings: &str) -> (String, Arc<BatchCounters>) {
+    let address = collector.url.trim_start_matches("tcp://");
+    (
+        format!("{}?{settings}", collector.url),
+        stats::batch_counters(&format!("tcp {address}")),
+    )
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn batches_stop_at_the_batch_size() {
+    let feed = Feed::memory(&unique_name("in"));
+    let mut collector = Collector::tcp();
+    let (url, counters) = batched(&collector, "batch-size=8");
+    let bridge = Bridge::new()
+        .input(feed.url.clone())
+        .output(url)
+        .start()
+        .expect("starting the bridge");
+    connect(&feed, &mut collector).await;
+
+    let sent = messages(200);
+    for message in &sent {
+        feed.send(message);
+    }
+    assert_eq!(collector.expect(sent.len()).await, sent);
+    within("the bridge to shut down", bridge.shutdown())
+        .await
+        .expect("shutting down");
+
+    assert!(counters.messages() >= sent.len() as u64);
+    let summary = counters.summary();
+    assert!((2..=8).contains(&counters.largest()), "{summary}");
+    assert!(counters.batches() >= counters.messages() / 8);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn a_batch_waits_for_the_delay_before_flushing() {
+    let feed = Feed::memory(&unique_name("in"));
+    let mut collector = Collector::tcp();
+    let (url, counters) = batched(&collector, "batch-delay-ms=2000");
+    let bridge = Bridge::new()
+        .input(feed.url.clone())
+        .output(url)
+        .start()
+        .expect("starting the bridge");
+    connect(&feed, &mut collector).await;
+
+    // Trickled in, but well inside the delay, so they go out together.
+    let sent = messages(10);
+    for message in &sent {
+        feed.send(message);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(collector.expect(sent.len()).await, sent);
+    assert!(counters.largest() >= 10, "{}", counters.summary());
+    within("the bridge to shut down", bridge.shutdown())
+        .await
+        .expect("shutting down");
+}
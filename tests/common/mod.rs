@@ -0,0 +1,603 @@
+// Copyright (c) 2024-2026 Fred Clausen
+//
+// Licensed under the MIT license: https://opensource.org/licenses/MIT
+// Permission is granted to use, copy, modify, and redistribute the work.
+// Full license information available in the project LICENSE file.
+
+//! Stand-in peers for the integration tests: a `Feed` plays the decoder a
+//! bridge input receives from, a `Collector` the router a bridge output
+//! sends to, and a `Webhook` the consumer an HTTP output posts to. Each runs
+//! in a background task, so every protocol looks the same to a test.
+
+#![allow(dead_code)]
+
+use futures::{SinkExt, StreamExt};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::net::{TcpListener as StdTcpListener, UdpSocket as StdUdpSocket};
+use std::process::{Output, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream, UdpSocket};
+use tokio::process::{Child, Command};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::task::JoinHandle;
+use tokio_util::codec::{FramedRead, LinesCodec};
+
+use acars_bridge::memory;
+
+/// How long any one step of a test may take before it fails.
+pub const STEP: Duration = Duration::from_secs(10);
+
+/// Run `future`, failing the test if it takes longer than `STEP`.
+pub async fn within<T>(what: &str, future: impl Future<Output = T>) -> T {
+    tokio::time::timeout(STEP, future)
+        .await
+        .unwrap_or_else(|_| panic!("timed out waiting for {what}"))
+}
+
+/// A port nothing is listening on right now.
+#[must_use]
+pub fn free_port() -> u16 {
+    StdTcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .expect("binding a free port")
+        .port()
+}
+
+/// A UDP port nothing is bound to right now.
+#[must_use]
+pub fn free_udp_port() -> u16 {
+    StdUdpSocket::bind("127.0.0.1:0")
+        .and_then(|socket| socket.local_addr())
+        .expect("binding a free UDP port")
+        .port()
+}
+
+/// The binary reading `stdin`, with `args` for the rest. Its `stdout` is
+/// piped, for a `stdout` destination.
+pub fn bridge_binary(args: &[&str]) -> Child {
+    Command::new(env!("CARGO_BIN_EXE_acars-bridge"))
+        .env_clear()
+        .args(["--source", "stdin:"])
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .expect("starting acars-bridge")
+}
+
+/// The position of the first log line containing `text`.
+#[must_use]
+pub fn logged(output: &Output, text: &str) -> usize {
+    String::from_utf8_lossy(&output.stderr)
+        .lines()
+        .position(|line| line.contains(text))
+        .unwrap_or_else(|| panic!("nothing logged containing {text:?}"))
+}
+
+/// A name no other test uses for a memory queue.
+#[must_use]
+pub fn unique_name(prefix: &str) -> String {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    format!("{prefix}-{}", NEXT.fetch_add(1, Ordering::Relaxed))
+}
+
+/// `count` decoder-style JSON messages, numbered so loss and reordering
+/// show, with non-ASCII text and lengths from a few bytes to a few KiB.
+#[must_use]
+pub fn messages(count: usize) -> Vec<String> {
+    (0..count)
+        .map(|seq| {
+            let padding = "x".repeat((seq * 397) % 4000);
+            format!(r#"{{"seq":{seq},"hfdl":{{"msg":"Überflug ✈ KÖLN → ORD","pad":"{padding}"}}}}"#)
+        })
+        .collect()
+}
+
+fn probe(seq: usize) -> String {
+    format!(r#"{{"probe":{seq}}}"#)
+}
+
+fn is_probe(message: &str) -> bool {
+    message.starts_with(r#"{"probe":"#)
+}
+
+/// Aborts the peer's tasks when the peer is dropped.
+struct Tasks(Vec<JoinHandle<()>>);
+
+impl Drop for Tasks {
+    fn drop(&mut self) {
+        for task in &self.0 {
+            task.abort();
+        }
+    }
+}
+
+enum FeedCommand {
+    Send(String),
+    /// Close the connection to the bridge, for protocols that have one.
+    Disconnect,
+}
+
+/// A peer that sends messages into a bridge input.
+pub struct Feed {
+    /// Where the bridge input should receive from.
+    pub url: String,
+    commands: UnboundedSender<FeedCommand>,
+    /// Connections the bridge has made to the peer, for TCP.
+    pub connections: Arc<AtomicUsize>,
+    _tasks: Tasks,
+}
+
+impl Feed {
+    fn spawn(
+        url: String,
+        task: impl FnOnce(UnboundedReceiver<FeedCommand>) -> JoinHandle<()>,
+    ) -> Self {
+        let (commands, receiver) = mpsc::unbounded_channel();
+        Self {
+            url,
+            commands,
+            connections: Arc::default(),
+            _tasks: Tasks(vec![task(receiver)]),
+        }
+    }
+
+    pub fn send(&self, message: &str) {
+        let _ = self.commands.send(FeedCommand::Send(message.to_string()));
+    }
+
+    pub fn disconnect(&self) {
+        let _ = self.commands.send(FeedCommand::Disconnect);
+    }
+
+    /// Feeds the bridge through the memory queue `name`.
+    #[must_use]
+    pub fn memory(name: &str) -> Self {
+        let queue = memory::queue(name);
+        Self::spawn(format!("memory://{name}"), |mut commands| {
+            tokio::spawn(async move {
+                while let Some(command) = commands.recv().await {
+                    if let FeedCommand::Send(message) = command {
+                        queue.send(message).await;
+                    }
+                }
+            })
+        })
+    }
+
+    /// Sends datagrams to the port the bridge binds.
+    #[must_use]
+    pub fn udp() -> Self {
+        let port = free_udp_port();
+        Self::spawn(format!("udp://127.0.0.1:{port}"), |mut commands| {
+            tokio::spawn(async move {
+                let socket = UdpSocket::bind("127.0.0.1:0").await.expect("binding UDP");
+                while let Some(command) = commands.recv().await {
+                    if let FeedCommand::Send(message) = command {
+                        let _ = socket
+                            .send_to(message.as_bytes(), ("127.0.0.1", port))
+                            .await;
+                    }
+                }
+            })
+        })
+    }
+
+    /// Listens for the bridge to connect, and writes lines to the latest
+    /// connection, waiting for one when there is none.
+    #[must_use]
+    pub fn tcp() -> Self {
+        let listener = StdTcpListener::bind("127.0.0.1:0").expect("binding TCP");
+        listener.set_nonblocking(true).expect("setting nonblocking");
+        let port = listener.local_addr().expect("local address").port();
+        let connections = Arc::new(AtomicUsize::new(0));
+        let counted = Arc::clone(&connections);
+        let mut feed = Self::spawn(format!("tcp://127.0.0.1:{port}"), |mut commands| {
+            tokio::spawn(async move {
+                let listener = TcpListener::from_std(listener).expect("adopting listener");
+                let mut client: Option<TcpStream> = None;
+                while let Some(command) = commands.recv().await {
+                    let message = match command {
+                        FeedCommand::Send(message) => message,
+                        FeedCommand::Disconnect => {
+                            client = None;
+                            continue;
+                        }
+                    };
+                    loop {
+                        if client.is_none() {
+                            let (stream, _) = listener.accept().await.expect("accepting");
+                            counted.fetch_add(1, Ordering::Relaxed);
+                            client = Some(stream);
+                        }
+                        let Some(stream) = client.as_mut() else {
+                            continue;
+                        };
+                        if stream
+                            .write_all(format!("{message}\n").as_bytes())
+                            .await
+                            .is_ok()
+                        {
+                            break;
+                        }
+                        client = None;
+                    }
+                }
+            })
+        });
+        feed.connections = connections;
+        feed
+    }
+
+    /// Binds a PUB socket for a `zmq` input to connect to.
+    #[must_use]
+    pub fn zmq() -> Self {
+        Self::zmq_at(free_port()).expect("binding PUB")
+    }
+
+    /// Like `zmq`, on `port`, or `None` if it can't be bound yet.
+    #[must_use]
+    pub fn zmq_at(port: u16) -> Option<Self> {
+        let socket = tmq::publish(&tmq::Context::new())
+            .bind(&format!("tcp://127.0.0.1:{port}"))
+            .ok()?;
+        Some(Self::spawn(format!("zmq://127.0.0.1:{port}"), |commands| {
+            tokio::spawn(send_zmq(socket, commands))
+        }))
+    }
+
+    /// Binds a PUSH socket for a `zmq-pull` input to connect to.
+    #[must_use]
+    pub fn zmq_push() -> Self {
+        let port = free_port();
+        let socket = tmq::push(&tmq::Context::new())
+            .bind(&format!("tcp://127.0.0.1:{port}"))
+            .expect("binding PUSH");
+        Self::spawn(format!("zmq-pull://127.0.0.1:{port}"), |commands| {
+            tokio::spawn(send_zmq(socket, commands))
+        })
+    }
+}
+
+async fn send_zmq<S>(mut socket: S, mut commands: UnboundedReceiver<FeedCommand>)
+where
+    S: futures::Sink<tmq::Multipart> + Unpin,
+{
+    while let Some(command) = commands.recv().await {
+        if let FeedCommand::Send(message) = command {
+            let _ = socket
+                .send(tmq::Multipart::from(vec![message.as_bytes()]))
+                .await;
+        }
+    }
+}
+
+/// A peer that receives what a bridge output sends.
+pub struct Collector {
+    /// Where the bridge output should send to.
+    pub url: String,
+    received: UnboundedReceiver<String>,
+    /// Connections the bridge has made to the peer, for TCP.
+    pub connections: Arc<AtomicUsize>,
+    /// The tasks reading each TCP connection, so they can be dropped.
+    readers: Arc<std::sync::Mutex<Vec<JoinHandle<()>>>>,
+    _tasks: Tasks,
+}
+
+impl Collector {
+    fn spawn(url: String, task: impl FnOnce(UnboundedSender<String>) -> JoinHandle<()>) -> Self {
+        let (sender, received) = mpsc::unbounded_channel();
+        Self {
+            url,
+            received,
+            connections: Arc::default(),
+            readers: Arc::default(),
+            _tasks: Tasks(vec![task(sender)]),
+        }
+    }
+
+    /// The next message, other than the probes `connect` sends, if one
+    /// arrives within `wait`.
+    pub async fn recv_within(&mut self, wait: Duration) -> Option<String> {
+        tokio::time::timeout(wait, async {
+            loop {
+                let message = self.received.recv().await?;
+                if !is_probe(&message) {
+                    return Some(message);
+                }
+            }
+        })
+        .await
+        .ok()
+        .flatten()
+    }
+
+    /// The next `count` messages, failing the test if they don't arrive.
+    pub async fn expect(&mut self, count: usize) -> Vec<String> {
+        let mut messages = Vec::with_capacity(count);
+        while messages.len() < count {
+            match self.recv_within(STEP).await {
+                Some(message) => messages.push(message),
+                None => panic!(
+                    "{}: received {} of {count} messages",
+                    self.url,
+                    messages.len()
+                ),
+            }
+        }
+        messages
+    }
+
+    /// Close every connection the bridge has open to the peer, for TCP.
+    pub fn disconnect(&self) {
+        for reader in self.readers.lock().expect("readers").drain(..) {
+            reader.abort();
+        }
+    }
+
+    /// Reads the memory queue `name`.
+    #[must_use]
+    pub fn memory(name: &str) -> Self {
+        let queue = memory::queue(name);
+        Self::spawn(format!("memory://{name}"), |sender| {
+            tokio::spawn(async move {
+                loop {
+                    if sender.send(queue.recv().await.into()).is_err() {
+                        break;
+                    }
+                }
+            })
+        })
+    }
+
+    /// Binds a UDP port for the bridge to send datagrams to.
+    #[must_use]
+    pub fn udp() -> Self {
+        let socket = StdUdpSocket::bind("127.0.0.1:0").expect("binding UDP");
+        socket.set_nonblocking(true).expect("setting nonblocking");
+        let port = socket.local_addr().expect("local address").port();
+        Self::spawn(format!("udp://127.0.0.1:{port}"), |sender| {
+            tokio::spawn(async move {
+                let socket = UdpSocket::from_std(socket).expect("adopting socket");
+                let mut buffer = vec![0; 65536];
+                while let Ok(size) = socket.recv(&mut buffer).await {
+                    let message = String::from_utf8_lossy(&buffer[..size]);
+                    if sender
+                        .send(message.trim_end_matches('\n').to_string())
+                        .is_err()
+                    {
+                        break;
+                    }
+                }
+            })
+        })
+    }
+
+    /// Listens for the bridge to connect, and reads lines from every
+    /// connection.
+    #[must_use]
+    pub fn tcp() -> Self {
+        let listener = StdTcpListener::bind("127.0.0.1:0").expect("binding TCP");
+        Self::tcp_on(listener)
+    }
+
+    /// Like `tcp`, on `port`, e.g. one the bridge was already told to send
+    /// to before anything listened there.
+    #[must_use]
+    pub fn tcp_at(port: u16) -> Self {
+        let listener = StdTcpListener::bind(("127.0.0.1", port)).expect("binding TCP");
+        Self::tcp_on(listener)
+    }
+
+    fn tcp_on(listener: StdTcpListener) -> Self {
+        listener.set_nonblocking(true).expect("setting nonblocking");
+        let port = listener.local_addr().expect("local address").port();
+        let connections = Arc::new(AtomicUsize::new(0));
+        let readers: Arc<std::sync::Mutex<Vec<JoinHandle<()>>>> = Arc::default();
+        let counted = Arc::clone(&connections);
+        let tracked = Arc::clone(&readers);
+        let mut collector = Self::spawn(format!("tcp://127.0.0.1:{port}"), |sender| {
+            tokio::spawn(async move {
+                let listener = TcpListener::from_std(listener).expect("adopting listener");
+                while let Ok((stream, _)) = listener.accept().await {
+                    counted.fetch_add(1, Ordering::Relaxed);
+                    let sender = sender.clone();
+                    let reader = tokio::spawn(async move {
+                        let mut lines = FramedRead::new(stream, LinesCodec::new());
+                        while let Some(Ok(line)) = lines.next().await {
+                            if sender.send(line).is_err() {
+                                break;
+                            }
+                        }
+                    });
+                    tracked.lock().expect("readers").push(reader);
+                }
+            })
+        });
+        collector.connections = connections;
+        collector.readers = readers;
+        collector
+    }
+
+    /// Binds a SUB socket for a `zmq` output to connect to.
+    #[must_use]
+    pub fn zmq() -> Self {
+        let port = free_port();
+        let socket = tmq::subscribe(&tmq::Context::new())
+            .bind(&format!("tcp://127.0.0.1:{port}"))
+            .expect("binding SUB")
+            .subscribe(b"")
+            .expect("subscribing");
+        Self::spawn(format!("zmq://127.0.0.1:{port}"), |sender| {
+            tokio::spawn(receive_zmq(socket, sender))
+        })
+    }
+
+    /// Binds a PULL socket for a `zmq-push` output to connect to.
+    #[must_use]
+    pub fn zmq_pull() -> Self {
+        let port = free_port();
+        let socket = tmq::pull(&tmq::Context::new())
+            .bind(&format!("tcp://127.0.0.1:{port}"))
+            .expect("binding PULL");
+        Self::spawn(format!("zmq-push://127.0.0.1:{port}"), |sender| {
+            tokio::spawn(receive_zmq(socket, sender))
+        })
+    }
+}
+
+async fn receive_zmq<S>(mut socket: S, sender: UnboundedSender<String>)
+where
+    S: futures::Stream<Item = Result<tmq::Multipart, tmq::TmqError>> + Unpin,
+{
+    while let Some(Ok(multipart)) = socket.next().await {
+        let frames: Vec<String> = multipart
+            .iter()
+            .map(|frame| String::from_utf8_lossy(frame).into_owned())
+            .collect();
+        if sender.send(frames.join(" ")).is_err() {
+            break;
+        }
+    }
+}
+
+/// A webhook consumer for the HTTP output to post to. It answers each
+/// request with the next of the statuses it was given, then with `200`, and
+/// keeps every request body whatever it answered.
+pub struct Webhook {
+    /// Where the bridge output should post to.
+    pub url: String,
+    bodies: UnboundedReceiver<String>,
+    _tasks: Tasks,
+}
+
+impl Webhook {
+    #[must_use]
+    pub fn new(statuses: &[u16]) -> Self {
+        let listener = StdTcpListener::bind("127.0.0.1:0").expect("binding TCP");
+        listener.set_nonblocking(true).expect("setting nonblocking");
+        let port = listener.local_addr().expect("local address").port();
+        let statuses = Arc::new(Mutex::new(statuses.iter().copied().collect()));
+        let (sender, bodies) = mpsc::unbounded_channel();
+        let task = tokio::spawn(async move {
+            let listener = TcpListener::from_std(listener).expect("adopting listener");
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(answer(stream, Arc::clone(&statuses), sender.clone()));
+            }
+        });
+        Self {
+            url: format!("http://127.0.0.1:{port}/hook"),
+            bodies,
+            _tasks: Tasks(vec![task]),
+        }
+    }
+
+    /// The next request body, if one arrives within `wait`.
+    pub async fn recv_within(&mut self, wait: Duration) -> Option<String> {
+        tokio::time::timeout(wait, self.bodies.recv())
+            .await
+            .ok()
+            .flatten()
+    }
+
+    /// The next `count` request bodies, failing the test if they don't
+    /// arrive.
+    pub async fn expect(&mut self, count: usize) -> Vec<String> {
+        let mut bodies = Vec::with_capacity(count);
+        while bodies.len() < count {
+            match self.recv_within(STEP).await {
+                Some(body) => bodies.push(body),
+                None => panic!(
+                    "{}: received {} of {count} requests",
+                    self.url,
+                    bodies.len()
+                ),
+            }
+        }
+        bodies
+    }
+}
+
+/// Answer the requests on one keep-alive connection.
+async fn answer(
+    mut stream: TcpStream,
+    statuses: Arc<Mutex<VecDeque<u16>>>,
+    bodies: UnboundedSender<String>,
+) {
+    let mut buffer = Vec::new();
+    loop {
+        let (head, length) = loop {
+            let mut headers = [httparse::EMPTY_HEADER; 32];
+            let mut request = httparse::Request::new(&mut headers);
+            if let Ok(httparse::Status::Complete(head)) = request.parse(&buffer) {
+                let length = request
+                    .headers
+                    .iter()
+                    .find(|header| header.name.eq_ignore_ascii_case("content-length"))
+                    .and_then(|header| std::str::from_utf8(header.value).ok()?.parse().ok())
+                    .unwrap_or(0);
+                break (head, length);
+            }
+            if !read_more(&mut stream, &mut buffer).await {
+                return;
+            }
+        };
+        while buffer.len() < head + length {
+            if !read_more(&mut stream, &mut buffer).await {
+                return;
+            }
+        }
+        let body = String::from_utf8_lossy(&buffer[head..head + length]).into_owned();
+        buffer.drain(..head + length);
+        let _ = bodies.send(body);
+
+        let status = statuses
+            .lock()
+            .expect("statuses")
+            .pop_front()
+            .unwrap_or(200);
+        let response = format!("HTTP/1.1 {status} Status\r\ncontent-length: 0\r\n\r\n");
+        if stream.write_all(response.as_bytes()).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Read what the peer sent next onto `buffer`, or `false` once it is gone.
+async fn read_more(stream: &mut TcpStream, buffer: &mut Vec<u8>) -> bool {
+    let mut chunk = [0; 8192];
+    match stream.read(&mut chunk).await {
+        Ok(0) | Err(_) => false,
+        Ok(read) => {
+            buffer.extend_from_slice(&chunk[..read]);
+            true
+        }
+    }
+}
+
+/// Send probes from `feed` until one reaches `collector`, so both ends are
+/// connected through the bridge before a test counts anything. PUB/SUB
+/// drops messages until the subscriber has joined, and UDP until the
+/// bridge has bound its port.
+pub async fn connect(feed: &Feed, collector: &mut Collector) {
+    within("the bridge to connect", async {
+        for seq in 0.. {
+            feed.send(&probe(seq));
+            if let Ok(Some(message)) =
+                tokio::time::timeout(Duration::from_millis(200), collector.received.recv()).await
+            {
+                if is_probe(&message) {
+                    return;
+                }
+                panic!("{}: got {message} before any probe", collector.url);
+            }
+        }
+    })
+    .await;
+}
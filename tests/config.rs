@@ -0,0 +1,48 @@
+// Copyright (c) 2024-2026 Fred Clausen
+//
+// Licensed under the MIT license: https://opensource.org/licenses/MIT
+// Permission is granted to use, copy, modify, and redistribute the work.
+// Full license information available in the project LICENSE file.
+
+//! A config file overrides the environment, for a flag turned off as much
+//! as for one turned on, and the command line overrides both.
+
+mod common;
+
+use tokio::process::Command;
+
+use common::{logged, unique_name};
+
+/// The debug log of `check` run with `AB_HTTP_TLS` set to `env` in the
+/// environment, `file` in a config file, and the extra `args`.
+async fn http_tls_checked(env: &str, file: &str, args: &[&str]) -> std::process::Output {
+    let path = std::env::temp_dir().join(format!("{}.env", unique_name("acars-bridge-config")));
+    std::fs::write(&path, format!("AB_HTTP_TLS={file}\n")).expect("writing the config file");
+    let output = Command::new(env!("CARGO_BIN_EXE_acars-bridge"))
+        .env_clear()
+        .env("AB_HTTP_TLS", env)
+        .env("AB_LOG_LEVEL", "debug")
+        .arg("--config-file")
+        .arg(&path)
+        .args(["--source", "stdin:", "--destination-protocol", "stdout"])
+        .args(args)
+        .arg("check")
+        .output()
+        .await
+        .expect("running acars-bridge");
+    std::fs::remove_file(&path).expect("removing the config file");
+    assert!(output.status.success(), "check failed: {output:?}");
+    output
+}
+
+#[tokio::test]
+async fn a_flag_turned_off_in_the_file_stays_off_when_the_environment_turns_it_on() {
+    let output = http_tls_checked("true", "false", &[]).await;
+    let _ = logged(&output, "HTTP TLS: false");
+}
+
+#[tokio::test]
+async fn a_flag_on_the_command_line_beats_the_file_turning_it_off() {
+    let output = http_tls_checked("false", "false", &["--http-tls"]).await;
+    let _ = logged(&output, "HTTP TLS: true");
+}
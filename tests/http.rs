@@ -0,0 +1,112 @@
+// Copyright (c) 2024-2026 Fred Clausen
+//
+// Licensed under the MIT license: https://opensource.org/licenses/MIT
+// Permission is granted to use, copy, modify, and redistribute the work.
+// Full license information available in the project LICENSE file.
+
+//! The HTTP output posts to a local webhook: batches as JSON arrays,
+//! retrying a 5xx or 429, and dropping what any other 4xx rejects.
+
+mod common;
+
+use acars_bridge::Bridge;
+use serde_json::Value;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+
+use common::{Feed, Webhook, bridge_binary, logged, messages, unique_name, within};
+
+/// A bridge from a memory feed to `url`.
+fn bridge_to(feed: &Feed, url: String) -> Bridge {
+    Bridge::new().input(feed.url.clone()).output(url)
+}
+
+fn json(text: &str) -> Value {
+    serde_json::from_str(text).expect("valid JSON")
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn batches_are_posted_as_json_arrays() {
+    let feed = Feed::memory(&unique_name("in"));
+    let mut webhook = Webhook::new(&[]);
+    let bridge = bridge_to(
+        &feed,
+        format!("{}?batch-size=5&batch-timeout-ms=500", webhook.url),
+    )
+    .start()
+    .expect("starting the bridge");
+
+    let sent = messages(12);
+    for message in &sent {
+        feed.send(message);
+    }
+    let mut received = Vec::new();
+    let mut largest = 0;
+    while received.len() < sent.len() {
+        let body = webhook.expect(1).await.remove(0);
+        let Value::Array(batch) = json(&body) else {
+            panic!("not a JSON array: {body}");
+        };
+        assert!(
+            (1..=5).contains(&batch.len()),
+            "{} messages in a batch",
+            batch.len()
+        );
+        largest = largest.max(batch.len());
+        received.extend(batch);
+    }
+    assert_eq!(received, sent.iter().map(|m| json(m)).collect::<Vec<_>>());
+    assert_eq!(largest, 5);
+    within("the bridge to shut down", bridge.shutdown())
+        .await
+        .expect("shutting down");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn posts_are_retried_after_a_5xx_or_429() {
+    let feed = Feed::memory(&unique_name("in"));
+    let mut webhook = Webhook::new(&[503, 429]);
+    let bridge = bridge_to(&feed, webhook.url.clone())
+        .start()
+        .expect("starting the bridge");
+
+    let sent = messages(2);
+    feed.send(&sent[0]);
+    // Two failures, then the retry that succeeds.
+    assert_eq!(webhook.expect(3).await, vec![sent[0].clone(); 3]);
+    feed.send(&sent[1]);
+    assert_eq!(webhook.expect(1).await, vec![sent[1].clone()]);
+    within("the bridge to shut down", bridge.shutdown())
+        .await
+        .expect("shutting down");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn a_rejected_post_is_dropped_and_logged() {
+    let mut webhook = Webhook::new(&[400]);
+    let mut child = bridge_binary(&["--destination", &webhook.url]);
+    let sent = messages(2);
+    let mut stdin = child.stdin.take().expect("stdin");
+    for message in &sent {
+        stdin
+            .write_all(format!("{message}\n").as_bytes())
+            .await
+            .expect("writing stdin");
+    }
+    // stdin can end, and the drain begin, before the output has first run;
+    // it must still start and post both.
+    drop(stdin);
+
+    let output = within("acars-bridge to exit", child.wait_with_output())
+        .await
+        .expect("waiting for acars-bridge");
+    assert!(output.status.success(), "exited with {}", output.status);
+    // Each posted once: the rejected one isn't retried, and the next still
+    // goes out.
+    assert_eq!(webhook.expect(2).await, sent);
+    assert_eq!(webhook.recv_within(Duration::from_millis(200)).await, None);
+    assert!(
+        logged(&output, "Consumer rejected 1 message(s) with HTTP 400")
+            < logged(&output, "Clean exit")
+    );
+}
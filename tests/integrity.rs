@@ -0,0 +1,115 @@
+// Copyright (c) 2024-2026 Fred Clausen
+//
+// Licensed under the MIT license: https://opensource.org/licenses/MIT
+// Permission is granted to use, copy, modify, and redistribute the work.
+// Full license information available in the project LICENSE file.
+
+//! Every message arrives unchanged and in order, for every pair of input
+//! and output protocols.
+
+mod common;
+
+use acars_bridge::Bridge;
+
+use common::{Collector, Feed, connect, messages, unique_name, within};
+
+const COUNT: usize = 50;
+
+async fn bridge_between(feed: Feed, mut collector: Collector) {
+    let bridge = Bridge::new()
+        .input(feed.url.clone())
+        .output(collector.url.clone())
+        .start()
+        .expect("starting the bridge");
+    connect(&feed, &mut collector).await;
+
+    let sent = messages(COUNT);
+    for message in &sent {
+        feed.send(message);
+    }
+    let received = collector.expect(COUNT).await;
+    assert_eq!(received, sent, "{} -> {}", feed.url, collector.url);
+
+    within("the bridge to shut down", bridge.shutdown())
+        .await
+        .expect("shutting down");
+}
+
+macro_rules! pair {
+    ($name:ident, $feed:expr, $collector:expr) => {
+        #[tokio::test(flavor = "multi_thread")]
+        async fn $name() {
+            bridge_between($feed, $collector).await;
+        }
+    };
+}
+
+pair!(
+    memory_to_memory,
+    Feed::memory(&unique_name("in")),
+    Collector::memory(&unique_name("out"))
+);
+pair!(
+    memory_to_udp,
+    Feed::memory(&unique_name("in")),
+    Collector::udp()
+);
+pair!(
+    memory_to_tcp,
+    Feed::memory(&unique_name("in")),
+    Collector::tcp()
+);
+pair!(
+    memory_to_zmq,
+    Feed::memory(&unique_name("in")),
+    Collector::zmq()
+);
+pair!(
+    memory_to_zmq_push,
+    Feed::memory(&unique_name("in")),
+    Collector::zmq_pull()
+);
+
+pair!(
+    udp_to_memory,
+    Feed::udp(),
+    Collector::memory(&unique_name("out"))
+);
+pair!(udp_to_udp, Feed::udp(), Collector::udp());
+pair!(udp_to_tcp, Feed::udp(), Collector::tcp());
+pair!(udp_to_zmq, Feed::udp(), Collector::zmq());
+pair!(udp_to_zmq_push, Feed::udp(), Collector::zmq_pull());
+
+pair!(
+    tcp_to_memory,
+    Feed::tcp(),
+    Collector::memory(&unique_name("out"))
+);
+pair!(tcp_to_udp, Feed::tcp(), Collector::udp());
+pair!(tcp_to_tcp, Feed::tcp(), Collector::tcp());
+pair!(tcp_to_zmq, Feed::tcp(), Collector::zmq());
+pair!(tcp_to_zmq_push, Feed::tcp(), Collector::zmq_pull());
+
+pair!(
+    zmq_to_memory,
+    Feed::zmq(),
+    Collector::memory(&unique_name("out"))
+);
+pair!(zmq_to_udp, Feed::zmq(), Collector::udp());
+pair!(zmq_to_tcp, Feed::zmq(), Collector::tcp());
+pair!(zmq_to_zmq, Feed::zmq(), Collector::zmq());
+pair!(zmq_to_zmq_push, Feed::zmq(), Collector::zmq_pull());
+
+pair!(
+    zmq_pull_to_memory,
+    Feed::zmq_push(),
+    Collector::memory(&unique_name("out"))
+);
+pair!(zmq_pull_to_udp, Feed::zmq_push(), Collector::udp());
+pair!(zmq_pull_to_tcp, Feed::zmq_push(), Collector::tcp());
+pair!(zmq_pull_to_zmq, Feed::zmq_push(), Collector::zmq());
+pair!(
+    zmq_pull_to_zmq_push,
+    Feed::zmq_push(),
+    Collector::zmq_pull()
+);
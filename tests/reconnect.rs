@@ -0,0 +1,149 @@
+// Copyright (c) 2024-2026 Fred Clausen
+//
+// Licensed under the MIT license: https://opensource.org/licenses/MIT
+// Permission is granted to use, copy, modify, and redistribute the work.
+// Full license information available in the project LICENSE file.
+
+//! The bridge gets back to a peer that went away, on either side, and
+//! carries on where it left off.
+
+mod common;
+
+use acars_bridge::backoff::BackoffPolicy;
+use acars_bridge::status::SupervisorState;
+use acars_bridge::{Bridge, TransportOptions};
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use common::{Collector, Feed, connect, free_port, messages, unique_name, within};
+
+/// Retry quickly, so the tests don't sit through the default backoff.
+fn quick_retries() -> TransportOptions {
+    let policy = BackoffPolicy::parse("initial=0.05,max=0.2").expect("backoff policy");
+    let mut options = TransportOptions::default();
+    options.source_backoff = Some(policy.clone());
+    options.destination_backoff = Some(policy);
+    options
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn tcp_output_reconnects_after_the_peer_drops_it() {
+    let feed = Feed::memory(&unique_name("in"));
+    let mut collector = Collector::tcp();
+    let bridge = Bridge::new()
+        .input(feed.url.clone())
+        .output(collector.url.clone())
+        .options(quick_retries())
+        .start()
+        .expect("starting the bridge");
+    connect(&feed, &mut collector).await;
+
+    // A write or two into the closed connection can be lost before the
+    // bridge notices, so probe until one gets through again.
+    collector.disconnect();
+    connect(&feed, &mut collector).await;
+    assert!(collector.connections.load(Ordering::Relaxed) >= 2);
+
+    let sent = messages(20);
+    for message in &sent {
+        feed.send(message);
+    }
+    assert_eq!(collector.expect(sent.len()).await, sent);
+    within("the bridge to shut down", bridge.shutdown())
+        .await
+        .expect("shutting down");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn tcp_input_reconnects_after_the_peer_drops_it() {
+    let feed = Feed::tcp();
+    let mut collector = Collector::memory(&unique_name("out"));
+    let bridge = Bridge::new()
+        .input(feed.url.clone())
+        .output(collector.url.clone())
+        .options(quick_retries())
+        .start()
+        .expect("starting the bridge");
+    connect(&feed, &mut collector).await;
+
+    feed.disconnect();
+    connect(&feed, &mut collector).await;
+    assert!(feed.connections.load(Ordering::Relaxed) >= 2);
+
+    let sent = messages(20);
+    for message in &sent {
+        feed.send(message);
+    }
+    assert_eq!(collector.expect(sent.len()).await, sent);
+    within("the bridge to shut down", bridge.shutdown())
+        .await
+        .expect("shutting down");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn output_delivers_what_queued_while_its_peer_was_down() {
+    let port = free_port();
+    let feed = Feed::memory(&unique_name("in"));
+    let bridge = Bridge::new()
+        .input(feed.url.clone())
+        .output(format!("tcp://127.0.0.1:{port}"))
+        .options(quick_retries())
+        .start()
+        .expect("starting the bridge");
+
+    // Nothing listens yet, so the output keeps retrying its first connect
+    // while the messages wait in the bridge channel.
+    let sent = messages(20);
+    for message in &sent {
+        feed.send(message);
+    }
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert_ne!(
+        bridge.output_status()[0].borrow().state,
+        SupervisorState::Running,
+        "the output should not be running without a peer"
+    );
+
+    let mut collector = Collector::tcp_at(port);
+    assert_eq!(collector.expect(sent.len()).await, sent);
+    within("the bridge to shut down", bridge.shutdown())
+        .await
+        .expect("shutting down");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn zmq_input_resubscribes_after_the_publisher_restarts() {
+    let mut collector = Collector::memory(&unique_name("out"));
+    let feed = Feed::zmq();
+    let url = feed.url.clone();
+    let bridge = Bridge::new()
+        .input(url.clone())
+        .output(collector.url.clone())
+        .options(quick_retries())
+        .start()
+        .expect("starting the bridge");
+    connect(&feed, &mut collector).await;
+
+    // A new publisher on the same port, as after a decoder restart.
+    let port = url.rsplit(':').next().expect("port").to_string();
+    drop(feed);
+    let feed = within("the port to be released", async {
+        loop {
+            if let Some(feed) = Feed::zmq_at(port.parse().expect("port")) {
+                return feed;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    })
+    .await;
+    connect(&feed, &mut collector).await;
+
+    let sent = messages(20);
+    for message in &sent {
+        feed.send(message);
+    }
+    assert_eq!(collector.expect(sent.len()).await, sent);
+    within("the bridge to shut down", bridge.shutdown())
+        .await
+        .expect("shutting down");
+}
@@ -0,0 +1,91 @@
+// Copyright (c) 2024-2026 Fred Clausen
+//
+// Licensed under the MIT license: https://opensource.org/licenses/MIT
+// Permission is granted to use, copy, modify, and redistribute the work.
+// Full license information available in the project LICENSE file.
+
+//! A protocol registered by the embedding program is selected by its name in
+//! an endpoint URL, and keeps its settings in `TransportOptions` beside the
+//! built-in ones.
+
+mod common;
+
+use acars_bridge::status::StatusReporter;
+use acars_bridge::{Bridge, Message, Transport, TransportOptions, memory, transport};
+use anyhow::Result;
+use async_trait::async_trait;
+use tokio::sync::mpsc::Receiver;
+
+use common::{unique_name, within};
+
+/// The settings of `tagged`: what it puts before each message.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Tag(String);
+
+/// Passes each message on to the memory queue named by its host, tagged.
+struct TaggedTransport;
+
+#[async_trait]
+impl Transport for TaggedTransport {
+    fn name(&self) -> &'static str {
+        "tagged"
+    }
+
+    fn needs_port(&self) -> bool {
+        false
+    }
+
+    fn supports_output(&self) -> bool {
+        true
+    }
+
+    fn configure(&self, name: &str, value: &str, options: &mut TransportOptions) -> Result<()> {
+        match name {
+            "tag" => options.get_mut::<Tag>().0 = value.to_string(),
+            _ => return Err(acars_bridge::endpoint::unknown_setting(self.name(), name)),
+        }
+        Ok(())
+    }
+
+    async fn run_output(
+        &self,
+        host: &str,
+        _port: u16,
+        receiver: &mut Receiver<Message>,
+        options: &TransportOptions,
+        _status: &StatusReporter,
+    ) -> Result<()> {
+        let queue = memory::queue(host);
+        let tag = options.get::<Tag>().into_owned();
+        while let Some(message) = receiver.recv().await {
+            queue.send(format!("{}{}", tag.0, &*message)).await;
+        }
+        Ok(())
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn a_registered_transport_runs_with_its_own_options() {
+    transport::register(&TaggedTransport);
+    let input_name = unique_name("in");
+    let (plain_name, tagged_name) = (unique_name("plain"), unique_name("tagged"));
+    let input = memory::queue(&input_name);
+    let (plain, tagged) = (memory::queue(&plain_name), memory::queue(&tagged_name));
+
+    let bridge = Bridge::new()
+        .input(format!("memory://{input_name}"))
+        .output(format!("tagged://{plain_name}"))
+        .output(format!("tagged://{tagged_name}?tag=b:"))
+        .options(TransportOptions::default().with(Tag("a:".to_string())))
+        .start()
+        .expect("starting the bridge");
+
+    input.send(r#"{"n":1}"#).await;
+    within("the messages to arrive", async {
+        assert_eq!(&*plain.recv().await, r#"a:{"n":1}"#);
+        assert_eq!(&*tagged.recv().await, r#"b:{"n":1}"#);
+    })
+    .await;
+
+    bridge.shutdown().await.expect("shutting down");
+}
@@ -0,0 +1,202 @@
+// Copyright (c) 2024-2026 Fred Clausen
+//
+// Licensed under the MIT license: https://opensource.org/licenses/MIT
+// Permission is granted to use, copy, modify, and redistribute the work.
+// Full license information available in the project LICENSE file.
+
+//! Shutdown stops the inputs first, then lets the outputs deliver what is
+//! queued, within the drain timeout. The binary's own drain, in `main`, is
+//! run as a child process, including that a `stdin` input only starts it at
+//! the real end of input, not at a line that isn't UTF-8.
+
+mod common;
+
+use acars_bridge::{Bridge, memory};
+use std::time::{Duration, Instant};
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use common::{Collector, bridge_binary, free_port, logged, messages, unique_name, within};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn shutdown_delivers_what_is_queued() {
+    let (input_name, output_name) = (unique_name("in"), unique_name("out"));
+    let input = memory::queue(&input_name);
+    // Too small for what is sent, so most of it is still in the bridge when
+    // the shutdown starts.
+    let output = memory::create(&output_name, 1);
+    let bridge = Bridge::new()
+        .input(format!("memory://{input_name}"))
+        .output(format!("memory://{output_name}"))
+        .start()
+        .expect("starting the bridge");
+
+    let sent = messages(100);
+    for message in &sent {
+        input.send(message.clone()).await;
+    }
+    within("the input to take everything", async {
+        while !input.is_empty() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await;
+
+    let stopping = tokio::spawn(bridge.shutdown());
+    let mut received = Vec::with_capacity(sent.len());
+    while received.len() < sent.len() {
+        received.push(within("the next message", output.recv()).await);
+    }
+    assert_eq!(received, sent);
+    within("the bridge to shut down", stopping)
+        .await
+        .expect("joining")
+        .expect("shutting down");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn drain_timeout_stops_a_stuck_output() {
+    let (input_name, output_name) = (unique_name("in"), unique_name("out"));
+    let input = memory::queue(&input_name);
+    // Never read, so the output can't finish draining.
+    let _output = memory::create(&output_name, 1);
+    let bridge = Bridge::new()
+        .input(format!("memory://{input_name}"))
+        .output(format!("memory://{output_name}"))
+        .drain_timeout(Some(Duration::from_millis(500)))
+        .start()
+        .expect("starting the bridge");
+    for message in messages(10) {
+        input.send(message).await;
+    }
+
+    let started = Instant::now();
+    within("the bridge to shut down", bridge.shutdown())
+        .await
+        .expect("shutting down");
+    assert!(started.elapsed() < Duration::from_secs(3));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn binary_drains_to_the_destination_at_end_of_input() {
+    let mut collector = Collector::tcp();
+    let mut child = bridge_binary(&["--destination", &collector.url]);
+    let sent = messages(100);
+    let mut stdin = child.stdin.take().expect("stdin");
+    for message in &sent {
+        stdin
+            .write_all(format!("{message}\n").as_bytes())
+            .await
+            .expect("writing stdin");
+    }
+    drop(stdin);
+
+    let output = within("acars-bridge to exit", child.wait_with_output())
+        .await
+        .expect("waiting for acars-bridge");
+    assert!(output.status.success(), "exited with {}", output.status);
+    assert_eq!(collector.expect(sent.len()).await, sent);
+
+    // The input stops before the output is left to drain, and the output
+    // finishes before the clean exit.
+    let input_stopped = logged(&output, "Cancelling input supervisor");
+    let output_drained = logged(&output, "Input channel closed (shutdown)");
+    let clean_exit = logged(&output, "Clean exit");
+    assert!(input_stopped < output_drained && output_drained < clean_exit);
+}
+
+#[cfg(unix)]
+#[tokio::test(flavor = "multi_thread")]
+async fn binary_spills_what_it_could_not_deliver_on_sigterm() {
+    let spill = std::env::temp_dir().join(format!(
+        "acars-bridge-{}-{}.jsonl",
+        std::process::id(),
+        unique_name("spill")
+    ));
+    // Nothing listens at the destination, so the output never starts.
+    let destination = format!("tcp://127.0.0.1:{}", free_port());
+    let mut child = bridge_binary(&[
+        "--destination",
+        &destination,
+        "--spill-file",
+        spill.to_str().expect("spill path"),
+        "--drain-timeout",
+        "2",
+    ]);
+    let sent = messages(100);
+    let mut stdin = child.stdin.take().expect("stdin");
+    for message in &sent {
+        stdin
+            .write_all(format!("{message}\n").as_bytes())
+            .await
+            .expect("writing stdin");
+    }
+    stdin.flush().await.expect("flushing stdin");
+    // Give the input time to read everything into the bridge channel.
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    let pid = child.id().expect("pid").to_string();
+    let killed = Command::new("kill")
+        .args(["-TERM", &pid])
+        .status()
+        .await
+        .expect("running kill");
+    assert!(killed.success());
+    let output = within("acars-bridge to exit", child.wait_with_output())
+        .await
+        .expect("waiting for acars-bridge");
+    assert!(output.status.success(), "exited with {}", output.status);
+
+    let spilled = std::fs::read_to_string(&spill).expect("reading the spill file");
+    let _ = std::fs::remove_file(&spill);
+    assert_eq!(spilled.lines().collect::<Vec<_>>(), sent);
+    assert!(logged(&output, "Received SIGTERM") < logged(&output, "undelivered message(s)"));
+}
+
+#[cfg(unix)]
+#[tokio::test(flavor = "multi_thread")]
+async fn one_signal_during_a_drain_at_end_of_input_does_not_cut_it_short() {
+    // Nothing listens at the destination, so the drain lasts until its
+    // timeout.
+    let destination = format!("tcp://127.0.0.1:{}", free_port());
+    let mut child = bridge_binary(&["--destination", &destination, "--drain-timeout", "2"]);
+    let mut stdin = child.stdin.take().expect("stdin");
+    stdin.write_all(b"one\n").await.expect("writing stdin");
+    drop(stdin);
+    // Give the drain time to begin.
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let pid = child.id().expect("pid").to_string();
+    let killed = Command::new("kill")
+        .args(["-TERM", &pid])
+        .status()
+        .await
+        .expect("running kill");
+    assert!(killed.success());
+    let output = within("acars-bridge to exit", child.wait_with_output())
+        .await
+        .expect("waiting for acars-bridge");
+    assert!(output.status.success(), "exited with {}", output.status);
+    assert!(logged(&output, "Already draining") < logged(&output, "undelivered message(s)"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn a_line_that_is_not_utf8_does_not_end_the_input() {
+    let mut child = bridge_binary(&["--destination-protocol", "stdout"]);
+    let mut stdin = child.stdin.take().expect("stdin");
+    stdin
+        .write_all(b"one\n\xff\xfe\ntwo\nthree\n")
+        .await
+        .expect("writing stdin");
+    drop(stdin);
+
+    let output = within("acars-bridge to exit", child.wait_with_output())
+        .await
+        .expect("waiting for acars-bridge");
+    assert!(output.status.success(), "exited with {}", output.status);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "one\n\u{fffd}\u{fffd}\ntwo\nthree\n"
+    );
+    assert!(logged(&output, "Non-UTF-8 line") < logged(&output, "Clean exit"));
+}